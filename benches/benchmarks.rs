@@ -121,7 +121,7 @@ mod rcprf_benches {
                     b.iter(|| {
                         rcprf
                             .eval_range(
-                                &RcPrfRange::from(0..size as u64),
+                                &RcPrfRange::from(0..size),
                                 &mut slice[..size as usize],
                             )
                             .unwrap();
@@ -155,7 +155,7 @@ mod rcprf_benches {
                     b.iter(|| {
                         rcprf
                             .par_eval_range(
-                                &RcPrfRange::from(0..size as u64),
+                                &RcPrfRange::from(0..size),
                                 &mut slice[..size as usize],
                             )
                             .unwrap();
@@ -183,10 +183,7 @@ mod rcprf_benches {
                 |b, &size| {
                     b.iter(|| {
                         let _: Vec<Vec<u8>> = rcprf
-                            .value_range_iter(
-                                &RcPrfRange::from(0..size as u64),
-                                16,
-                            )
+                            .value_range_iter(&RcPrfRange::from(0..size), 16)
                             .unwrap()
                             .map(|(_, v)| v)
                             .collect();
@@ -4,19 +4,23 @@ use chacha20poly1305::aead::{AeadInPlace, KeyInit};
 use chacha20poly1305::{ChaCha20Poly1305, Nonce, Tag};
 
 // use clear_on_drop::clear_stack_on_return;
-use rand::RngCore;
-use zeroize::Zeroize;
+use rand::{CryptoRng, RngCore};
+use zeroize::{Zeroize, Zeroizing};
 
 // use std::vec::Vec;
 
 use crate::insecure_clone::{private::InsecureClone, CryptographyClone};
+use crate::prf::REKEY_CONTEXT;
 use crate::serialization::cleartext_serialization::{
     DeserializableCleartextContent, SerializableCleartextContent,
+    SERIALIZATION_FORMAT_VERSION,
 };
 use crate::serialization::errors::CleartextContentDeserializationError;
 use crate::EncryptionError;
 use crate::{DecryptionError, KeyDerivationPrf};
-use crate::{Key256, KeyAccessor};
+use crate::{Key, Key256, KeyAccessor};
+
+use std::sync::atomic::{AtomicU64, Ordering};
 
 /// Authenticated encryption & decryption
 ///
@@ -41,138 +45,931 @@ use crate::{Key256, KeyAccessor};
 /// This approach has been thoroughly described by Gueron and Bellare, with examples of real-world application in [their CCS'17 paper](https://eprint.iacr.org/2017/702.pdf).
 /// We refer to this document for the full proof of security of this
 /// construction.
+///
+/// ## Direct mode
+/// For interoperability with systems using Chacha20+Poly1305 directly, a cipher
+/// can be built in the `AeadMode::Direct` mode using `AeadCipherBuilder`. In
+/// that mode, the main key is used as the Chacha20+Poly1305 key, and the
+/// ciphertext starts with a random 96 bits nonce. No key derivation happens,
+/// so the aforementioned limitation on the number of encrypted messages
+/// applies.
+///
+/// ## Message budget
+/// A cipher built with `AeadCipher::with_budget` (or with the `budget`
+/// function of `AeadCipherBuilder`) counts the messages it encrypts, and
+/// refuses to encrypt more than the given number of messages. This allows to
+/// enforce a limit on the number of messages encrypted with a single key (e.g.
+/// in the `AeadMode::Direct` mode), and to rotate the key (with `rekey`) once
+/// it is reached.
+///
+/// The budget is serialized along with the key: a deserialized cipher resumes
+/// with the budget that remained when it was serialized. Hence, restoring an
+/// older serialization of a cipher also restores a larger budget.
 
 #[derive(Zeroize)]
 #[zeroize(drop)]
 pub struct AeadCipher {
-    key_derivation_prf: KeyDerivationPrf<Key256>,
+    key: AeadCipherKey,
+    #[zeroize(skip)]
+    budget: Option<MessageBudget>,
+}
+
+/// Number of messages encrypted by a cipher, out of a maximum
+struct MessageBudget {
+    max_messages: u64,
+    encrypted_messages: AtomicU64,
+}
+
+impl MessageBudget {
+    fn new(max_messages: u64) -> Self {
+        MessageBudget {
+            max_messages,
+            encrypted_messages: AtomicU64::new(0),
+        }
+    }
+
+    /// Count a new message, or return an error if the budget is exhausted
+    fn consume(&self) -> Result<(), EncryptionError> {
+        self.consume_many(1)
+    }
+
+    /// Count `count` new messages at once, or return an error (and count
+    /// none of them) if the remaining budget is smaller than `count`
+    fn consume_many(&self, count: u64) -> Result<(), EncryptionError> {
+        match self.encrypted_messages.fetch_update(
+            Ordering::SeqCst,
+            Ordering::SeqCst,
+            |n| n.checked_add(count).filter(|&m| m <= self.max_messages),
+        ) {
+            Ok(_) => Ok(()),
+            Err(_) => Err(EncryptionError::BudgetExhausted {
+                max_messages: self.max_messages,
+            }),
+        }
+    }
+
+    fn remaining(&self) -> u64 {
+        self.max_messages - self.encrypted_messages.load(Ordering::SeqCst)
+    }
+
+    /// Size of the serialization of an optional budget
+    fn serialization_byte_size(budget: Option<&Self>) -> usize {
+        1 + budget.map_or(0, |_| 2 * std::mem::size_of::<u64>())
+    }
+
+    /// Serialize an optional budget: a presence byte, followed (if any) by the
+    /// maximum and the current number of messages
+    fn serialize(
+        budget: Option<&Self>,
+        writer: &mut dyn std::io::Write,
+    ) -> Result<(), std::io::Error> {
+        match budget {
+            None => writer.write_all(&[0]),
+            Some(b) => {
+                writer.write_all(&[1])?;
+                writer.write_all(&b.max_messages.to_le_bytes())?;
+                writer.write_all(
+                    &b.encrypted_messages.load(Ordering::SeqCst).to_le_bytes(),
+                )
+            }
+        }
+    }
+
+    /// Deserialize an optional budget written by `serialize`
+    fn deserialize(
+        reader: &mut dyn std::io::Read,
+    ) -> Result<Option<Self>, CleartextContentDeserializationError> {
+        let mut presence = [0u8; 1];
+        reader.read_exact(&mut presence)?;
+        match presence[0] {
+            0 => Ok(None),
+            1 => {
+                let mut buf = [0u8; 8];
+                reader.read_exact(&mut buf)?;
+                let max_messages = u64::from_le_bytes(buf);
+                reader.read_exact(&mut buf)?;
+                let encrypted_messages = u64::from_le_bytes(buf);
+
+                if encrypted_messages > max_messages {
+                    return Err(
+                        CleartextContentDeserializationError::ContentError(
+                            format!("Invalid AEAD cipher budget: {encrypted_messages} messages out of {max_messages}"),
+                        ),
+                    );
+                }
+                Ok(Some(MessageBudget {
+                    max_messages,
+                    encrypted_messages: AtomicU64::new(encrypted_messages),
+                }))
+            }
+            x => Err(CleartextContentDeserializationError::ContentError(
+                format!("Invalid AEAD cipher budget presence byte ({x})"),
+            )),
+        }
+    }
+}
+
+/// Mode of operation of an `AeadCipher`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AeadMode {
+    /// A 128 bits nonce is used to derive a per-message key from the main
+    /// key (default mode)
+    DerivedKey,
+    /// The main key is directly used as the Chacha20+Poly1305 key, with a 96
+    /// bits nonce
+    Direct,
+}
+
+impl AeadMode {
+    fn serialization_byte(self) -> u8 {
+        match self {
+            AeadMode::DerivedKey => 0,
+            AeadMode::Direct => 1,
+        }
+    }
+}
+
+/// Key material of an `AeadCipher`, depending on its mode
+enum AeadCipherKey {
+    Derived(KeyDerivationPrf<Key256>),
+    Direct(Key256),
+}
+
+impl Zeroize for AeadCipherKey {
+    fn zeroize(&mut self) {
+        match self {
+            AeadCipherKey::Derived(prf) => prf.zeroize(),
+            AeadCipherKey::Direct(key) => key.zeroize(),
+        }
+    }
+}
+
+impl InsecureClone for AeadCipherKey {
+    fn insecure_clone(&self) -> Self {
+        match self {
+            AeadCipherKey::Derived(prf) => {
+                AeadCipherKey::Derived(prf.insecure_clone())
+            }
+            AeadCipherKey::Direct(key) => {
+                AeadCipherKey::Direct(key.insecure_clone())
+            }
+        }
+    }
 }
 
 impl InsecureClone for AeadCipher {
     fn insecure_clone(&self) -> Self {
         AeadCipher {
-            key_derivation_prf: self.key_derivation_prf.insecure_clone(),
+            key: self.key.insecure_clone(),
+            budget: self.budget.as_ref().map(|b| MessageBudget {
+                max_messages: b.max_messages,
+                encrypted_messages: AtomicU64::new(
+                    b.encrypted_messages.load(Ordering::SeqCst),
+                ),
+            }),
         }
     }
 }
 impl CryptographyClone for AeadCipher {}
 
+/// Builder for `AeadCipher`, used to select the mode of the cipher and its
+/// message budget
+///
+/// The authentication tag is always `AeadCipher::TAG_LENGTH` bytes long, in
+/// every mode: truncated Poly1305 tags are not supported.
+///
+/// # Example
+/// ```
+/// # extern crate crypto_tk_rs;
+/// use crypto_tk_rs::{AeadCipherBuilder, AeadMode, Key, Key256};
+///
+/// let cipher = AeadCipherBuilder::new()
+///     .mode(AeadMode::Direct)
+///     .budget(1000)
+///     .build(Key256::new());
+/// assert_eq!(cipher.mode(), AeadMode::Direct);
+/// assert_eq!(cipher.remaining_budget(), Some(1000));
+/// ```
+#[derive(Clone, Copy, Debug)]
+pub struct AeadCipherBuilder {
+    mode: AeadMode,
+    budget: Option<u64>,
+}
+
+impl Default for AeadCipherBuilder {
+    fn default() -> Self {
+        AeadCipherBuilder {
+            mode: AeadMode::DerivedKey,
+            budget: None,
+        }
+    }
+}
+
+impl AeadCipherBuilder {
+    /// Returns a new builder, for a cipher in the default
+    /// `AeadMode::DerivedKey` mode
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the mode of the cipher
+    #[must_use]
+    pub fn mode(mut self, mode: AeadMode) -> Self {
+        self.mode = mode;
+        self
+    }
+
+    /// Limit the number of messages the cipher can encrypt to
+    /// `max_messages` (see `AeadCipher::with_budget`). By default, the number
+    /// of messages is not limited.
+    #[must_use]
+    pub fn budget(mut self, max_messages: u64) -> Self {
+        self.budget = Some(max_messages);
+        self
+    }
+
+    /// Build the cipher from a 256 bits key
+    #[must_use]
+    pub fn build(self, key: Key256) -> AeadCipher {
+        let key =
+            match self.mode {
+                AeadMode::DerivedKey => AeadCipherKey::Derived(
+                    KeyDerivationPrf::<Key256>::from_key(key),
+                ),
+                AeadMode::Direct => AeadCipherKey::Direct(key),
+            };
+        AeadCipher {
+            key,
+            budget: self.budget.map(MessageBudget::new),
+        }
+    }
+}
+
+/// A nonce of an `AeadCipher` in the default `AeadMode::DerivedKey` mode, as
+/// returned by `AeadCipher::encrypt_detached`
+pub type Nonce16 = [u8; AeadCipher::NONCE_SIZE];
+
+/// A nonce of an `AeadCipher` in the `AeadMode::Direct` mode, as returned by
+/// `AeadCipher::encrypt_detached_direct`
+pub type Nonce12 = [u8; AeadCipher::DIRECT_NONCE_SIZE];
+
 impl AeadCipher {
-    /// Size of a nonce, in bytes
+    /// Size of a nonce, in bytes, in the default `AeadMode::DerivedKey` mode
     pub const NONCE_SIZE: usize = 16;
 
+    /// Size of a nonce, in bytes, in the `AeadMode::Direct` mode
+    pub const DIRECT_NONCE_SIZE: usize = 12;
+
     /// Size of the authentication tag, in bytes
     pub const TAG_LENGTH: usize = 16;
 
     const CHACHA20_NONCE_LENGTH: usize = 12;
 
+    /// Maximum length of a plaintext, in bytes (about 256 GiB), one byte below
+    /// the `P_MAX` limit of RFC 8439. Chacha20 uses a 32 bits block counter,
+    /// and the first 64 bytes block of the keystream is used for the Poly1305
+    /// key. The `chacha20poly1305` backend rejects plaintexts of `P_MAX` bytes
+    /// or more, so they must be rejected before any budget is consumed.
+    pub const MAX_PLAINTEXT_LEN: u64 = (u32::MAX as u64) * 64 - 1;
+
+    /// Returns an error if a plaintext of `len` bytes is too long to be
+    /// encrypted
+    fn check_plaintext_length(len: u64) -> Result<(), EncryptionError> {
+        if len > AeadCipher::MAX_PLAINTEXT_LEN {
+            return Err(EncryptionError::PlaintextTooLong {
+                plaintext_length: len,
+                max_length: AeadCipher::MAX_PLAINTEXT_LEN,
+            });
+        }
+        Ok(())
+    }
+
     /// The ciphertext expansion, i.e. the number of additional bytes due to the
-    /// encryption
+    /// encryption, in the default `AeadMode::DerivedKey` mode. Ciphers in the
+    /// `AeadMode::Direct` mode have a smaller expansion,
+    /// `DIRECT_CIPHERTEXT_EXPANSION`: use `ciphertext_expansion()` when the
+    /// mode of the cipher is not known.
     pub const CIPHERTEXT_EXPANSION: usize =
         AeadCipher::NONCE_SIZE + AeadCipher::TAG_LENGTH;
 
+    /// The ciphertext expansion in the `AeadMode::Direct` mode
+    pub const DIRECT_CIPHERTEXT_EXPANSION: usize =
+        AeadCipher::DIRECT_NONCE_SIZE + AeadCipher::TAG_LENGTH;
+
     /// Construct a cipher from a 256 bits key
     #[must_use]
     pub fn from_key(key: Key256) -> AeadCipher {
-        AeadCipher {
-            key_derivation_prf: KeyDerivationPrf::<Key256>::from_key(key),
+        AeadCipherBuilder::new().build(key)
+    }
+
+    /// Construct a cipher from a new key generated with the `csprng` random
+    /// generator, e.g. for reproducible tests or custom entropy sources
+    pub fn from_rng<R>(csprng: &mut R) -> AeadCipher
+    where
+        R: CryptoRng + RngCore,
+    {
+        AeadCipher::from_key(Key256::generate(csprng))
+    }
+
+    /// Construct a cipher from a 256 bits key, that encrypts at most
+    /// `max_messages` messages. Once the budget is exhausted, the encryption
+    /// functions return a `BudgetExhausted` error.
+    /// A clone of the cipher starts with the remaining budget of the original
+    /// cipher, independently of it, and so does a deserialized cipher.
+    /// Ciphers in other modes can be given a budget with
+    /// `AeadCipherBuilder::budget`.
+    ///
+    /// # Example
+    /// ```
+    /// # extern crate crypto_tk_rs;
+    /// use crypto_tk_rs::{AeadCipher, EncryptionError, Key, Key256};
+    ///
+    /// let cipher = AeadCipher::with_budget(Key256::new(), 1);
+    /// let mut ciphertext = [0u8; 5 + AeadCipher::CIPHERTEXT_EXPANSION];
+    ///
+    /// cipher.encrypt(b"Hello", &mut ciphertext).unwrap();
+    /// assert!(matches!(
+    ///     cipher.encrypt(b"Hello", &mut ciphertext),
+    ///     Err(EncryptionError::BudgetExhausted { max_messages: 1 })
+    /// ));
+    /// ```
+    #[must_use]
+    pub fn with_budget(key: Key256, max_messages: u64) -> AeadCipher {
+        AeadCipherBuilder::new().budget(max_messages).build(key)
+    }
+
+    /// Returns the number of messages the cipher can still encrypt, or `None`
+    /// if the cipher has no message budget.
+    #[must_use]
+    pub fn remaining_budget(&self) -> Option<u64> {
+        self.budget.as_ref().map(MessageBudget::remaining)
+    }
+
+    /// Returns a new cipher with the same mode, whose key is
+    /// deterministically derived from the key of `self`. This allows to rotate
+    /// the key after a large number of encryptions without ever exposing the
+    /// key material. Ciphertexts produced by `self` cannot be decrypted by the
+    /// returned cipher. If `self` has a message budget, the returned cipher
+    /// has a fresh budget of the same size.
+    ///
+    /// In the `AeadMode::Direct` mode, the single key of the cipher is used
+    /// both as the ChaCha20-Poly1305 key and as the key of the Blake2b based
+    /// `KeyDerivationPrf` deriving the new key. This reuse of a key across two
+    /// different primitives is not covered by their security proofs: prefer
+    /// the `AeadMode::DerivedKey` mode when the cipher has to be rekeyed.
+    #[must_use]
+    pub fn rekey(&self) -> AeadCipher {
+        let new_key: Key256 = match &self.key {
+            AeadCipherKey::Derived(prf) => prf.derive_key(REKEY_CONTEXT),
+            // the AEAD key is also used as a key derivation key (see above)
+            AeadCipherKey::Direct(key) => {
+                KeyDerivationPrf::<Key256>::from_key(key.insecure_clone())
+                    .derive_key(REKEY_CONTEXT)
+            }
+        };
+        let mut cipher =
+            AeadCipherBuilder::new().mode(self.mode()).build(new_key);
+        cipher.budget = self
+            .budget
+            .as_ref()
+            .map(|b| MessageBudget::new(b.max_messages));
+        cipher
+    }
+
+    /// Returns the mode of the cipher
+    #[must_use]
+    pub fn mode(&self) -> AeadMode {
+        match self.key {
+            AeadCipherKey::Derived(_) => AeadMode::DerivedKey,
+            AeadCipherKey::Direct(_) => AeadMode::Direct,
+        }
+    }
+
+    /// Size of the nonces used by the cipher, in bytes
+    #[must_use]
+    pub fn nonce_size(&self) -> usize {
+        match self.mode() {
+            AeadMode::DerivedKey => AeadCipher::NONCE_SIZE,
+            AeadMode::Direct => AeadCipher::DIRECT_NONCE_SIZE,
+        }
+    }
+
+    /// The ciphertext expansion of the cipher, i.e. the number of additional
+    /// bytes due to the encryption
+    #[must_use]
+    pub fn ciphertext_expansion(&self) -> usize {
+        self.nonce_size() + AeadCipher::TAG_LENGTH
+    }
+
+    /// Returns `true` if `len` is a possible length for a ciphertext produced
+    /// by this cipher, i.e. if it is at least `ciphertext_expansion()` bytes.
+    #[must_use]
+    pub fn is_valid_ciphertext_length(&self, len: usize) -> bool {
+        len >= self.ciphertext_expansion()
+    }
+
+    /// Returns the nonce of a ciphertext produced by a cipher in the default
+    /// `AeadMode::DerivedKey` mode, i.e. its first `NONCE_SIZE` bytes, without
+    /// decrypting it. Returns an error if the ciphertext is shorter than
+    /// `CIPHERTEXT_EXPANSION`. Use `extract_direct_nonce` for ciphertexts of
+    /// the `AeadMode::Direct` mode.
+    ///
+    /// # Example
+    /// ```
+    /// # extern crate crypto_tk_rs;
+    /// use crypto_tk_rs::{AeadCipher, Key, Key256};
+    ///
+    /// let cipher = AeadCipher::from_key(Key256::new());
+    /// let mut ciphertext = [0u8; 5 + AeadCipher::CIPHERTEXT_EXPANSION];
+    /// cipher.encrypt(b"Hello", &mut ciphertext).unwrap();
+    ///
+    /// let nonce = AeadCipher::extract_nonce(&ciphertext).unwrap();
+    /// assert_eq!(nonce[..], ciphertext[..AeadCipher::NONCE_SIZE]);
+    /// ```
+    pub fn extract_nonce(
+        ciphertext: &[u8],
+    ) -> Result<Nonce16, DecryptionError> {
+        AeadCipher::extract_nonce_array(ciphertext)
+    }
+
+    /// Returns the nonce of a ciphertext produced by a cipher in the
+    /// `AeadMode::Direct` mode, i.e. its first `DIRECT_NONCE_SIZE` bytes,
+    /// without decrypting it. Returns an error if the ciphertext is shorter
+    /// than `DIRECT_CIPHERTEXT_EXPANSION`.
+    pub fn extract_direct_nonce(
+        ciphertext: &[u8],
+    ) -> Result<Nonce12, DecryptionError> {
+        AeadCipher::extract_nonce_array(ciphertext)
+    }
+
+    /// Returns the first `N` bytes of a ciphertext, the nonce of a cipher
+    /// using `N` bytes nonces
+    fn extract_nonce_array<const N: usize>(
+        ciphertext: &[u8],
+    ) -> Result<[u8; N], DecryptionError> {
+        let ciphertext_expansion = N + AeadCipher::TAG_LENGTH;
+        if ciphertext.len() < ciphertext_expansion {
+            return Err(DecryptionError::CiphertextLengthError {
+                ciphertext_length: ciphertext.len(),
+                ciphertext_expansion,
+            });
+        }
+        let mut nonce = [0u8; N];
+        nonce.copy_from_slice(&ciphertext[..N]);
+        Ok(nonce)
+    }
+
+    /// Returns an error if `nonce` does not have the size of the nonces of
+    /// the cipher
+    fn check_nonce_length(&self, nonce: &[u8]) -> Result<(), DecryptionError> {
+        if nonce.len() != self.nonce_size() {
+            return Err(DecryptionError::NonceLengthError {
+                expected: self.nonce_size(),
+                found: nonce.len(),
+            });
+        }
+        Ok(())
+    }
+
+    /// Returns the Chacha20+Poly1305 instance used to encrypt or decrypt with
+    /// the nonce `iv`
+    fn inner_cipher(&self, iv: &[u8]) -> ChaCha20Poly1305 {
+        match &self.key {
+            AeadCipherKey::Derived(prf) => {
+                let encryption_key = prf.derive_key(iv);
+                ChaCha20Poly1305::new(chacha20poly1305::Key::from_slice(
+                    encryption_key.content(),
+                ))
+            }
+            AeadCipherKey::Direct(key) => ChaCha20Poly1305::new(
+                chacha20poly1305::Key::from_slice(key.content()),
+            ),
         }
     }
 
     /// Encrypt a byte slice and write the result of the encryption in
-    /// `ciphertext`. Returns an error if the `ciphertext` slice cannot
-    /// contain the result, i.e. if it is not at least `CIPHERTEXT_EXPANSION`
-    /// bytes longer than `plaintext`.
+    /// `ciphertext`. Returns the number of bytes written to `ciphertext`, or
+    /// an error if the `ciphertext` slice cannot contain the result, i.e. if
+    /// it is not at least `ciphertext_expansion()` bytes longer than
+    /// `plaintext`.
     pub fn encrypt(
         &self,
         plaintext: &[u8],
         ciphertext: &mut [u8],
-    ) -> Result<(), EncryptionError> {
-        if ciphertext.len() < plaintext.len() + AeadCipher::CIPHERTEXT_EXPANSION
-        {
+    ) -> Result<usize, EncryptionError> {
+        self.encrypt_with_ad(plaintext, b"", ciphertext)
+    }
+
+    /// Encrypt a byte slice, authenticating the associated data `ad` along
+    /// with it, and write the result of the encryption in `ciphertext`. The
+    /// associated data is not part of the ciphertext, and the exact same `ad`
+    /// must be given back for the decryption to succeed.
+    /// Returns the number of bytes written to `ciphertext`, or an error if the
+    /// `ciphertext` slice cannot contain the result, i.e. if it is not at
+    /// least `ciphertext_expansion()` bytes longer than `plaintext`, or if
+    /// `plaintext` is longer than `MAX_PLAINTEXT_LEN` bytes.
+    pub fn encrypt_with_ad(
+        &self,
+        plaintext: &[u8],
+        ad: &[u8],
+        ciphertext: &mut [u8],
+    ) -> Result<usize, EncryptionError> {
+        self.encrypt_with_rng(
+            plaintext,
+            ad,
+            ciphertext,
+            &mut rand::thread_rng(),
+        )
+    }
+
+    /// Encrypt each of the `plaintexts`, and returns the ciphertexts in the
+    /// same order. All the nonces are drawn from `csprng`, which avoids
+    /// getting a handle on the thread's random generator for every message
+    /// when encrypting many small messages.
+    /// The nonces of the whole batch are drawn at once, and every ciphertext
+    /// is allocated once, with its exact size.
+    /// Returns an error if one of the plaintexts is longer than
+    /// `MAX_PLAINTEXT_LEN` bytes, or if the remaining message budget of the
+    /// cipher is smaller than the number of plaintexts. These conditions are
+    /// checked before encrypting anything, and the budget of the whole batch
+    /// is reserved at once: a failed call does not consume any budget.
+    ///
+    /// # Example
+    /// ```
+    /// # extern crate crypto_tk_rs;
+    /// use crypto_tk_rs::{AeadCipher, Key, Key256};
+    ///
+    /// let cipher = AeadCipher::from_key(Key256::new());
+    /// let plaintexts: [&[u8]; 2] = [b"Hello", b"World"];
+    /// let ciphertexts = cipher
+    ///     .encrypt_batch(&plaintexts, &mut rand::thread_rng())
+    ///     .unwrap();
+    ///
+    /// assert_eq!(cipher.decrypt_to_vec(&ciphertexts[1]).unwrap(), b"World");
+    /// ```
+    pub fn encrypt_batch<R>(
+        &self,
+        plaintexts: &[&[u8]],
+        csprng: &mut R,
+    ) -> Result<Vec<Vec<u8>>, EncryptionError>
+    where
+        R: CryptoRng + RngCore,
+    {
+        for plaintext in plaintexts {
+            AeadCipher::check_plaintext_length(plaintext.len() as u64)?;
+        }
+        if let Some(budget) = &self.budget {
+            budget.consume_many(plaintexts.len() as u64)?;
+        }
+
+        let nonce_size = self.nonce_size();
+        let mut nonces = vec![0u8; plaintexts.len() * nonce_size];
+        csprng.fill_bytes(&mut nonces);
+
+        plaintexts
+            .iter()
+            .zip(nonces.chunks_exact(nonce_size))
+            .map(|(plaintext, iv)| {
+                let mut ciphertext =
+                    vec![0u8; plaintext.len() + self.ciphertext_expansion()];
+                self.encrypt_with_nonce(iv, plaintext, b"", &mut ciphertext)?;
+                Ok(ciphertext)
+            })
+            .collect()
+    }
+
+    /// Encrypt a byte slice as `encrypt_with_ad` does, drawing the nonce from
+    /// `csprng`
+    fn encrypt_with_rng(
+        &self,
+        plaintext: &[u8],
+        ad: &[u8],
+        ciphertext: &mut [u8],
+        csprng: &mut dyn RngCore,
+    ) -> Result<usize, EncryptionError> {
+        let nonce_size = self.nonce_size();
+        AeadCipher::check_plaintext_length(plaintext.len() as u64)?;
+        if ciphertext.len() < plaintext.len() + self.ciphertext_expansion() {
             return Err(EncryptionError::CiphertextLengthError {
                 plaintext_length: plaintext.len(),
                 ciphertext_length: ciphertext.len(),
             });
         }
+        if let Some(budget) = &self.budget {
+            budget.consume()?;
+        }
 
-        let mut iv = [0u8; AeadCipher::NONCE_SIZE];
-        let mut rng = rand::thread_rng();
-        rng.fill_bytes(&mut iv);
+        let mut iv_buf = [0u8; AeadCipher::NONCE_SIZE];
+        let iv = &mut iv_buf[..nonce_size];
+        csprng.fill_bytes(iv);
+
+        self.encrypt_with_nonce(iv, plaintext, ad, ciphertext)
+    }
+
+    /// Encrypt a byte slice with the given nonce, once the lengths and the
+    /// budget have been checked by the caller
+    fn encrypt_with_nonce(
+        &self,
+        iv: &[u8],
+        plaintext: &[u8],
+        ad: &[u8],
+        ciphertext: &mut [u8],
+    ) -> Result<usize, EncryptionError> {
+        let nonce_size = iv.len();
 
         // write the nonce at the beginning of the ciphertext
-        ciphertext[..AeadCipher::NONCE_SIZE].copy_from_slice(&iv);
+        ciphertext[..nonce_size].copy_from_slice(iv);
 
         // copy the plaintext
-        ciphertext[AeadCipher::NONCE_SIZE
-            ..(AeadCipher::NONCE_SIZE + plaintext.len())]
+        ciphertext[nonce_size..(nonce_size + plaintext.len())]
             .copy_from_slice(plaintext);
 
-        let encryption_key = self.key_derivation_prf.derive_key(&iv);
-        let chacha_key =
-            chacha20poly1305::Key::from_slice(encryption_key.content());
-        let cipher = ChaCha20Poly1305::new(chacha_key);
+        let cipher = self.inner_cipher(iv);
 
         let inner_nonce =
             Nonce::from_slice(&iv[..AeadCipher::CHACHA20_NONCE_LENGTH]);
 
         let tag = cipher.encrypt_in_place_detached(
             inner_nonce,
-            b"",
-            &mut ciphertext[AeadCipher::NONCE_SIZE
-                ..(AeadCipher::NONCE_SIZE + plaintext.len())],
+            ad,
+            &mut ciphertext[nonce_size..(nonce_size + plaintext.len())],
         )?;
 
-        ciphertext[(AeadCipher::NONCE_SIZE + plaintext.len())
-            ..(AeadCipher::NONCE_SIZE
-                + plaintext.len()
-                + AeadCipher::TAG_LENGTH)]
+        ciphertext[(nonce_size + plaintext.len())
+            ..(nonce_size + plaintext.len() + AeadCipher::TAG_LENGTH)]
             .copy_from_slice(&tag);
-        Ok(())
+        Ok(plaintext.len() + self.ciphertext_expansion())
     }
 
     /// Decrypt a byte slice and write the result of the decryption in
     /// `plaintext`. Returns an error if the `plaintext` slice cannot
-    /// contain the result, i.e. if it is not at least `CIPHERTEXT_EXPANSION`
-    /// bytes smaller than `ciphertext`. Also returns an error if
-    /// `ciphertext`'s length is smaller than `CIPHERTEXT_EXPANSION` bytes
+    /// contain the result, i.e. if it is not at least
+    /// `ciphertext_expansion()` bytes smaller than `ciphertext`. Also returns
+    /// an error if `ciphertext`'s length is smaller than
+    /// `ciphertext_expansion()` bytes
     pub fn decrypt(
         &self,
         ciphertext: &[u8],
         plaintext: &mut [u8],
+    ) -> Result<(), DecryptionError> {
+        self.decrypt_with_ad(ciphertext, b"", plaintext)
+    }
+
+    /// Decrypt a byte slice produced by `encrypt_with_ad`, check the
+    /// associated data `ad`, and write the result of the decryption in
+    /// `plaintext`. Returns the same errors as `decrypt`, and an error if `ad`
+    /// is not the associated data used during the encryption.
+    pub fn decrypt_with_ad(
+        &self,
+        ciphertext: &[u8],
+        ad: &[u8],
+        plaintext: &mut [u8],
     ) -> Result<(), DecryptionError> {
         let l = ciphertext.len();
-        if l < AeadCipher::CIPHERTEXT_EXPANSION {
-            return Err(DecryptionError::CiphertextLengthError(l));
+        let nonce_size = self.nonce_size();
+        if l < self.ciphertext_expansion() {
+            return Err(DecryptionError::CiphertextLengthError {
+                ciphertext_length: l,
+                ciphertext_expansion: self.ciphertext_expansion(),
+            });
         }
 
-        if l > plaintext.len() + AeadCipher::CIPHERTEXT_EXPANSION {
+        if l > plaintext.len() + self.ciphertext_expansion() {
             return Err(DecryptionError::PlaintextLengthError {
                 plaintext_length: plaintext.len(),
                 ciphertext_length: l,
             });
         }
 
-        let real_plaintext_length = l - AeadCipher::CIPHERTEXT_EXPANSION;
-        let iv = &ciphertext[0..AeadCipher::NONCE_SIZE];
+        let iv = &ciphertext[0..nonce_size];
         let tag = Tag::from_slice(&ciphertext[l - AeadCipher::TAG_LENGTH..]);
 
+        self.decrypt_body(
+            iv,
+            &ciphertext[nonce_size..l - AeadCipher::TAG_LENGTH],
+            tag,
+            ad,
+            plaintext,
+        )
+    }
+
+    /// Decrypt a ciphertext whose nonce and tag are stored separately from
+    /// its `body` (the encrypted bytes), and write the result of the
+    /// decryption in `plaintext`. Returns an error if the `plaintext` slice is
+    /// smaller than `body`.
+    ///
+    /// The nonce, body and tag of a ciphertext produced by `encrypt` are
+    /// respectively its first `NONCE_SIZE` bytes, the following
+    /// `ciphertext.len() - CIPHERTEXT_EXPANSION` bytes, and its last
+    /// `TAG_LENGTH` bytes.
+    ///
+    /// Only ciphers in the `AeadMode::DerivedKey` mode use 16 bytes nonces:
+    /// for other modes, a `NonceLengthError` is returned (see
+    /// `decrypt_with_direct_nonce` for the `AeadMode::Direct` mode).
+    pub fn decrypt_with_nonce(
+        &self,
+        nonce: &Nonce16,
+        body: &[u8],
+        tag: &[u8; AeadCipher::TAG_LENGTH],
+        plaintext: &mut [u8],
+    ) -> Result<(), DecryptionError> {
+        self.decrypt_with_nonce_slice(nonce, body, tag, plaintext)
+    }
+
+    /// Same as `decrypt_with_nonce`, for ciphers in the `AeadMode::Direct`
+    /// mode, using `DIRECT_NONCE_SIZE` bytes nonces. For other modes, a
+    /// `NonceLengthError` is returned.
+    pub fn decrypt_with_direct_nonce(
+        &self,
+        nonce: &Nonce12,
+        body: &[u8],
+        tag: &[u8; AeadCipher::TAG_LENGTH],
+        plaintext: &mut [u8],
+    ) -> Result<(), DecryptionError> {
+        self.decrypt_with_nonce_slice(nonce, body, tag, plaintext)
+    }
+
+    /// Implementation of `decrypt_with_nonce` and `decrypt_with_direct_nonce`
+    fn decrypt_with_nonce_slice(
+        &self,
+        nonce: &[u8],
+        body: &[u8],
+        tag: &[u8; AeadCipher::TAG_LENGTH],
+        plaintext: &mut [u8],
+    ) -> Result<(), DecryptionError> {
+        self.check_nonce_length(nonce)?;
+
+        if body.len() > plaintext.len() {
+            return Err(DecryptionError::PlaintextLengthError {
+                plaintext_length: plaintext.len(),
+                ciphertext_length: body.len() + self.ciphertext_expansion(),
+            });
+        }
+
+        self.decrypt_body(nonce, body, Tag::from_slice(tag), b"", plaintext)
+    }
+
+    /// Encrypt a byte slice, and returns the nonce separately from the rest of
+    /// the ciphertext (the encrypted bytes followed by the authentication
+    /// tag), e.g. for storage schemes keeping the nonce in a separate field.
+    /// The result can be decrypted with `decrypt_detached`.
+    ///
+    /// Only ciphers in the `AeadMode::DerivedKey` mode use 16 bytes nonces:
+    /// for other modes, a `NonceLengthError` is returned (see
+    /// `encrypt_detached_direct` for the `AeadMode::Direct` mode). Also
+    /// returns the same errors as `encrypt`.
+    ///
+    /// # Example
+    /// ```
+    /// # extern crate crypto_tk_rs;
+    /// use crypto_tk_rs::{AeadCipher, Key, Key256};
+    ///
+    /// let cipher = AeadCipher::from_key(Key256::new());
+    /// let (nonce, ciphertext) = cipher.encrypt_detached(b"Hello").unwrap();
+    /// assert_eq!(ciphertext.len(), 5 + AeadCipher::TAG_LENGTH);
+    ///
+    /// let plaintext = cipher.decrypt_detached(&nonce, &ciphertext).unwrap();
+    /// assert_eq!(plaintext, b"Hello");
+    /// ```
+    pub fn encrypt_detached(
+        &self,
+        plaintext: &[u8],
+    ) -> Result<(Nonce16, Vec<u8>), EncryptionError> {
+        self.encrypt_detached_array(plaintext)
+    }
+
+    /// Same as `encrypt_detached`, for ciphers in the `AeadMode::Direct` mode,
+    /// using `DIRECT_NONCE_SIZE` bytes nonces. For other modes, a
+    /// `NonceLengthError` is returned. The result can be decrypted with
+    /// `decrypt_detached_direct`.
+    ///
+    /// # Example
+    /// ```
+    /// # extern crate crypto_tk_rs;
+    /// use crypto_tk_rs::{AeadCipherBuilder, AeadMode, Key, Key256};
+    ///
+    /// let cipher = AeadCipherBuilder::new()
+    ///     .mode(AeadMode::Direct)
+    ///     .build(Key256::new());
+    /// let (nonce, ciphertext) =
+    ///     cipher.encrypt_detached_direct(b"Hello").unwrap();
+    ///
+    /// let plaintext =
+    ///     cipher.decrypt_detached_direct(&nonce, &ciphertext).unwrap();
+    /// assert_eq!(plaintext, b"Hello");
+    /// ```
+    pub fn encrypt_detached_direct(
+        &self,
+        plaintext: &[u8],
+    ) -> Result<(Nonce12, Vec<u8>), EncryptionError> {
+        self.encrypt_detached_array(plaintext)
+    }
+
+    /// Implementation of `encrypt_detached` and `encrypt_detached_direct`,
+    /// for ciphers using `N` bytes nonces
+    fn encrypt_detached_array<const N: usize>(
+        &self,
+        plaintext: &[u8],
+    ) -> Result<([u8; N], Vec<u8>), EncryptionError> {
+        if self.nonce_size() != N {
+            return Err(EncryptionError::NonceLengthError {
+                expected: self.nonce_size(),
+                found: N,
+            });
+        }
+
+        let mut ciphertext =
+            vec![0u8; plaintext.len() + self.ciphertext_expansion()];
+        self.encrypt(plaintext, &mut ciphertext)?;
+
+        let mut nonce = [0u8; N];
+        nonce.copy_from_slice(&ciphertext[..N]);
+        ciphertext.drain(..N);
+
+        Ok((nonce, ciphertext))
+    }
+
+    /// Decrypt a ciphertext produced by `encrypt_detached`, i.e. the
+    /// encrypted bytes followed by the authentication tag, with the nonce
+    /// `nonce`, and returns the result of the decryption as a vector of bytes.
+    /// Returns an error if `ciphertext` is shorter than `TAG_LENGTH`, or if
+    /// the decryption fails.
+    ///
+    /// Only ciphers in the `AeadMode::DerivedKey` mode use 16 bytes nonces:
+    /// for other modes, a `NonceLengthError` is returned (see
+    /// `decrypt_detached_direct` for the `AeadMode::Direct` mode).
+    pub fn decrypt_detached(
+        &self,
+        nonce: &Nonce16,
+        ciphertext: &[u8],
+    ) -> Result<Vec<u8>, DecryptionError> {
+        self.decrypt_detached_slice(nonce, ciphertext)
+    }
+
+    /// Same as `decrypt_detached`, for ciphertexts produced by
+    /// `encrypt_detached_direct` by ciphers in the `AeadMode::Direct` mode.
+    /// For other modes, a `NonceLengthError` is returned.
+    pub fn decrypt_detached_direct(
+        &self,
+        nonce: &Nonce12,
+        ciphertext: &[u8],
+    ) -> Result<Vec<u8>, DecryptionError> {
+        self.decrypt_detached_slice(nonce, ciphertext)
+    }
+
+    /// Implementation of `decrypt_detached` and `decrypt_detached_direct`
+    fn decrypt_detached_slice(
+        &self,
+        nonce: &[u8],
+        ciphertext: &[u8],
+    ) -> Result<Vec<u8>, DecryptionError> {
+        self.check_nonce_length(nonce)?;
+        if ciphertext.len() < AeadCipher::TAG_LENGTH {
+            return Err(DecryptionError::CiphertextLengthError {
+                ciphertext_length: ciphertext.len(),
+                ciphertext_expansion: AeadCipher::TAG_LENGTH,
+            });
+        }
+
+        let (body, tag) =
+            ciphertext.split_at(ciphertext.len() - AeadCipher::TAG_LENGTH);
+        let mut plaintext = vec![0u8; body.len()];
+        self.decrypt_body(
+            nonce,
+            body,
+            Tag::from_slice(tag),
+            b"",
+            &mut plaintext,
+        )?;
+
+        Ok(plaintext)
+    }
+
+    /// Decrypt `body` with the nonce `iv` and check the
+    /// authentication `tag` against `body` and `ad`. `plaintext` must be at
+    /// least as long as `body`.
+    fn decrypt_body(
+        &self,
+        iv: &[u8],
+        body: &[u8],
+        tag: &Tag,
+        ad: &[u8],
+        plaintext: &mut [u8],
+    ) -> Result<(), DecryptionError> {
+        let real_plaintext_length = body.len();
+
         // copy the ciphertext
-        plaintext[..real_plaintext_length].copy_from_slice(
-            &ciphertext[AeadCipher::NONCE_SIZE..l - AeadCipher::TAG_LENGTH],
-        );
+        plaintext[..real_plaintext_length].copy_from_slice(body);
 
-        let encryption_key = self.key_derivation_prf.derive_key(iv);
-        let chacha_key =
-            chacha20poly1305::Key::from_slice(encryption_key.content());
-        let cipher = ChaCha20Poly1305::new(chacha_key);
+        let cipher = self.inner_cipher(iv);
 
         let inner_nonce =
             Nonce::from_slice(&iv[..AeadCipher::CHACHA20_NONCE_LENGTH]);
 
         cipher.decrypt_in_place_detached(
             inner_nonce,
-            b"",
+            ad,
             &mut plaintext[..real_plaintext_length],
             tag,
         )?;
@@ -182,60 +979,206 @@ impl AeadCipher {
 
     /// Decrypt a byte slice and returns the result of the decryption as a
     /// vector of byte. Returns an error if `ciphertext`'s length is smaller
-    /// than `CIPHERTEXT_EXPANSION` bytes
+    /// than `ciphertext_expansion()` bytes
     pub fn decrypt_to_vec(
         &self,
         ciphertext: &[u8],
+    ) -> Result<Vec<u8>, DecryptionError> {
+        self.decrypt_to_vec_with_ad(ciphertext, b"")
+    }
+
+    /// Decrypt a byte slice produced by `encrypt_with_ad`, check the
+    /// associated data `ad`, and returns the result of the decryption as a
+    /// vector of byte. Returns the same errors as `decrypt_with_ad`.
+    pub fn decrypt_to_vec_with_ad(
+        &self,
+        ciphertext: &[u8],
+        ad: &[u8],
     ) -> Result<Vec<u8>, DecryptionError> {
         let l = ciphertext.len();
-        if l < AeadCipher::CIPHERTEXT_EXPANSION {
-            return Err(DecryptionError::CiphertextLengthError(l));
+        if l < self.ciphertext_expansion() {
+            return Err(DecryptionError::CiphertextLengthError {
+                ciphertext_length: l,
+                ciphertext_expansion: self.ciphertext_expansion(),
+            });
         }
 
-        let pt_l = l - AeadCipher::CIPHERTEXT_EXPANSION;
+        let pt_l = l - self.ciphertext_expansion();
 
         let mut pt = vec![0u8; pt_l];
 
-        self.decrypt(ciphertext, &mut pt)?;
+        self.decrypt_with_ad(ciphertext, ad, &mut pt)?;
 
         Ok(pt)
     }
-}
 
-impl SerializableCleartextContent for AeadCipher {
-    fn serialization_content_byte_size(&self) -> usize {
-        self.key_derivation_prf.serialization_content_byte_size()
-    }
-    fn serialize_content(
+    /// Decrypt a byte slice and returns the result of the decryption as a
+    /// vector of bytes that is zeroized on drop. Returns the same errors as
+    /// `decrypt_to_vec`. In case of failure, the intermediate buffer is
+    /// cleared before returning.
+    pub fn decrypt_to_zeroizing(
         &self,
-        writer: &mut dyn std::io::Write,
-    ) -> Result<usize, std::io::Error> {
-        self.key_derivation_prf.serialize_content(writer)?;
+        ciphertext: &[u8],
+    ) -> Result<Zeroizing<Vec<u8>>, DecryptionError> {
+        let l = ciphertext.len();
+        if l < self.ciphertext_expansion() {
+            return Err(DecryptionError::CiphertextLengthError {
+                ciphertext_length: l,
+                ciphertext_expansion: self.ciphertext_expansion(),
+            });
+        }
 
-        Ok(self.serialization_content_byte_size())
+        let mut pt = Zeroizing::new(vec![0u8; l - self.ciphertext_expansion()]);
+        self.decrypt(ciphertext, &mut pt)?;
+
+        Ok(pt)
     }
-}
+
+    /// Decrypt a byte slice and returns the result of the decryption as a
+    /// vector of bytes that is zeroized on drop, or `None` if the decryption
+    /// failed for any reason (invalid length or authentication failure).
+    /// In case of failure, the intermediate buffer is cleared before
+    /// returning.
+    #[must_use]
+    pub fn try_decrypt_to_vec(
+        &self,
+        ciphertext: &[u8],
+    ) -> Option<Zeroizing<Vec<u8>>> {
+        let pt_l = ciphertext.len().checked_sub(self.ciphertext_expansion())?;
+
+        // the buffer is zeroized when dropped, including on failure
+        let mut pt = Zeroizing::new(vec![0u8; pt_l]);
+
+        self.decrypt(ciphertext, &mut pt).ok()?;
+
+        Some(pt)
+    }
+
+    /// Check that `ciphertext` is a valid ciphertext for the key of the
+    /// cipher, without returning the plaintext. Returns the same errors as
+    /// `decrypt`.
+    /// The ciphertext is decrypted in a scratch buffer, which is zeroized
+    /// before returning.
+    pub fn verify(&self, ciphertext: &[u8]) -> Result<(), DecryptionError> {
+        let l = ciphertext.len();
+        if l < self.ciphertext_expansion() {
+            return Err(DecryptionError::CiphertextLengthError {
+                ciphertext_length: l,
+                ciphertext_expansion: self.ciphertext_expansion(),
+            });
+        }
+
+        let mut scratch =
+            Zeroizing::new(vec![0u8; l - self.ciphertext_expansion()]);
+        self.decrypt(ciphertext, &mut scratch)
+    }
+}
+
+impl SerializableCleartextContent for AeadCipher {
+    fn serialization_content_byte_size(&self) -> usize {
+        1 + match &self.key {
+            AeadCipherKey::Derived(prf) => {
+                prf.serialization_content_byte_size()
+            }
+            AeadCipherKey::Direct(key) => key.serialization_content_byte_size(),
+        } + MessageBudget::serialization_byte_size(self.budget.as_ref())
+    }
+    fn serialize_content(
+        &self,
+        writer: &mut dyn std::io::Write,
+    ) -> Result<usize, std::io::Error> {
+        // the mode is written before the key material
+        writer.write_all(&[self.mode().serialization_byte()])?;
+
+        match &self.key {
+            AeadCipherKey::Derived(prf) => prf.serialize_content(writer)?,
+            AeadCipherKey::Direct(key) => key.serialize_content(writer)?,
+        };
+        // and the budget after it
+        MessageBudget::serialize(self.budget.as_ref(), writer)?;
+
+        Ok(self.serialization_content_byte_size())
+    }
+}
 
 impl DeserializableCleartextContent for AeadCipher {
     fn deserialize_content(
         reader: &mut dyn std::io::Read,
     ) -> Result<Self, CleartextContentDeserializationError> {
-        Ok(AeadCipher {
-            key_derivation_prf:
-                KeyDerivationPrf::<Key256>::deserialize_content(reader)?,
-        })
+        Self::deserialize_content_versioned(
+            reader,
+            SERIALIZATION_FORMAT_VERSION,
+        )
+    }
+
+    fn deserialize_content_versioned(
+        reader: &mut dyn std::io::Read,
+        version: u8,
+    ) -> Result<Self, CleartextContentDeserializationError> {
+        // before version 2, there was no mode nor budget, and only the key of
+        // the derived-key mode was serialized
+        if version < 2 {
+            return Ok(AeadCipher {
+                key: AeadCipherKey::Derived(
+                    KeyDerivationPrf::<Key256>::deserialize_content(reader)?,
+                ),
+                budget: None,
+            });
+        }
+
+        let mut mode = [0u8; 1];
+        reader.read_exact(&mut mode)?;
+
+        let key = match mode[0] {
+            x if x == AeadMode::DerivedKey.serialization_byte() => {
+                AeadCipherKey::Derived(
+                    KeyDerivationPrf::<Key256>::deserialize_content(reader)?,
+                )
+            }
+            x if x == AeadMode::Direct.serialization_byte() => {
+                AeadCipherKey::Direct(Key256::deserialize_content(reader)?)
+            }
+            x => {
+                return Err(CleartextContentDeserializationError::ContentError(
+                    format!("Invalid AEAD cipher mode ({x})"),
+                ))
+            }
+        };
+        let budget = MessageBudget::deserialize(reader)?;
+        Ok(AeadCipher { key, budget })
     }
 }
 
 #[cfg(test)]
 mod tests {
     #![allow(clippy::unwrap_used)]
+    #![allow(clippy::expect_used)]
     use crate::Key;
 
     use super::*;
 
     const TEST_PLAINTEXT: &[u8] = b"Test plaintext";
 
+    #[test]
+    fn seeded_generation() {
+        use rand::rngs::StdRng;
+        use rand::SeedableRng;
+
+        let plaintext = TEST_PLAINTEXT;
+        let cipher_1 =
+            AeadCipher::from_rng(&mut StdRng::seed_from_u64(0xdead_beef));
+        let cipher_2 =
+            AeadCipher::from_rng(&mut StdRng::seed_from_u64(0xdead_beef));
+
+        let mut ciphertext =
+            vec![0u8; plaintext.len() + AeadCipher::CIPHERTEXT_EXPANSION];
+        let mut dec_result = vec![0u8; plaintext.len()];
+        cipher_1.encrypt(plaintext, &mut ciphertext).unwrap();
+        cipher_2.decrypt(&ciphertext, &mut dec_result).unwrap();
+
+        assert_eq!(plaintext, &dec_result[..]);
+    }
+
     #[test]
     fn encryption_errors() {
         let plaintext = TEST_PLAINTEXT;
@@ -257,7 +1200,8 @@ mod tests {
 
         let k = Key256::new();
         let cipher = AeadCipher::from_key(k);
-        cipher.encrypt(plaintext, &mut ciphertext).unwrap();
+        let written_bytes = cipher.encrypt(plaintext, &mut ciphertext).unwrap();
+        assert_eq!(written_bytes, ciphertext.len());
 
         cipher.decrypt(&ciphertext, &mut dec_result).unwrap();
         let pt_vec = cipher.decrypt_to_vec(&ciphertext).unwrap();
@@ -266,6 +1210,49 @@ mod tests {
         assert_eq!(plaintext, &pt_vec[..]);
     }
 
+    #[test]
+    fn rekey() {
+        let plaintext = TEST_PLAINTEXT;
+
+        for mode in [AeadMode::DerivedKey, AeadMode::Direct] {
+            let cipher =
+                AeadCipherBuilder::new().mode(mode).build(Key256::new());
+            let mut ciphertext =
+                vec![0u8; plaintext.len() + cipher.ciphertext_expansion()];
+            cipher.encrypt(plaintext, &mut ciphertext).unwrap();
+
+            let rekeyed_cipher = cipher.rekey();
+            assert_eq!(rekeyed_cipher.mode(), mode);
+            rekeyed_cipher
+                .decrypt_to_vec(&ciphertext)
+                .expect_err("Decryption with the rekeyed cipher should fail");
+
+            // rekeying is deterministic
+            rekeyed_cipher.encrypt(plaintext, &mut ciphertext).unwrap();
+            let pt = cipher.rekey().decrypt_to_vec(&ciphertext).unwrap();
+            assert_eq!(plaintext, &pt[..]);
+            cipher
+                .decrypt_to_vec(&ciphertext)
+                .expect_err("Decryption with the original cipher should fail");
+        }
+    }
+
+    #[test]
+    fn encryption_length() {
+        let plaintext = TEST_PLAINTEXT;
+        // give more space than needed
+        let mut ciphertext =
+            vec![0u8; plaintext.len() + AeadCipher::CIPHERTEXT_EXPANSION + 10];
+
+        let k = Key256::new();
+        let cipher = AeadCipher::from_key(k);
+        let written_bytes = cipher.encrypt(plaintext, &mut ciphertext).unwrap();
+        assert_eq!(
+            written_bytes,
+            plaintext.len() + AeadCipher::CIPHERTEXT_EXPANSION
+        );
+    }
+
     fn ciphertext_integrity(tampered_byte_index: usize) {
         let plaintext = TEST_PLAINTEXT;
         let mut ciphertext =
@@ -298,6 +1285,611 @@ mod tests {
         ciphertext_integrity(AeadCipher::NONCE_SIZE + TEST_PLAINTEXT.len());
     }
 
+    #[test]
+    fn associated_data() {
+        let plaintext = TEST_PLAINTEXT;
+        let mut ciphertext =
+            vec![0u8; plaintext.len() + AeadCipher::CIPHERTEXT_EXPANSION];
+        let mut dec_result = vec![0u8; plaintext.len()];
+
+        let k = Key256::new();
+        let cipher = AeadCipher::from_key(k);
+        cipher
+            .encrypt_with_ad(plaintext, b"associated data", &mut ciphertext)
+            .unwrap();
+
+        cipher
+            .decrypt_with_ad(&ciphertext, b"associated data", &mut dec_result)
+            .unwrap();
+        assert_eq!(plaintext, &dec_result[..]);
+        assert_eq!(
+            plaintext,
+            &cipher
+                .decrypt_to_vec_with_ad(&ciphertext, b"associated data")
+                .unwrap()[..]
+        );
+
+        cipher
+            .decrypt_with_ad(&ciphertext, b"associated dat4", &mut dec_result)
+            .expect_err("Expected decryption error");
+        cipher
+            .decrypt(&ciphertext, &mut dec_result)
+            .expect_err("Expected decryption error");
+    }
+
+    #[test]
+    fn silent_decryption() {
+        let plaintext = TEST_PLAINTEXT;
+        let mut ciphertext =
+            vec![0u8; plaintext.len() + AeadCipher::CIPHERTEXT_EXPANSION];
+
+        let cipher = AeadCipher::from_key(Key256::new());
+        cipher.encrypt(plaintext, &mut ciphertext).unwrap();
+
+        let dec = cipher.try_decrypt_to_vec(&ciphertext).unwrap();
+        assert_eq!(plaintext, &dec[..]);
+
+        let mut tampered = ciphertext.clone();
+        tampered[AeadCipher::NONCE_SIZE] ^= 1;
+        assert!(cipher.try_decrypt_to_vec(&tampered).is_none());
+
+        assert!(cipher
+            .try_decrypt_to_vec(
+                &ciphertext[..AeadCipher::CIPHERTEXT_EXPANSION - 1]
+            )
+            .is_none());
+        assert!(cipher.try_decrypt_to_vec(&[]).is_none());
+    }
+
+    #[test]
+    fn verification() {
+        let plaintext = TEST_PLAINTEXT;
+
+        for mode in [AeadMode::DerivedKey, AeadMode::Direct] {
+            let cipher =
+                AeadCipherBuilder::new().mode(mode).build(Key256::new());
+            let mut ciphertext =
+                vec![0u8; plaintext.len() + cipher.ciphertext_expansion()];
+            cipher.encrypt(plaintext, &mut ciphertext).unwrap();
+            cipher.verify(&ciphertext).unwrap();
+
+            // tampering with any byte breaks the verification
+            for i in 0..ciphertext.len() {
+                let mut tampered = ciphertext.clone();
+                tampered[i] ^= 0x01;
+                cipher
+                    .verify(&tampered)
+                    .expect_err("Expected decryption error");
+            }
+
+            // as well as using a different key
+            AeadCipherBuilder::new()
+                .mode(mode)
+                .build(Key256::new())
+                .verify(&ciphertext)
+                .expect_err("Expected decryption error");
+
+            match cipher
+                .verify(&ciphertext[..cipher.ciphertext_expansion() - 1])
+                .unwrap_err()
+            {
+                DecryptionError::CiphertextLengthError { .. } => (),
+                _ => panic!("Invalid Error"),
+            }
+        }
+    }
+
+    #[test]
+    fn message_budget() {
+        let plaintext = TEST_PLAINTEXT;
+        let mut ciphertext =
+            vec![0u8; plaintext.len() + AeadCipher::CIPHERTEXT_EXPANSION];
+
+        let cipher = AeadCipher::with_budget(Key256::new(), 5);
+        assert_eq!(cipher.remaining_budget(), Some(5));
+
+        // failed encryptions do not count
+        cipher
+            .encrypt(plaintext, &mut [0u8; 3])
+            .expect_err("Expected invalid ciphertext length error");
+
+        for i in 0..5 {
+            cipher.encrypt(plaintext, &mut ciphertext).unwrap();
+            assert_eq!(cipher.remaining_budget(), Some(4 - i));
+        }
+        match cipher.encrypt(plaintext, &mut ciphertext).unwrap_err() {
+            EncryptionError::BudgetExhausted { max_messages: 5 } => (),
+            _ => panic!("Invalid Error"),
+        }
+        match cipher
+            .encrypt_with_ad(plaintext, b"ad", &mut ciphertext)
+            .unwrap_err()
+        {
+            EncryptionError::BudgetExhausted { .. } => (),
+            _ => panic!("Invalid Error"),
+        }
+
+        // decryption is not limited
+        let mut dec_result = vec![0u8; plaintext.len()];
+        cipher.decrypt(&ciphertext, &mut dec_result).unwrap();
+        assert_eq!(plaintext, &dec_result[..]);
+
+        // rekeying resets the budget
+        let rekeyed_cipher = cipher.rekey();
+        assert_eq!(rekeyed_cipher.remaining_budget(), Some(5));
+        rekeyed_cipher.encrypt(plaintext, &mut ciphertext).unwrap();
+
+        // clones keep the remaining budget
+        assert_eq!(rekeyed_cipher.insecure_clone().remaining_budget(), Some(4));
+
+        // no budget by default
+        let cipher = AeadCipher::from_key(Key256::new());
+        assert_eq!(cipher.remaining_budget(), None);
+        for _ in 0..10 {
+            cipher.encrypt(plaintext, &mut ciphertext).unwrap();
+        }
+    }
+
+    #[test]
+    fn zeroizing_decryption() {
+        let plaintext = TEST_PLAINTEXT;
+
+        for mode in [AeadMode::DerivedKey, AeadMode::Direct] {
+            let cipher =
+                AeadCipherBuilder::new().mode(mode).build(Key256::new());
+            let mut ciphertext =
+                vec![0u8; plaintext.len() + cipher.ciphertext_expansion()];
+            cipher.encrypt(plaintext, &mut ciphertext).unwrap();
+
+            let dec = cipher.decrypt_to_zeroizing(&ciphertext).unwrap();
+            assert_eq!(plaintext, &dec[..]);
+            assert_eq!(
+                &dec[..],
+                &cipher.decrypt_to_vec(&ciphertext).unwrap()[..]
+            );
+
+            let mut tampered = ciphertext.clone();
+            tampered[cipher.nonce_size()] ^= 1;
+            cipher
+                .decrypt_to_zeroizing(&tampered)
+                .expect_err("Expected decryption error");
+
+            match cipher.decrypt_to_zeroizing(&ciphertext[..3]).unwrap_err() {
+                DecryptionError::CiphertextLengthError { .. } => (),
+                _ => panic!("Invalid Error"),
+            }
+        }
+    }
+
+    #[test]
+    fn max_plaintext_length() {
+        // P_MAX - 1, P_MAX being specified in RFC 8439, section 2.8
+        assert_eq!(AeadCipher::MAX_PLAINTEXT_LEN, 274_877_906_879);
+        assert_eq!(AeadCipher::MAX_PLAINTEXT_LEN, (1u64 << 38) - 65);
+
+        AeadCipher::check_plaintext_length(0).unwrap();
+        AeadCipher::check_plaintext_length(AeadCipher::MAX_PLAINTEXT_LEN)
+            .unwrap();
+        // P_MAX itself is rejected, as the backend would fail on it
+        match AeadCipher::check_plaintext_length(
+            AeadCipher::MAX_PLAINTEXT_LEN + 1,
+        )
+        .unwrap_err()
+        {
+            EncryptionError::PlaintextTooLong {
+                plaintext_length,
+                max_length: AeadCipher::MAX_PLAINTEXT_LEN,
+            } => {
+                assert_eq!(plaintext_length, AeadCipher::MAX_PLAINTEXT_LEN + 1);
+            }
+            _ => panic!("Invalid Error"),
+        }
+        AeadCipher::check_plaintext_length(u64::MAX)
+            .expect_err("Expected plaintext length error");
+    }
+
+    #[test]
+    fn direct_mode() {
+        let plaintext = TEST_PLAINTEXT;
+        let mut buf = [0x11u8; 32];
+        let mut buf_dup = buf;
+
+        let cipher = AeadCipherBuilder::new()
+            .mode(AeadMode::Direct)
+            .build(Key256::from_bytes(&mut buf));
+        assert_eq!(cipher.mode(), AeadMode::Direct);
+        assert_eq!(cipher.nonce_size(), AeadCipher::DIRECT_NONCE_SIZE);
+        assert_eq!(
+            cipher.ciphertext_expansion(),
+            AeadCipher::DIRECT_NONCE_SIZE + AeadCipher::TAG_LENGTH
+        );
+
+        let mut ciphertext =
+            vec![0u8; plaintext.len() + cipher.ciphertext_expansion()];
+        let written_bytes = cipher.encrypt(plaintext, &mut ciphertext).unwrap();
+        assert_eq!(written_bytes, ciphertext.len());
+
+        assert_eq!(plaintext, &cipher.decrypt_to_vec(&ciphertext).unwrap()[..]);
+
+        // the ciphertext is a plain ChaCha20Poly1305 ciphertext, prefixed by
+        // its nonce
+        let mut key_bytes = [0x11u8; 32];
+        let reference = ChaCha20Poly1305::new(
+            chacha20poly1305::Key::from_slice(&key_bytes),
+        );
+        let mut body = ciphertext[AeadCipher::DIRECT_NONCE_SIZE
+            ..ciphertext.len() - AeadCipher::TAG_LENGTH]
+            .to_vec();
+        reference
+            .decrypt_in_place_detached(
+                Nonce::from_slice(&ciphertext[..AeadCipher::DIRECT_NONCE_SIZE]),
+                b"",
+                &mut body,
+                Tag::from_slice(
+                    &ciphertext[ciphertext.len() - AeadCipher::TAG_LENGTH..],
+                ),
+            )
+            .unwrap();
+        assert_eq!(plaintext, &body[..]);
+        key_bytes.zeroize();
+
+        // the same key in the default mode cannot decrypt the ciphertext
+        let derived_cipher =
+            AeadCipher::from_key(Key256::from_bytes(&mut buf_dup));
+        assert_eq!(derived_cipher.mode(), AeadMode::DerivedKey);
+        assert!(derived_cipher.decrypt_to_vec(&ciphertext).is_err());
+
+        // separate nonces have the wrong size for the direct mode
+        let mut dec_result = vec![0u8; plaintext.len()];
+        match cipher.decrypt_with_nonce(
+            &[0u8; AeadCipher::NONCE_SIZE],
+            &ciphertext[AeadCipher::DIRECT_NONCE_SIZE
+                ..plaintext.len() + AeadCipher::DIRECT_NONCE_SIZE],
+            &[0u8; AeadCipher::TAG_LENGTH],
+            &mut dec_result,
+        ) {
+            Err(DecryptionError::NonceLengthError { expected, found }) => {
+                assert_eq!(expected, AeadCipher::DIRECT_NONCE_SIZE);
+                assert_eq!(found, AeadCipher::NONCE_SIZE);
+            }
+            _ => panic!("Expected a nonce length error"),
+        }
+
+        assert!(cipher
+            .decrypt_to_vec(&ciphertext[..cipher.ciphertext_expansion() - 1])
+            .is_err());
+    }
+
+    #[test]
+    fn ciphertext_length_validation() {
+        let cipher = AeadCipher::from_key(Key256::new());
+        assert!(!cipher.is_valid_ciphertext_length(0));
+        assert!(!cipher.is_valid_ciphertext_length(31));
+        assert!(cipher.is_valid_ciphertext_length(32));
+        assert!(cipher.is_valid_ciphertext_length(100));
+
+        let direct_cipher = AeadCipherBuilder::new()
+            .mode(AeadMode::Direct)
+            .build(Key256::new());
+        assert!(!direct_cipher.is_valid_ciphertext_length(27));
+        assert!(direct_cipher.is_valid_ciphertext_length(28));
+
+        let err = cipher.decrypt_to_vec(&[0u8; 5]).unwrap_err();
+        assert!(err.to_string().contains("5 < 32"));
+        let err = direct_cipher.decrypt_to_vec(&[0u8; 5]).unwrap_err();
+        assert!(err.to_string().contains("5 < 28"));
+    }
+
+    /// Split a ciphertext into its nonce, body and tag
+    fn split_ciphertext(
+        ciphertext: &[u8],
+    ) -> (
+        [u8; AeadCipher::NONCE_SIZE],
+        Vec<u8>,
+        [u8; AeadCipher::TAG_LENGTH],
+    ) {
+        let l = ciphertext.len();
+        let mut nonce = [0u8; AeadCipher::NONCE_SIZE];
+        let mut tag = [0u8; AeadCipher::TAG_LENGTH];
+        nonce.copy_from_slice(&ciphertext[..AeadCipher::NONCE_SIZE]);
+        tag.copy_from_slice(&ciphertext[l - AeadCipher::TAG_LENGTH..]);
+        let body = ciphertext
+            [AeadCipher::NONCE_SIZE..l - AeadCipher::TAG_LENGTH]
+            .to_vec();
+
+        (nonce, body, tag)
+    }
+
+    #[test]
+    fn nonce_extraction() {
+        let plaintext = TEST_PLAINTEXT;
+        let cipher = AeadCipher::from_key(Key256::new());
+        let mut ciphertext =
+            vec![0u8; plaintext.len() + AeadCipher::CIPHERTEXT_EXPANSION];
+        cipher.encrypt(plaintext, &mut ciphertext).unwrap();
+
+        let nonce = AeadCipher::extract_nonce(&ciphertext).unwrap();
+        assert_eq!(nonce[..], ciphertext[..AeadCipher::NONCE_SIZE]);
+
+        // the nonce can be used to decrypt the rest of the ciphertext
+        let (_, body, tag) = split_ciphertext(&ciphertext);
+        let mut dec_result = vec![0u8; plaintext.len()];
+        cipher
+            .decrypt_with_nonce(&nonce, &body, &tag, &mut dec_result)
+            .unwrap();
+        assert_eq!(plaintext, &dec_result[..]);
+
+        // an empty plaintext results in the smallest valid ciphertext
+        let mut ciphertext = [0u8; AeadCipher::CIPHERTEXT_EXPANSION];
+        cipher.encrypt(b"", &mut ciphertext).unwrap();
+        assert_eq!(
+            AeadCipher::extract_nonce(&ciphertext).unwrap()[..],
+            ciphertext[..AeadCipher::NONCE_SIZE]
+        );
+
+        match AeadCipher::extract_nonce(
+            &ciphertext[..AeadCipher::CIPHERTEXT_EXPANSION - 1],
+        ) {
+            Err(DecryptionError::CiphertextLengthError {
+                ciphertext_length,
+                ciphertext_expansion,
+            }) => {
+                assert_eq!(
+                    ciphertext_length,
+                    AeadCipher::CIPHERTEXT_EXPANSION - 1
+                );
+                assert_eq!(
+                    ciphertext_expansion,
+                    AeadCipher::CIPHERTEXT_EXPANSION
+                );
+            }
+            _ => panic!("Expected a ciphertext length error"),
+        }
+
+        // the nonces of the direct mode are shorter
+        let direct_cipher = AeadCipherBuilder::new()
+            .mode(AeadMode::Direct)
+            .build(Key256::new());
+        let mut direct_ciphertext = vec![
+                0u8;
+                plaintext.len() + AeadCipher::DIRECT_CIPHERTEXT_EXPANSION
+            ];
+        direct_cipher
+            .encrypt(plaintext, &mut direct_ciphertext)
+            .unwrap();
+        let direct_nonce =
+            AeadCipher::extract_direct_nonce(&direct_ciphertext).unwrap();
+        assert_eq!(
+            direct_nonce[..],
+            direct_ciphertext[..AeadCipher::DIRECT_NONCE_SIZE]
+        );
+
+        let body = &direct_ciphertext[AeadCipher::DIRECT_NONCE_SIZE
+            ..direct_ciphertext.len() - AeadCipher::TAG_LENGTH];
+        let mut tag = [0u8; AeadCipher::TAG_LENGTH];
+        tag.copy_from_slice(
+            &direct_ciphertext
+                [direct_ciphertext.len() - AeadCipher::TAG_LENGTH..],
+        );
+        let mut dec_result = vec![0u8; plaintext.len()];
+        direct_cipher
+            .decrypt_with_direct_nonce(
+                &direct_nonce,
+                body,
+                &tag,
+                &mut dec_result,
+            )
+            .unwrap();
+        assert_eq!(plaintext, &dec_result[..]);
+
+        // the separate nonce functions check the mode of the cipher
+        match cipher.decrypt_with_direct_nonce(
+            &direct_nonce,
+            body,
+            &tag,
+            &mut dec_result,
+        ) {
+            Err(DecryptionError::NonceLengthError { expected, found }) => {
+                assert_eq!(expected, AeadCipher::NONCE_SIZE);
+                assert_eq!(found, AeadCipher::DIRECT_NONCE_SIZE);
+            }
+            _ => panic!("Expected a nonce length error"),
+        }
+
+        assert!(AeadCipher::extract_direct_nonce(
+            &direct_ciphertext[..AeadCipher::DIRECT_CIPHERTEXT_EXPANSION - 1]
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn separate_nonce_decryption() {
+        let plaintext = TEST_PLAINTEXT;
+        let mut ciphertext =
+            vec![0u8; plaintext.len() + AeadCipher::CIPHERTEXT_EXPANSION];
+        let mut dec_result = vec![0u8; plaintext.len()];
+
+        let k = Key256::new();
+        let cipher = AeadCipher::from_key(k);
+        cipher.encrypt(plaintext, &mut ciphertext).unwrap();
+
+        let (nonce, body, tag) = split_ciphertext(&ciphertext);
+        cipher
+            .decrypt_with_nonce(&nonce, &body, &tag, &mut dec_result)
+            .unwrap();
+        assert_eq!(plaintext, &dec_result[..]);
+
+        // plaintext too short
+        match cipher
+            .decrypt_with_nonce(&nonce, &body, &tag, &mut dec_result[1..])
+            .unwrap_err()
+        {
+            DecryptionError::PlaintextLengthError { .. } => (),
+            _ => panic!("Invalid Error"),
+        }
+    }
+
+    #[test]
+    fn separate_nonce_integrity() {
+        let plaintext = TEST_PLAINTEXT;
+        let mut ciphertext =
+            vec![0u8; plaintext.len() + AeadCipher::CIPHERTEXT_EXPANSION];
+        let mut dec_result = vec![0u8; plaintext.len()];
+
+        let k = Key256::new();
+        let cipher = AeadCipher::from_key(k);
+        cipher.encrypt(plaintext, &mut ciphertext).unwrap();
+
+        let (nonce, body, tag) = split_ciphertext(&ciphertext);
+
+        let mut tampered_nonce = nonce;
+        tampered_nonce[0] ^= 0x01;
+        cipher
+            .decrypt_with_nonce(&tampered_nonce, &body, &tag, &mut dec_result)
+            .expect_err("Expected decryption error");
+
+        let mut tampered_body = body.clone();
+        tampered_body[0] ^= 0x01;
+        cipher
+            .decrypt_with_nonce(&nonce, &tampered_body, &tag, &mut dec_result)
+            .expect_err("Expected decryption error");
+
+        let mut tampered_tag = tag;
+        tampered_tag[0] ^= 0x01;
+        cipher
+            .decrypt_with_nonce(&nonce, &body, &tampered_tag, &mut dec_result)
+            .expect_err("Expected decryption error");
+    }
+
+    #[test]
+    fn batch_encryption() {
+        use rand::rngs::StdRng;
+        use rand::SeedableRng;
+
+        let messages: Vec<Vec<u8>> = (0..100u32)
+            .map(|i| i.to_le_bytes().repeat(i as usize % 5))
+            .collect();
+        let plaintexts: Vec<&[u8]> =
+            messages.iter().map(Vec::as_slice).collect();
+        let mut rng = StdRng::from_entropy();
+
+        for mode in [AeadMode::DerivedKey, AeadMode::Direct] {
+            let cipher =
+                AeadCipherBuilder::new().mode(mode).build(Key256::new());
+            let ciphertexts =
+                cipher.encrypt_batch(&plaintexts, &mut rng).unwrap();
+            assert_eq!(ciphertexts.len(), plaintexts.len());
+
+            let mut nonces = std::collections::HashSet::new();
+            for (ct, pt) in ciphertexts.iter().zip(plaintexts.iter()) {
+                assert_eq!(ct.len(), pt.len() + cipher.ciphertext_expansion());
+                assert_eq!(&cipher.decrypt_to_vec(ct).unwrap(), pt);
+                assert!(nonces.insert(ct[..cipher.nonce_size()].to_vec()));
+            }
+        }
+
+        // the budget is consumed by every message of the batch
+        let cipher = AeadCipher::with_budget(Key256::new(), 150);
+        cipher.encrypt_batch(&plaintexts, &mut rng).unwrap();
+        assert_eq!(cipher.remaining_budget(), Some(50));
+
+        // a batch larger than the remaining budget fails without consuming it
+        assert!(matches!(
+            cipher.encrypt_batch(&plaintexts, &mut rng),
+            Err(EncryptionError::BudgetExhausted { max_messages: 150 })
+        ));
+        assert_eq!(cipher.remaining_budget(), Some(50));
+        cipher.encrypt_batch(&plaintexts[..50], &mut rng).unwrap();
+        assert_eq!(cipher.remaining_budget(), Some(0));
+    }
+
+    #[test]
+    fn detached_nonce_encryption() {
+        let plaintext = TEST_PLAINTEXT;
+        let cipher = AeadCipher::from_key(Key256::new());
+
+        let (nonce, ciphertext) = cipher.encrypt_detached(plaintext).unwrap();
+        assert_eq!(ciphertext.len(), plaintext.len() + AeadCipher::TAG_LENGTH);
+        assert_eq!(
+            cipher.decrypt_detached(&nonce, &ciphertext).unwrap(),
+            plaintext
+        );
+
+        // the detached parts are the ones of a regular ciphertext
+        let mut full_ciphertext = nonce.to_vec();
+        full_ciphertext.extend_from_slice(&ciphertext);
+        assert_eq!(cipher.decrypt_to_vec(&full_ciphertext).unwrap(), plaintext);
+
+        // swapping the nonces of two ciphertexts makes the decryption fail
+        let (other_nonce, other_ciphertext) =
+            cipher.encrypt_detached(plaintext).unwrap();
+        assert_ne!(nonce, other_nonce);
+        cipher
+            .decrypt_detached(&other_nonce, &ciphertext)
+            .expect_err("Expected decryption error");
+        cipher
+            .decrypt_detached(&nonce, &other_ciphertext)
+            .expect_err("Expected decryption error");
+
+        match cipher
+            .decrypt_detached(&nonce, &ciphertext[..AeadCipher::TAG_LENGTH - 1])
+            .unwrap_err()
+        {
+            DecryptionError::CiphertextLengthError { .. } => (),
+            _ => panic!("Invalid Error"),
+        }
+
+        let direct_cipher = AeadCipherBuilder::new()
+            .mode(AeadMode::Direct)
+            .build(Key256::new());
+        match direct_cipher.encrypt_detached(plaintext).unwrap_err() {
+            EncryptionError::NonceLengthError { .. } => (),
+            _ => panic!("Invalid Error"),
+        }
+        match direct_cipher
+            .decrypt_detached(&nonce, &ciphertext)
+            .unwrap_err()
+        {
+            DecryptionError::NonceLengthError { .. } => (),
+            _ => panic!("Invalid Error"),
+        }
+
+        // the direct mode has its own detached functions
+        let (direct_nonce, direct_ciphertext) =
+            direct_cipher.encrypt_detached_direct(plaintext).unwrap();
+        assert_eq!(
+            direct_ciphertext.len(),
+            plaintext.len() + AeadCipher::TAG_LENGTH
+        );
+        assert_eq!(
+            direct_cipher
+                .decrypt_detached_direct(&direct_nonce, &direct_ciphertext)
+                .unwrap(),
+            plaintext
+        );
+        let mut full_ciphertext = direct_nonce.to_vec();
+        full_ciphertext.extend_from_slice(&direct_ciphertext);
+        assert_eq!(
+            direct_cipher.decrypt_to_vec(&full_ciphertext).unwrap(),
+            plaintext
+        );
+
+        match cipher.encrypt_detached_direct(plaintext).unwrap_err() {
+            EncryptionError::NonceLengthError { expected, found } => {
+                assert_eq!(expected, AeadCipher::NONCE_SIZE);
+                assert_eq!(found, AeadCipher::DIRECT_NONCE_SIZE);
+            }
+            _ => panic!("Invalid Error"),
+        }
+        match cipher
+            .decrypt_detached_direct(&direct_nonce, &direct_ciphertext)
+            .unwrap_err()
+        {
+            DecryptionError::NonceLengthError { .. } => (),
+            _ => panic!("Invalid Error"),
+        }
+    }
+
     #[test]
     fn decryption_errors() {
         let plaintext = TEST_PLAINTEXT;
@@ -313,12 +1905,12 @@ mod tests {
             .decrypt(&ciphertext[0..1], &mut dec_result)
             .unwrap_err()
         {
-            DecryptionError::CiphertextLengthError(_) => (),
+            DecryptionError::CiphertextLengthError { .. } => (),
             _ => panic!("Invalid Error"),
         }
 
         match cipher.decrypt_to_vec(&ciphertext[0..1]).unwrap_err() {
-            DecryptionError::CiphertextLengthError(_) => (),
+            DecryptionError::CiphertextLengthError { .. } => (),
             _ => panic!("Invalid Error"),
         }
 
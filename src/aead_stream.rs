@@ -0,0 +1,397 @@
+//! Authenticated Encryption of streams
+
+use chacha20poly1305::aead::{AeadInPlace, KeyInit};
+use chacha20poly1305::{ChaCha20Poly1305, Nonce, Tag};
+
+use rand::RngCore;
+use zeroize::Zeroize;
+
+use crate::{AeadCipher, DecryptionError, EncryptionError, KeyDerivationPrf};
+use crate::{Key256, KeyAccessor};
+
+/// Size of the stream header, in bytes
+const STREAM_HEADER_SIZE: usize = 16;
+
+/// Key derivation context used to derive the stream keys
+const STREAM_CONTEXT: &[u8] = b"stream";
+
+/// Derive the key of the stream with header `header` from the main key
+fn stream_key(key: Key256, header: &[u8]) -> Key256 {
+    KeyDerivationPrf::<Key256>::from_key(key)
+        .derive_key_with_context(header, STREAM_CONTEXT)
+}
+
+/// Nonce of the `counter`-th chunk of a stream: the counter (encoded as a 8
+/// bytes little endian integer), followed by three zero bytes and the last
+/// chunk flag.
+fn chunk_nonce(counter: u64, is_last: bool) -> [u8; 12] {
+    let mut nonce = [0u8; 12];
+    nonce[..8].copy_from_slice(&counter.to_le_bytes());
+    nonce[11] = u8::from(is_last);
+    nonce
+}
+
+/// Authenticated encryption of a stream of chunks
+///
+/// `AeadStreamEncryptor` implements the STREAM construction of Hoang, Reyhanitabar, Rogaway and Vizár ([CRYPTO'15](https://eprint.iacr.org/2015/189.pdf)):
+/// every chunk is encrypted and authenticated individually with Chacha20 and
+/// Poly1305, using a nonce made of the index of the chunk in the stream and of
+/// a flag set only for the last chunk. This way, reordering, dropping or
+/// truncating chunks is detected by the decryptor.
+///
+/// ## Key derivation
+/// Similarly to `AeadCipher`, a random 128 bits header is generated for every
+/// stream, and used to derive the key of the stream from the main key. The
+/// header must be transmitted to the decryptor (it is not secret), before the
+/// encrypted chunks.
+///
+/// # Example
+/// ```
+/// # extern crate crypto_tk_rs;
+/// use crypto_tk_rs::{AeadStreamDecryptor, AeadStreamEncryptor, Key256};
+///
+/// let mut key_buf = [1u8; 32];
+/// let mut key_buf_copy = key_buf;
+///
+/// let mut encryptor =
+///     AeadStreamEncryptor::from_key(Key256::from_bytes(&mut key_buf));
+/// let c1 = encryptor.next_chunk(b"first chunk", false).unwrap();
+/// let c2 = encryptor.next_chunk(b"last chunk", true).unwrap();
+///
+/// let mut decryptor = AeadStreamDecryptor::from_key(
+///     Key256::from_bytes(&mut key_buf_copy),
+///     &encryptor.header(),
+/// )
+/// .unwrap();
+/// assert_eq!(decryptor.next_chunk(&c1, false).unwrap(), b"first chunk");
+/// assert_eq!(decryptor.next_chunk(&c2, true).unwrap(), b"last chunk");
+/// decryptor.finish().unwrap();
+/// ```
+#[derive(Zeroize)]
+#[zeroize(drop)]
+pub struct AeadStreamEncryptor {
+    key: Key256,
+    header: [u8; STREAM_HEADER_SIZE],
+    counter: u64,
+    finished: bool,
+}
+
+impl AeadStreamEncryptor {
+    /// Size of the stream header, in bytes
+    pub const HEADER_SIZE: usize = STREAM_HEADER_SIZE;
+
+    /// The ciphertext expansion of every chunk, i.e. the number of additional
+    /// bytes due to the encryption
+    pub const CHUNK_EXPANSION: usize = AeadCipher::TAG_LENGTH;
+
+    /// Start the encryption of a new stream, with a random header, using the
+    /// main key `key`
+    #[must_use]
+    pub fn from_key(key: Key256) -> Self {
+        let mut header = [0u8; STREAM_HEADER_SIZE];
+        rand::thread_rng().fill_bytes(&mut header);
+
+        AeadStreamEncryptor {
+            key: stream_key(key, &header),
+            header,
+            counter: 0,
+            finished: false,
+        }
+    }
+
+    /// Returns the header of the stream, needed to decrypt the stream
+    #[must_use]
+    pub fn header(&self) -> [u8; STREAM_HEADER_SIZE] {
+        self.header
+    }
+
+    /// Returns `true` if the last chunk has been encrypted
+    #[must_use]
+    pub fn is_finished(&self) -> bool {
+        self.finished
+    }
+
+    /// Encrypt the next chunk of the stream, and return the encrypted chunk
+    /// (`CHUNK_EXPANSION` bytes longer than `plaintext`). `is_last` must be
+    /// set for the last chunk of the stream, after which the encryptor
+    /// cannot be used anymore.
+    /// Returns an error if the last chunk has already been encrypted.
+    pub fn next_chunk(
+        &mut self,
+        plaintext: &[u8],
+        is_last: bool,
+    ) -> Result<Vec<u8>, EncryptionError> {
+        if self.finished {
+            return Err(EncryptionError::StreamFinished);
+        }
+        let counter = self.counter;
+        self.counter = counter
+            .checked_add(1)
+            .ok_or(EncryptionError::StreamFinished)?;
+
+        let mut ciphertext = vec![0u8; plaintext.len() + Self::CHUNK_EXPANSION];
+        ciphertext[..plaintext.len()].copy_from_slice(plaintext);
+
+        let cipher = ChaCha20Poly1305::new(chacha20poly1305::Key::from_slice(
+            self.key.content(),
+        ));
+        let nonce = chunk_nonce(counter, is_last);
+        let tag = cipher.encrypt_in_place_detached(
+            Nonce::from_slice(&nonce),
+            b"",
+            &mut ciphertext[..plaintext.len()],
+        )?;
+        ciphertext[plaintext.len()..].copy_from_slice(&tag);
+
+        self.finished = is_last;
+        Ok(ciphertext)
+    }
+}
+
+/// Authenticated decryption of a stream of chunks encrypted with
+/// `AeadStreamEncryptor`
+///
+/// The chunks must be decrypted in order, and the last chunk must be flagged
+/// as such. Once all the chunks have been decrypted, `finish` must be called
+/// to make sure that the stream has not been truncated.
+#[derive(Zeroize)]
+#[zeroize(drop)]
+pub struct AeadStreamDecryptor {
+    key: Key256,
+    counter: u64,
+    finished: bool,
+}
+
+impl AeadStreamDecryptor {
+    /// Start the decryption of the stream with header `header`, using the main
+    /// key `key`.
+    /// Returns an error if the header is not `AeadStreamEncryptor::HEADER_SIZE`
+    /// bytes long.
+    pub fn from_key(
+        key: Key256,
+        header: &[u8],
+    ) -> Result<Self, DecryptionError> {
+        if header.len() != STREAM_HEADER_SIZE {
+            return Err(DecryptionError::StreamHeaderLengthError {
+                expected: STREAM_HEADER_SIZE,
+                found: header.len(),
+            });
+        }
+
+        Ok(AeadStreamDecryptor {
+            key: stream_key(key, header),
+            counter: 0,
+            finished: false,
+        })
+    }
+
+    /// Returns `true` if the last chunk has been decrypted
+    #[must_use]
+    pub fn is_finished(&self) -> bool {
+        self.finished
+    }
+
+    /// Decrypt the next chunk of the stream. `is_last` must be set if the
+    /// chunk is expected to be the last one of the stream.
+    /// Returns an error if the chunk is not the next authentic chunk of the
+    /// stream (including when its last chunk flag does not match `is_last`),
+    /// or if the last chunk has already been decrypted. On error, the state
+    /// of the decryptor is unchanged.
+    pub fn next_chunk(
+        &mut self,
+        ciphertext: &[u8],
+        is_last: bool,
+    ) -> Result<Vec<u8>, DecryptionError> {
+        if self.finished {
+            return Err(DecryptionError::StreamFinished);
+        }
+        let next_counter = self
+            .counter
+            .checked_add(1)
+            .ok_or(DecryptionError::StreamFinished)?;
+
+        if ciphertext.len() < AeadStreamEncryptor::CHUNK_EXPANSION {
+            return Err(DecryptionError::CiphertextLengthError {
+                ciphertext_length: ciphertext.len(),
+                ciphertext_expansion: AeadStreamEncryptor::CHUNK_EXPANSION,
+            });
+        }
+        let plaintext_length =
+            ciphertext.len() - AeadStreamEncryptor::CHUNK_EXPANSION;
+
+        let mut plaintext = ciphertext[..plaintext_length].to_vec();
+
+        let cipher = ChaCha20Poly1305::new(chacha20poly1305::Key::from_slice(
+            self.key.content(),
+        ));
+        let nonce = chunk_nonce(self.counter, is_last);
+        let result = cipher.decrypt_in_place_detached(
+            Nonce::from_slice(&nonce),
+            b"",
+            &mut plaintext,
+            Tag::from_slice(&ciphertext[plaintext_length..]),
+        );
+        if let Err(e) = result {
+            plaintext.zeroize();
+            return Err(e.into());
+        }
+
+        self.counter = next_counter;
+        self.finished = is_last;
+        Ok(plaintext)
+    }
+
+    /// Terminate the decryption of the stream.
+    /// Returns an error if the last chunk of the stream has not been
+    /// decrypted, i.e. if the stream has been truncated.
+    pub fn finish(self) -> Result<(), DecryptionError> {
+        if self.finished {
+            Ok(())
+        } else {
+            Err(DecryptionError::TruncatedStream)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used)]
+    #![allow(clippy::expect_used)]
+    use super::*;
+    use crate::insecure_clone::private::InsecureClone;
+    use crate::Key;
+
+    const CHUNKS: [&[u8]; 4] =
+        [b"first chunk", b"", b"a longer third chunk", b"last chunk"];
+
+    fn encrypt_stream(key: Key256) -> ([u8; 16], Vec<Vec<u8>>) {
+        let mut encryptor = AeadStreamEncryptor::from_key(key);
+        let encrypted_chunks = CHUNKS
+            .iter()
+            .enumerate()
+            .map(|(i, chunk)| {
+                encryptor.next_chunk(chunk, i == CHUNKS.len() - 1).unwrap()
+            })
+            .collect();
+        assert!(encryptor.is_finished());
+        (encryptor.header(), encrypted_chunks)
+    }
+
+    #[test]
+    fn stream_correctness() {
+        let key = Key256::new();
+        let (header, encrypted_chunks) = encrypt_stream(key.insecure_clone());
+
+        let mut decryptor =
+            AeadStreamDecryptor::from_key(key, &header).unwrap();
+        for (i, (chunk, encrypted_chunk)) in
+            CHUNKS.iter().zip(encrypted_chunks.iter()).enumerate()
+        {
+            assert_eq!(
+                encrypted_chunk.len(),
+                chunk.len() + AeadStreamEncryptor::CHUNK_EXPANSION
+            );
+            let is_last = i == CHUNKS.len() - 1;
+            assert_eq!(
+                &decryptor.next_chunk(encrypted_chunk, is_last).unwrap()[..],
+                *chunk
+            );
+        }
+        assert!(decryptor.is_finished());
+        decryptor.finish().unwrap();
+    }
+
+    #[test]
+    fn stream_truncation() {
+        let key = Key256::new();
+        let (header, encrypted_chunks) = encrypt_stream(key.insecure_clone());
+        let truncated = &encrypted_chunks[..encrypted_chunks.len() - 1];
+
+        // without the last chunk, the decryption cannot be finished
+        let mut decryptor =
+            AeadStreamDecryptor::from_key(key.insecure_clone(), &header)
+                .unwrap();
+        for chunk in truncated {
+            decryptor.next_chunk(chunk, false).unwrap();
+        }
+        assert!(matches!(
+            decryptor.finish(),
+            Err(DecryptionError::TruncatedStream)
+        ));
+
+        // a chunk that was not the last one cannot be passed as the last one
+        let mut decryptor =
+            AeadStreamDecryptor::from_key(key.insecure_clone(), &header)
+                .unwrap();
+        for chunk in &truncated[..truncated.len() - 1] {
+            decryptor.next_chunk(chunk, false).unwrap();
+        }
+        decryptor
+            .next_chunk(&truncated[truncated.len() - 1], true)
+            .expect_err("Truncation should have been detected");
+
+        // nor can the last chunk be passed as an intermediate one
+        let mut decryptor =
+            AeadStreamDecryptor::from_key(key, &header).unwrap();
+        for chunk in truncated {
+            decryptor.next_chunk(chunk, false).unwrap();
+        }
+        decryptor
+            .next_chunk(&encrypted_chunks[encrypted_chunks.len() - 1], false)
+            .expect_err("The last chunk flag should have been checked");
+    }
+
+    #[test]
+    fn stream_reordering() {
+        let key = Key256::new();
+        let (header, encrypted_chunks) = encrypt_stream(key.insecure_clone());
+
+        let mut decryptor =
+            AeadStreamDecryptor::from_key(key.insecure_clone(), &header)
+                .unwrap();
+        decryptor
+            .next_chunk(&encrypted_chunks[1], false)
+            .expect_err("Reordering should have been detected");
+
+        // the state is unchanged after a failure
+        decryptor.next_chunk(&encrypted_chunks[0], false).unwrap();
+        decryptor.next_chunk(&encrypted_chunks[1], false).unwrap();
+
+        // using another header (i.e. another stream) fails
+        let mut other_header = header;
+        other_header[0] ^= 1;
+        let mut decryptor =
+            AeadStreamDecryptor::from_key(key, &other_header).unwrap();
+        assert!(decryptor.next_chunk(&encrypted_chunks[0], false).is_err());
+    }
+
+    #[test]
+    fn finished_stream() {
+        let key = Key256::new();
+        let mut encryptor = AeadStreamEncryptor::from_key(key.insecure_clone());
+        let chunk = encryptor.next_chunk(b"chunk", true).unwrap();
+        assert!(matches!(
+            encryptor.next_chunk(b"chunk", false),
+            Err(EncryptionError::StreamFinished)
+        ));
+
+        let mut decryptor = AeadStreamDecryptor::from_key(
+            key.insecure_clone(),
+            &encryptor.header(),
+        )
+        .unwrap();
+        decryptor.next_chunk(&chunk, true).unwrap();
+        assert!(matches!(
+            decryptor.next_chunk(&chunk, true),
+            Err(DecryptionError::StreamFinished)
+        ));
+
+        assert!(matches!(
+            AeadStreamDecryptor::from_key(key, &[0u8; 15]),
+            Err(DecryptionError::StreamHeaderLengthError {
+                expected: 16,
+                found: 15
+            })
+        ));
+    }
+}
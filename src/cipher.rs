@@ -7,8 +7,10 @@ use chacha20::{
 };
 
 // use clear_on_drop::clear_stack_on_return;
-use rand::RngCore;
-use zeroize::Zeroize;
+use rand::{CryptoRng, RngCore};
+use zeroize::{Zeroize, Zeroizing};
+
+use std::io::{Read, Write};
 
 // use std::vec::Vec;
 use crate::insecure_clone::{private::InsecureClone, CryptographyClone};
@@ -17,9 +19,10 @@ use crate::serialization::cleartext_serialization::{
 };
 use crate::serialization::errors::CleartextContentDeserializationError;
 
+use crate::prf::REKEY_CONTEXT;
 use crate::EncryptionError;
 use crate::{DecryptionError, KeyDerivationPrf};
-use crate::{Key256, KeyAccessor};
+use crate::{Key, Key256, KeyAccessor};
 
 /// Encryption & decryption (unauthenticated)
 ///
@@ -69,10 +72,20 @@ impl Cipher {
 
     const CHACHA20_NONCE_LENGTH: usize = 12;
 
+    /// Size of the chunks processed by the stream encryption functions
+    const STREAM_CHUNK_SIZE: usize = 4096;
+
     /// The ciphertext expansion, i.e. the number of additional bytes due to the
     /// encryption
     pub const CIPHERTEXT_EXPANSION: usize = Cipher::NONCE_SIZE;
 
+    /// Returns `true` if `len` is a possible length for a ciphertext, i.e. if
+    /// it is at least `CIPHERTEXT_EXPANSION` bytes.
+    #[must_use]
+    pub fn is_valid_ciphertext_length(len: usize) -> bool {
+        len >= Cipher::CIPHERTEXT_EXPANSION
+    }
+
     /// Construct a cipher from a 256 bits key
     #[must_use]
     pub fn from_key(key: Key256) -> Cipher {
@@ -81,15 +94,34 @@ impl Cipher {
         }
     }
 
+    /// Construct a cipher from a new key generated with the `csprng` random
+    /// generator, e.g. for reproducible tests or custom entropy sources
+    pub fn from_rng<R>(csprng: &mut R) -> Cipher
+    where
+        R: CryptoRng + RngCore,
+    {
+        Cipher::from_key(Key256::generate(csprng))
+    }
+
+    /// Returns a new cipher, whose key is deterministically derived from the
+    /// key of `self`. This allows to rotate the key after a large number of
+    /// encryptions without ever exposing the key material. Ciphertexts
+    /// produced by `self` cannot be decrypted by the returned cipher.
+    #[must_use]
+    pub fn rekey(&self) -> Cipher {
+        Cipher::from_key(self.key_derivation_prf.derive_key(REKEY_CONTEXT))
+    }
+
     /// Encrypt a byte slice and write the result of the encryption in
-    /// `ciphertext`. Returns an error if the `ciphertext` slice cannot
-    /// contain the result, i.e. if it is not at least `CIPHERTEXT_EXPANSION`
-    /// bytes longer than `plaintext`.
+    /// `ciphertext`. Returns the number of bytes written to `ciphertext`, or
+    /// an error if the `ciphertext` slice cannot contain the result, i.e. if
+    /// it is not at least `CIPHERTEXT_EXPANSION` bytes longer than
+    /// `plaintext`.
     pub fn encrypt(
         &self,
         plaintext: &[u8],
         ciphertext: &mut [u8],
-    ) -> Result<(), EncryptionError> {
+    ) -> Result<usize, EncryptionError> {
         if ciphertext
             .len()
             .saturating_sub(Cipher::CIPHERTEXT_EXPANSION)
@@ -101,29 +133,49 @@ impl Cipher {
             });
         }
 
-        let mut iv = [0u8; Cipher::NONCE_SIZE];
-        let mut rng = rand::thread_rng();
-        rng.fill_bytes(&mut iv);
-
-        // write the nonce at the beginning of the ciphertext
-        ciphertext[..Cipher::NONCE_SIZE].copy_from_slice(&iv);
-
         // copy the plaintext
         ciphertext[Cipher::NONCE_SIZE..(Cipher::NONCE_SIZE + plaintext.len())]
             .copy_from_slice(plaintext);
 
-        let encryption_key = self.key_derivation_prf.derive_key(&iv);
-        let chacha_key = chacha20::Key::from_slice(encryption_key.content());
-        let inner_nonce =
-            Nonce::from_slice(&iv[..Cipher::CHACHA20_NONCE_LENGTH]);
-        let mut cipher = ChaCha20::new(chacha_key, inner_nonce);
-
-        cipher.apply_keystream(
+        let iv = self.encrypt_in_place(
             &mut ciphertext
                 [Cipher::NONCE_SIZE..(Cipher::NONCE_SIZE + plaintext.len())],
-        );
+        )?;
 
-        Ok(())
+        // write the nonce at the beginning of the ciphertext
+        ciphertext[..Cipher::NONCE_SIZE].copy_from_slice(&iv);
+
+        Ok(plaintext.len() + Cipher::CIPHERTEXT_EXPANSION)
+    }
+
+    /// Encrypt `buffer` in place, without any copy, and return the randomly
+    /// generated nonce. The nonce is not part of the buffer: it is up to the
+    /// caller to store it alongside the ciphertext, and to give it back to
+    /// `decrypt_in_place`.
+    ///
+    /// # Example
+    /// ```
+    /// # extern crate crypto_tk_rs;
+    /// use crypto_tk_rs::{Cipher, Key, Key256};
+    ///
+    /// let cipher = Cipher::from_key(Key256::new());
+    /// let mut buffer = *b"Test plaintext";
+    ///
+    /// let nonce = cipher.encrypt_in_place(&mut buffer).unwrap();
+    /// cipher.decrypt_in_place(&nonce, &mut buffer).unwrap();
+    /// assert_eq!(&buffer, b"Test plaintext");
+    /// ```
+    pub fn encrypt_in_place(
+        &self,
+        buffer: &mut [u8],
+    ) -> Result<[u8; Cipher::NONCE_SIZE], EncryptionError> {
+        let mut iv = [0u8; Cipher::NONCE_SIZE];
+        let mut rng = rand::thread_rng();
+        rng.fill_bytes(&mut iv);
+
+        self.apply_keystream(&iv, buffer);
+
+        Ok(iv)
     }
 
     /// Decrypt a byte slice and write the result of the decryption in
@@ -138,7 +190,10 @@ impl Cipher {
     ) -> Result<(), DecryptionError> {
         let l = ciphertext.len();
         if l < Cipher::CIPHERTEXT_EXPANSION {
-            return Err(DecryptionError::CiphertextLengthError(l));
+            return Err(DecryptionError::CiphertextLengthError {
+                ciphertext_length: l,
+                ciphertext_expansion: Cipher::CIPHERTEXT_EXPANSION,
+            });
         }
 
         if l > plaintext.len() + Cipher::CIPHERTEXT_EXPANSION {
@@ -156,16 +211,129 @@ impl Cipher {
         plaintext[..real_plaintext_length]
             .copy_from_slice(&ciphertext[Cipher::NONCE_SIZE..]);
 
+        self.apply_keystream(iv, &mut plaintext[..real_plaintext_length]);
+
+        Ok(())
+    }
+
+    /// Decrypt `buffer` in place, without any copy, using the `nonce`
+    /// returned by `encrypt_in_place`.
+    pub fn decrypt_in_place(
+        &self,
+        nonce: &[u8; Cipher::NONCE_SIZE],
+        buffer: &mut [u8],
+    ) -> Result<(), DecryptionError> {
+        self.apply_keystream(nonce, buffer);
+
+        Ok(())
+    }
+
+    /// Encrypt the content of `reader` and write the result to `writer`,
+    /// without holding the whole plaintext or ciphertext in memory. The nonce
+    /// is written first, followed by the encrypted bytes, so that the output
+    /// has the same format as the one of `encrypt`.
+    /// Returns the total number of bytes written to `writer`.
+    pub fn encrypt_stream(
+        &self,
+        reader: &mut dyn Read,
+        writer: &mut dyn Write,
+    ) -> Result<u64, EncryptionError> {
+        let mut iv = [0u8; Cipher::NONCE_SIZE];
+        let mut rng = rand::thread_rng();
+        rng.fill_bytes(&mut iv);
+
+        writer.write_all(&iv)?;
+        let written_bytes = Self::apply_keystream_stream(
+            &mut self.stream_cipher(&iv),
+            reader,
+            writer,
+        )?;
+
+        Ok(written_bytes + Cipher::NONCE_SIZE as u64)
+    }
+
+    /// Decrypt the content of `reader`, produced by `encrypt_stream` (or
+    /// `encrypt`), and write the result to `writer`, without holding the
+    /// whole ciphertext or plaintext in memory.
+    /// Returns the total number of bytes written to `writer`, or an error if
+    /// the stream is shorter than `CIPHERTEXT_EXPANSION` bytes.
+    pub fn decrypt_stream(
+        &self,
+        reader: &mut dyn Read,
+        writer: &mut dyn Write,
+    ) -> Result<u64, DecryptionError> {
+        let mut iv = [0u8; Cipher::NONCE_SIZE];
+        let nonce_length = read_chunk(reader, &mut iv)?;
+        if nonce_length < Cipher::NONCE_SIZE {
+            return Err(DecryptionError::CiphertextLengthError {
+                ciphertext_length: nonce_length,
+                ciphertext_expansion: Cipher::CIPHERTEXT_EXPANSION,
+            });
+        }
+
+        Ok(Self::apply_keystream_stream(
+            &mut self.stream_cipher(&iv),
+            reader,
+            writer,
+        )?)
+    }
+
+    /// Read `reader` by chunks, XOR them with `cipher`'s keystream and write
+    /// the result to `writer`. Returns the number of processed bytes.
+    fn apply_keystream_stream(
+        cipher: &mut ChaCha20,
+        reader: &mut dyn Read,
+        writer: &mut dyn Write,
+    ) -> Result<u64, std::io::Error> {
+        let mut buffer = Zeroizing::new([0u8; Cipher::STREAM_CHUNK_SIZE]);
+        let mut processed_bytes = 0u64;
+
+        loop {
+            let l = read_chunk(reader, &mut *buffer)?;
+            if l == 0 {
+                break;
+            }
+            cipher.apply_keystream(&mut buffer[..l]);
+            writer.write_all(&buffer[..l])?;
+            processed_bytes += l as u64;
+        }
+
+        Ok(processed_bytes)
+    }
+
+    /// Derive the encryption key from `iv` and XOR `buffer` with the
+    /// corresponding Chacha20 keystream
+    fn apply_keystream(&self, iv: &[u8], buffer: &mut [u8]) {
+        self.stream_cipher(iv).apply_keystream(buffer);
+    }
+
+    /// Derive the encryption key from `iv` and return the corresponding
+    /// Chacha20 instance
+    fn stream_cipher(&self, iv: &[u8]) -> ChaCha20 {
         let encryption_key = self.key_derivation_prf.derive_key(iv);
         let chacha_key = chacha20::Key::from_slice(encryption_key.content());
         let inner_nonce =
             Nonce::from_slice(&iv[..Cipher::CHACHA20_NONCE_LENGTH]);
-        let mut cipher = ChaCha20::new(chacha_key, inner_nonce);
-
-        cipher.apply_keystream(&mut plaintext[..real_plaintext_length]);
+        ChaCha20::new(chacha_key, inner_nonce)
+    }
+}
 
-        Ok(())
+/// Fill `buffer` with bytes read from `reader`, until either the buffer is
+/// full or the end of the stream is reached. Returns the number of read bytes.
+fn read_chunk(
+    reader: &mut dyn Read,
+    buffer: &mut [u8],
+) -> Result<usize, std::io::Error> {
+    let mut l = 0;
+    while l < buffer.len() {
+        match reader.read(&mut buffer[l..]) {
+            Ok(0) => break,
+            Ok(n) => l += n,
+            Err(e) if e.kind() == std::io::ErrorKind::Interrupted => (),
+            Err(e) => return Err(e),
+        }
     }
+    Ok(l)
 }
 
 impl SerializableCleartextContent for Cipher {
@@ -196,12 +364,34 @@ impl DeserializableCleartextContent for Cipher {
 #[cfg(test)]
 mod tests {
     #![allow(clippy::unwrap_used)]
+    #![allow(clippy::expect_used)]
     use crate::Key;
 
     use super::*;
+    use std::io::Cursor;
 
     const TEST_PLAINTEXT: &[u8] = b"Test plaintext";
 
+    #[test]
+    fn seeded_generation() {
+        use rand::rngs::StdRng;
+        use rand::SeedableRng;
+
+        let plaintext = TEST_PLAINTEXT;
+        let cipher_1 =
+            Cipher::from_rng(&mut StdRng::seed_from_u64(0xdead_beef));
+        let cipher_2 =
+            Cipher::from_rng(&mut StdRng::seed_from_u64(0xdead_beef));
+
+        let mut ciphertext =
+            vec![0u8; plaintext.len() + Cipher::CIPHERTEXT_EXPANSION];
+        let mut dec_result = vec![0u8; plaintext.len()];
+        cipher_1.encrypt(plaintext, &mut ciphertext).unwrap();
+        cipher_2.decrypt(&ciphertext, &mut dec_result).unwrap();
+
+        assert_eq!(plaintext, &dec_result[..]);
+    }
+
     #[test]
     fn encryption_errors() {
         let plaintext = TEST_PLAINTEXT;
@@ -223,13 +413,155 @@ mod tests {
 
         let k = Key256::new();
         let cipher = Cipher::from_key(k);
-        cipher.encrypt(plaintext, &mut ciphertext).unwrap();
+        let written_bytes = cipher.encrypt(plaintext, &mut ciphertext).unwrap();
+        assert_eq!(written_bytes, ciphertext.len());
 
         cipher.decrypt(&ciphertext, &mut dec_result).unwrap();
 
         assert_eq!(plaintext, &dec_result[..]);
     }
 
+    #[test]
+    fn encryption_length() {
+        let plaintext = TEST_PLAINTEXT;
+        // give more space than needed
+        let mut ciphertext =
+            vec![0u8; plaintext.len() + Cipher::CIPHERTEXT_EXPANSION + 10];
+
+        let k = Key256::new();
+        let cipher = Cipher::from_key(k);
+        let written_bytes = cipher.encrypt(plaintext, &mut ciphertext).unwrap();
+        assert_eq!(
+            written_bytes,
+            plaintext.len() + Cipher::CIPHERTEXT_EXPANSION
+        );
+    }
+
+    #[test]
+    fn rekey() {
+        let plaintext = TEST_PLAINTEXT;
+        let mut ciphertext =
+            vec![0u8; plaintext.len() + Cipher::CIPHERTEXT_EXPANSION];
+        let mut dec_result = vec![0u8; plaintext.len()];
+
+        let k = Key256::new();
+        let cipher = Cipher::from_key(k);
+        cipher.encrypt(plaintext, &mut ciphertext).unwrap();
+
+        let rekeyed_cipher = cipher.rekey();
+        rekeyed_cipher
+            .decrypt(&ciphertext, &mut dec_result)
+            .unwrap();
+        assert_ne!(plaintext, &dec_result[..]);
+
+        // the rekeyed cipher is fully functional
+        rekeyed_cipher.encrypt(plaintext, &mut ciphertext).unwrap();
+        rekeyed_cipher
+            .decrypt(&ciphertext, &mut dec_result)
+            .unwrap();
+        assert_eq!(plaintext, &dec_result[..]);
+
+        // rekeying is deterministic
+        cipher
+            .rekey()
+            .decrypt(&ciphertext, &mut dec_result)
+            .unwrap();
+        assert_eq!(plaintext, &dec_result[..]);
+    }
+
+    #[test]
+    fn in_place_encryption_correctness() {
+        let mut buffer = TEST_PLAINTEXT.to_vec();
+
+        let k = Key256::new();
+        let cipher = Cipher::from_key(k);
+        let nonce = cipher.encrypt_in_place(&mut buffer).unwrap();
+        assert_ne!(TEST_PLAINTEXT, &buffer[..]);
+
+        cipher.decrypt_in_place(&nonce, &mut buffer).unwrap();
+        assert_eq!(TEST_PLAINTEXT, &buffer[..]);
+    }
+
+    #[test]
+    fn in_place_encryption_consistency() {
+        let mut ciphertext =
+            vec![0u8; TEST_PLAINTEXT.len() + Cipher::CIPHERTEXT_EXPANSION];
+
+        let k = Key256::new();
+        let cipher = Cipher::from_key(k);
+        cipher.encrypt(TEST_PLAINTEXT, &mut ciphertext).unwrap();
+
+        // decrypt the output of `encrypt` in place
+        let mut nonce = [0u8; Cipher::NONCE_SIZE];
+        nonce.copy_from_slice(&ciphertext[..Cipher::NONCE_SIZE]);
+        let buffer = &mut ciphertext[Cipher::NONCE_SIZE..];
+        cipher.decrypt_in_place(&nonce, buffer).unwrap();
+
+        assert_eq!(TEST_PLAINTEXT, &buffer[..]);
+    }
+
+    #[test]
+    fn stream_encryption_correctness() {
+        // use a length that is not a multiple of the chunk size
+        let plaintext: Vec<u8> =
+            (0..300 * 1024 + 17usize).map(|i| (i % 251) as u8).collect();
+
+        let k = Key256::new();
+        let cipher = Cipher::from_key(k);
+
+        let mut ciphertext = Vec::new();
+        let written_bytes = cipher
+            .encrypt_stream(&mut Cursor::new(&plaintext), &mut ciphertext)
+            .unwrap();
+        assert_eq!(written_bytes, ciphertext.len() as u64);
+        assert_eq!(
+            ciphertext.len(),
+            plaintext.len() + Cipher::CIPHERTEXT_EXPANSION
+        );
+
+        let mut dec_result = Vec::new();
+        let read_bytes = cipher
+            .decrypt_stream(&mut Cursor::new(&ciphertext), &mut dec_result)
+            .unwrap();
+        assert_eq!(read_bytes, plaintext.len() as u64);
+        assert_eq!(plaintext, dec_result);
+
+        // the stream format is the same as the one of `encrypt`
+        let mut dec_buffer = vec![0u8; plaintext.len()];
+        cipher.decrypt(&ciphertext, &mut dec_buffer).unwrap();
+        assert_eq!(plaintext, dec_buffer);
+    }
+
+    #[test]
+    fn stream_decryption_errors() {
+        let k = Key256::new();
+        let cipher = Cipher::from_key(k);
+
+        let mut dec_result = Vec::new();
+        match cipher
+            .decrypt_stream(&mut Cursor::new([0u8; 3]), &mut dec_result)
+            .unwrap_err()
+        {
+            DecryptionError::CiphertextLengthError {
+                ciphertext_length: 3,
+                ..
+            } => (),
+            _ => panic!("Invalid Error"),
+        }
+    }
+
+    #[test]
+    fn ciphertext_length_validation() {
+        assert!(!Cipher::is_valid_ciphertext_length(0));
+        assert!(!Cipher::is_valid_ciphertext_length(15));
+        assert!(Cipher::is_valid_ciphertext_length(16));
+        assert!(Cipher::is_valid_ciphertext_length(100));
+
+        let cipher = Cipher::from_key(Key256::new());
+        let err = cipher.decrypt(&[0u8; 5], &mut [0u8; 5]).unwrap_err();
+        assert!(err.to_string().contains("5 < 16"));
+    }
+
     #[test]
     fn decryption_errors() {
         let plaintext = TEST_PLAINTEXT;
@@ -245,7 +577,7 @@ mod tests {
             .decrypt(&ciphertext[0..1], &mut dec_result)
             .unwrap_err()
         {
-            DecryptionError::CiphertextLengthError(_) => (),
+            DecryptionError::CiphertextLengthError { .. } => (),
             _ => panic!("Invalid Error"),
         }
 
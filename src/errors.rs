@@ -16,14 +16,51 @@ pub enum EncryptionError {
     /// Opaque error during the encryption
     #[error("Encryption Error - Inner Error")]
     InnerError(#[from] aead::Error),
+    /// IO error while reading the plaintext or writing the ciphertext
+    #[error("Encryption Error - IO Error: {0}")]
+    IoError(#[from] std::io::Error),
+    /// The last chunk of the stream has already been encrypted (or the chunk
+    /// counter is exhausted)
+    #[error("Encryption Error - The stream is finished")]
+    StreamFinished,
+    /// The plaintext is too long to be encrypted in a single message
+    #[error("Encryption Error - The plaintext is too long ({plaintext_length} > {max_length})")]
+    PlaintextTooLong {
+        /// plaintext length
+        plaintext_length: u64,
+        /// maximum plaintext length
+        max_length: u64,
+    },
+    /// The maximum number of messages that can be encrypted with the cipher
+    /// has been reached
+    #[error(
+        "Encryption Error - The budget of {max_messages} messages is exhausted"
+    )]
+    BudgetExhausted {
+        /// maximum number of encrypted messages
+        max_messages: u64,
+    },
+    /// Invalid nonce length for the cipher's mode
+    #[error("Encryption Error - Invalid nonce length {found}, expected {expected} bytes")]
+    NonceLengthError {
+        /// nonce length expected by the cipher
+        expected: usize,
+        /// length of the requested nonce
+        found: usize,
+    },
 }
 
 /// Decryption error
 #[derive(Error, Debug)]
 pub enum DecryptionError {
     /// Invalid ciphertext length
-    #[error("Decryption Error - The length of the ciphertext slice is invalid  because it is smaller than the ciphertext expansion ({0} < 32)")]
-    CiphertextLengthError(usize),
+    #[error("Decryption Error - The length of the ciphertext slice is invalid because it is smaller than the ciphertext expansion ({ciphertext_length} < {ciphertext_expansion})")]
+    CiphertextLengthError {
+        /// ciphertext length
+        ciphertext_length: usize,
+        /// ciphertext expansion of the cipher
+        ciphertext_expansion: usize,
+    },
     /// Invalid plaintext length
     #[error("Decryption Error - The length of the plaintext  slice for a ciphertext of size {ciphertext_length} is invalid ({plaintext_length})")]
     PlaintextLengthError {
@@ -32,9 +69,74 @@ pub enum DecryptionError {
         /// ciphertext length
         ciphertext_length: usize,
     },
+    /// Invalid nonce length for the cipher's mode
+    #[error("Decryption Error - Invalid nonce length {found}, expected {expected} bytes")]
+    NonceLengthError {
+        /// nonce length expected by the cipher
+        expected: usize,
+        /// length of the given nonce
+        found: usize,
+    },
     /// Opaque error during the encryption
     #[error("Decryption Error - Inner Error")]
     InnerError(#[from] aead::Error),
+    /// IO error while reading the ciphertext or writing the plaintext
+    #[error("Decryption Error - IO Error: {0}")]
+    IoError(#[from] std::io::Error),
+    /// The last chunk of the stream has already been decrypted (or the chunk
+    /// counter is exhausted)
+    #[error("Decryption Error - The stream is finished")]
+    StreamFinished,
+    /// The stream ended before its last chunk was decrypted
+    #[error("Decryption Error - Truncated stream")]
+    TruncatedStream,
+    /// Invalid stream header length
+    #[error("Decryption Error - Invalid stream header length {found}, expected {expected} bytes")]
+    StreamHeaderLengthError {
+        /// header length expected by the decryptor
+        expected: usize,
+        /// length of the given header
+        found: usize,
+    },
+}
+
+/// Error while building a key from raw bytes
+#[derive(Error, Debug)]
+pub enum KeyParseError {
+    /// Invalid key length
+    #[error("Key Parse Error - Invalid key length {found}, expected {expected} bytes")]
+    InvalidLength {
+        /// expected key length
+        expected: usize,
+        /// length of the input
+        found: usize,
+    },
+}
+
+/// Error while deriving keys
+#[derive(Error, Debug)]
+pub enum KeyDerivationError {
+    /// Too many keys requested
+    #[error("Key Derivation Error - {requested} keys requested, more than the maximum of {max_count}")]
+    TooManyKeys {
+        /// number of requested keys
+        requested: usize,
+        /// maximum number of keys
+        max_count: usize,
+    },
+}
+
+/// Error while building a hash value from raw bytes
+#[derive(Error, Debug)]
+pub enum HashParseError {
+    /// Invalid hash length
+    #[error("Hash Parse Error - Invalid hash length {found}, expected {expected} bytes")]
+    InvalidLength {
+        /// expected hash length
+        expected: usize,
+        /// length of the input
+        found: usize,
+    },
 }
 
 /// Error while wrapping a cryptographic object
@@ -59,4 +161,35 @@ pub enum UnwrappingError {
     DeserializationError(
         #[from] serialization::errors::CleartextDeserializationError,
     ),
+    /// The unwrapped object is not of the expected type
+    #[error("UnwrappingError - type mismatch (expected {expected:?}, found {found:?})")]
+    TypeMismatch {
+        /// Serialization tag of the requested type
+        expected: serialization::tags::SerializationTag,
+        /// Serialization tag found in the unwrapped bytes
+        found: serialization::tags::SerializationTag,
+    },
+    /// The wrapped blob is too short to contain a versioned header
+    #[error("UnwrappingError - missing versioned header")]
+    MissingHeader,
+    /// The wrapped blob was produced with a different key identifier
+    #[error("UnwrappingError - key id mismatch (expected {expected}, found {found})")]
+    KeyIdMismatch {
+        /// Key identifier of the wrapper
+        expected: u64,
+        /// Key identifier found in the blob
+        found: u64,
+    },
+    /// The wrapped blob was produced with a different format version
+    #[error("UnwrappingError - version mismatch (expected {expected}, found {found})")]
+    VersionMismatch {
+        /// Format version of the wrapper
+        expected: u16,
+        /// Format version found in the blob
+        found: u16,
+    },
+    /// The hex-encoded wrapped blob is not a valid hexadecimal string
+    #[cfg(feature = "hex")]
+    #[error("UnwrappingError - invalid hex encoding: {0}")]
+    InvalidHex(#[from] hex::FromHexError),
 }
@@ -1,9 +1,19 @@
 //! Hashing value to bytes
 
+use crate::errors::HashParseError;
+
+use std::convert::TryFrom;
+use subtle::ConstantTimeEq;
+
 /// A finalized hash value with constant-time equality and an accessible `u8`
 /// representation This implementation uses Blake2b, and the `blake2b_simd`
 /// crate.
-#[derive(Debug, Clone, PartialEq, Eq)]
+///
+/// Hash values are always compared in constant time: `ct_eq` and the
+/// `PartialEq` implementations (against another `Hash` or against bytes) all
+/// use the constant-time comparison of the `subtle` crate, and can be used to
+/// verify MACs or other secret values.
+#[derive(Debug, Clone, Eq)]
 pub struct Hash {
     inner: blake2b_simd::Hash,
 }
@@ -19,6 +29,21 @@ impl Hash {
             inner: blake2b_simd::blake2b(data),
         }
     }
+
+    /// Reconstructs a hash value from its bytes, e.g. a previously computed
+    /// digest read from a storage
+    #[must_use]
+    pub fn from_bytes(bytes: &[u8; Hash::HASH_SIZE]) -> Hash {
+        Hash {
+            inner: blake2b_simd::Hash::from(*bytes),
+        }
+    }
+
+    /// Compares two hash values in constant time
+    #[must_use]
+    pub fn ct_eq(&self, other: &Hash) -> bool {
+        self.as_ref().ct_eq(other.as_ref()).into()
+    }
 }
 
 impl AsRef<[u8]> for Hash {
@@ -27,9 +52,35 @@ impl AsRef<[u8]> for Hash {
     }
 }
 
+impl TryFrom<&[u8]> for Hash {
+    type Error = HashParseError;
+
+    /// Reconstructs a hash value from its bytes. Returns an error if `bytes`
+    /// is not `HASH_SIZE` bytes long.
+    fn try_from(bytes: &[u8]) -> Result<Self, Self::Error> {
+        let bytes =
+            <&[u8; Hash::HASH_SIZE]>::try_from(bytes).map_err(|_e| {
+                HashParseError::InvalidLength {
+                    expected: Hash::HASH_SIZE,
+                    found: bytes.len(),
+                }
+            })?;
+        Ok(Hash::from_bytes(bytes))
+    }
+}
+
+impl PartialEq for Hash {
+    /// Compares two hash values in constant time, as `ct_eq`
+    fn eq(&self, other: &Hash) -> bool {
+        self.ct_eq(other)
+    }
+}
+
 impl PartialEq<[u8]> for Hash {
+    /// Compares the hash value with `other` in constant time. Slices that are
+    /// not `HASH_SIZE` bytes long are never equal to the hash value.
     fn eq(&self, other: &[u8]) -> bool {
-        self.inner.eq(other)
+        self.as_ref().ct_eq(other).into()
     }
 }
 
@@ -76,4 +127,59 @@ mod tests {
             assert_eq!(hash, expected[..]);
         }
     }
+
+    #[test]
+    fn constant_time_equality() {
+        let h1 = Hash::new(FOX_VALUE);
+        let h2 = Hash::new(FOX_VALUE);
+        assert!(h1.ct_eq(&h2));
+
+        // flip a single bit of the input
+        let mut other_value = FOX_VALUE.to_vec();
+        other_value[0] ^= 0x01;
+        assert!(!h1.ct_eq(&Hash::new(&other_value)));
+
+        // hashes differing by a single bit
+        for i in 0..Hash::HASH_SIZE {
+            let mut bytes = [0u8; Hash::HASH_SIZE];
+            bytes.copy_from_slice(h1.as_ref());
+            bytes[i] ^= 0x80;
+            let h3 = Hash::from_bytes(&bytes);
+            assert!(!h1.ct_eq(&h3));
+            assert_ne!(h1, h3);
+            assert!(h1 != bytes[..]);
+        }
+
+        // the `PartialEq` implementations agree with `ct_eq`
+        assert_eq!(h1, h2);
+        assert!(h1 == *h2.as_ref());
+        assert!(h1 != h2.as_ref()[..Hash::HASH_SIZE - 1]);
+    }
+
+    #[test]
+    fn reconstruction() {
+        let h = Hash::new(FOX_VALUE);
+
+        let mut bytes = [0u8; Hash::HASH_SIZE];
+        bytes.copy_from_slice(h.as_ref());
+        let h_bytes = Hash::from_bytes(&bytes);
+        assert_eq!(h_bytes, h);
+        assert!(h_bytes.ct_eq(&h));
+        assert_eq!(h_bytes, *h.as_ref());
+        assert_eq!(h_bytes.as_ref(), h.as_ref());
+
+        let stored = h.as_ref().to_vec();
+        let h_slice = Hash::try_from(&stored[..]).unwrap();
+        assert!(h_slice.ct_eq(&h));
+        assert_eq!(h_slice, hex::decode(FOX_HASH).unwrap()[..]);
+
+        for len in [0, 32, Hash::HASH_SIZE - 1, Hash::HASH_SIZE + 1] {
+            match Hash::try_from(&vec![0u8; len][..]).unwrap_err() {
+                HashParseError::InvalidLength { expected, found } => {
+                    assert_eq!(expected, Hash::HASH_SIZE);
+                    assert_eq!(found, len);
+                }
+            }
+        }
+    }
 }
@@ -1,10 +1,12 @@
 //! Secret Keys
 
+use crate::errors::KeyParseError;
 use crate::insecure_clone::private::InsecureClone;
 use crate::serialization::cleartext_serialization::*;
 use crate::serialization::errors::*;
 
 use rand::prelude::*;
+use subtle::{Choice, ConditionallySelectable, ConstantTimeEq};
 
 use zeroize::{Zeroize, Zeroizing};
 
@@ -50,6 +52,54 @@ pub trait Key: InsecureClone + Zeroize {
     /// assert_eq!(buf[..], [0u8; 32]);
     /// ```
     fn from_slice(bytes: &mut [u8]) -> Self;
+
+    /// Compares two keys in constant time
+    ///
+    /// # Example
+    /// ```
+    /// # extern crate crypto_tk_rs;
+    /// use crypto_tk_rs::{Key256, Key};
+    ///
+    /// let k1 = Key256::from_slice(&mut [1u8; 32]);
+    /// let k2 = Key256::from_slice(&mut [1u8; 32]);
+    /// assert!(k1.ct_eq(&k2));
+    /// assert!(!k1.ct_eq(&Key256::new()));
+    /// ```
+    fn ct_eq(&self, other: &Self) -> bool;
+}
+
+/// Test-only override of the randomness used by `Key::new`.
+///
+/// Once a seed has been installed with `install_seed`, the keys created by
+/// `Key::new` on the current thread are generated from a seeded PRNG instead
+/// of the OS CSPRNG, which makes the tests relying on them reproducible. This
+/// module is only compiled for tests and has no effect on other builds.
+#[cfg(test)]
+pub(crate) mod deterministic {
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+    use std::cell::RefCell;
+
+    thread_local! {
+        static TEST_RNG: RefCell<Option<StdRng>> = const { RefCell::new(None) };
+    }
+
+    /// Generate the keys of the current thread from a PRNG seeded with `seed`
+    pub(crate) fn install_seed(seed: u64) {
+        TEST_RNG
+            .with(|rng| *rng.borrow_mut() = Some(StdRng::seed_from_u64(seed)));
+    }
+
+    /// Go back to generating the keys of the current thread from the OS
+    /// CSPRNG
+    pub(crate) fn clear_seed() {
+        TEST_RNG.with(|rng| *rng.borrow_mut() = None);
+    }
+
+    /// Call `f` with the installed PRNG, if any
+    pub(crate) fn with_rng<T>(f: impl FnOnce(&mut StdRng) -> T) -> Option<T> {
+        TEST_RNG.with(|rng| rng.borrow_mut().as_mut().map(f))
+    }
 }
 
 pub(crate) trait KeyAccessor {
@@ -88,6 +138,167 @@ impl Key256 {
     }
 }
 
+impl Key256 {
+    /// Construct a `Key256` key from a zeroizing vector of bytes, consuming
+    /// (and zeroizing) it. Returns an error if the vector is not `KEY_SIZE`
+    /// bytes long.
+    pub(crate) fn from_zeroizing_vec(
+        mut bytes: Zeroizing<Vec<u8>>,
+    ) -> Result<Key256, KeyParseError> {
+        if bytes.len() != Key256::KEY_SIZE {
+            return Err(KeyParseError::InvalidLength {
+                expected: Key256::KEY_SIZE,
+                found: bytes.len(),
+            });
+        }
+        Ok(Key256::from_slice(&mut bytes))
+    }
+
+    /// Construct a `Key256` key from a slice of bytes and zero the slice, or
+    /// return an error if the slice is not exactly `KEY_SIZE` bytes long.
+    /// Unlike `Key::from_slice`, this function never panics, and can be used
+    /// to parse untrusted inputs.
+    ///
+    /// # Warning
+    /// On success, the input slice `bytes` will be zero after the function
+    /// returns. On error, it is left untouched.
+    ///
+    /// # Example
+    /// ```
+    /// # extern crate crypto_tk_rs;
+    /// use crypto_tk_rs::Key256;
+    ///
+    /// let mut buf = [1u8; 32];
+    /// let k = Key256::try_from_slice(&mut buf[..]).unwrap();
+    /// assert_eq!(buf, [0u8; 32]);
+    ///
+    /// assert!(Key256::try_from_slice(&mut [1u8; 31][..]).is_err());
+    /// ```
+    pub fn try_from_slice(bytes: &mut [u8]) -> Result<Key256, KeyParseError> {
+        if bytes.len() != Key256::KEY_SIZE {
+            return Err(KeyParseError::InvalidLength {
+                expected: Key256::KEY_SIZE,
+                found: bytes.len(),
+            });
+        }
+        Ok(Key256::from_slice(bytes))
+    }
+}
+
+impl Key256 {
+    /// Split the key into `n` shares, such that the XOR of all the shares is
+    /// the key (n-out-of-n secret sharing). Any set of at most `n-1` shares is
+    /// uniformly random and independent from the key. The shares are zeroized
+    /// on drop.
+    ///
+    /// All the shares together give access to the raw bytes of the key (in
+    /// particular, `split(1)` returns them verbatim), so this function is only
+    /// available with the `expose-key-bytes` feature.
+    ///
+    /// # Panics
+    /// Panics if `n` is 0.
+    ///
+    /// # Example
+    /// ```
+    /// # extern crate crypto_tk_rs;
+    /// use crypto_tk_rs::{Key256, Key};
+    ///
+    /// let k = Key256::new();
+    /// let shares = k.split(3);
+    /// let share_refs: Vec<&[u8; 32]> = shares.iter().map(|s| &**s).collect();
+    ///
+    /// let k_combined = Key256::combine(&share_refs);
+    /// ```
+    #[cfg(feature = "expose-key-bytes")]
+    #[must_use]
+    pub fn split(&self, n: usize) -> Vec<Zeroizing<[u8; 32]>> {
+        assert!(n > 0, "A key must be split in at least one share");
+
+        let mut rng = thread_rng();
+        let mut last_share = Zeroizing::new(*self.content);
+        let mut shares = Vec::with_capacity(n);
+
+        for _ in 1..n {
+            let mut share = Zeroizing::new([0u8; 32]);
+            rng.fill_bytes(&mut *share);
+            last_share
+                .iter_mut()
+                .zip(share.iter())
+                .for_each(|(l, s)| *l ^= s);
+            shares.push(share);
+        }
+        shares.push(last_share);
+
+        shares
+    }
+
+    /// Combine shares of a key produced by `split`, by computing their XOR.
+    /// Combining an empty set of shares results in the all-zero key.
+    #[must_use]
+    pub fn combine(shares: &[&[u8; 32]]) -> Key256 {
+        let mut content = [0u8; 32];
+
+        for share in shares {
+            content
+                .iter_mut()
+                .zip(share.iter())
+                .for_each(|(c, s)| *c ^= s);
+        }
+
+        // zeroizes the local buffer
+        Key256::from_bytes(&mut content)
+    }
+}
+
+impl Key256 {
+    /// Select `a` if `choice` is 0, and `b` if `choice` is 1, in constant time:
+    /// the execution does not branch on the value of `choice`.
+    ///
+    /// # Example
+    /// ```
+    /// # extern crate crypto_tk_rs;
+    /// use crypto_tk_rs::{Key, Key256};
+    /// use subtle::Choice;
+    ///
+    /// let a = Key256::new();
+    /// let b = Key256::new();
+    /// let k = Key256::conditional_select(&a, &b, Choice::from(1));
+    /// ```
+    #[must_use]
+    pub fn conditional_select(
+        a: &Key256,
+        b: &Key256,
+        choice: Choice,
+    ) -> Key256 {
+        let mut k = Key256 {
+            content: Zeroizing::new([0u8; 32]),
+            _marker: std::marker::PhantomPinned,
+        };
+        k.content
+            .iter_mut()
+            .zip(a.content.iter().zip(b.content.iter()))
+            .for_each(|(k, (a, b))| *k = u8::conditional_select(a, b, choice));
+        k
+    }
+}
+
+impl From<[u8; 32]> for Key256 {
+    /// Construct a `Key256` key from an owned array of bytes. The array is
+    /// moved into the function, and its local copy is zeroized before
+    /// returning.
+    ///
+    /// # Example
+    /// ```
+    /// # extern crate crypto_tk_rs;
+    /// use crypto_tk_rs::Key256;
+    ///
+    /// let k = Key256::from([1u8; 32]);
+    /// ```
+    fn from(mut bytes: [u8; 32]) -> Self {
+        Key256::from_bytes(&mut bytes)
+    }
+}
+
 impl Key for Key256 {
     const KEY_SIZE: usize = 32;
 
@@ -104,6 +315,11 @@ impl Key for Key256 {
     }
 
     fn new() -> Self {
+        #[cfg(test)]
+        if let Some(k) = deterministic::with_rng(Key256::generate) {
+            return k;
+        }
+
         let mut rng = thread_rng();
         Key256::generate(&mut rng)
     }
@@ -119,6 +335,10 @@ impl Key for Key256 {
 
         k
     }
+
+    fn ct_eq(&self, other: &Self) -> bool {
+        self.content().ct_eq(other.content()).into()
+    }
 }
 
 impl Zeroize for Key256 {
@@ -154,7 +374,7 @@ impl SerializableCleartextContent for Key256 {
         writer: &mut dyn std::io::Write,
     ) -> Result<usize, std::io::Error> {
         match writer.write_all(self.content()) {
-            Ok(_) => Ok(Key256::KEY_SIZE),
+            Ok(()) => Ok(Key256::KEY_SIZE),
             Err(e) => Err(e),
         }
     }
@@ -173,8 +393,223 @@ impl DeserializableCleartextContent for Key256 {
         Ok(k)
     }
 }
+
+/// Serde support for `Key256`, as a byte array.
+///
+/// As this exposes the raw key material, it is only available when both the
+/// `serde` and `expose-key-bytes` features are enabled.
+#[cfg(all(feature = "serde", feature = "expose-key-bytes"))]
+mod serde_support {
+    use super::{Key, Key256, KeyAccessor};
+    use serde::de::{Deserialize, Deserializer, Error, SeqAccess, Visitor};
+    use serde::ser::{Serialize, Serializer};
+    use zeroize::{Zeroize, Zeroizing};
+
+    impl Serialize for Key256 {
+        fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+        where
+            S: Serializer,
+        {
+            serializer.serialize_bytes(self.content())
+        }
+    }
+
+    struct Key256Visitor;
+
+    impl<'de> Visitor<'de> for Key256Visitor {
+        type Value = Key256;
+
+        fn expecting(
+            &self,
+            formatter: &mut std::fmt::Formatter<'_>,
+        ) -> std::fmt::Result {
+            write!(formatter, "an array of {} bytes", Key256::KEY_SIZE)
+        }
+
+        fn visit_bytes<E>(self, v: &[u8]) -> Result<Key256, E>
+        where
+            E: Error,
+        {
+            if v.len() != Key256::KEY_SIZE {
+                return Err(E::invalid_length(v.len(), &self));
+            }
+            let mut buf = Zeroizing::new([0u8; 32]);
+            buf.copy_from_slice(v);
+            Ok(Key256::from_bytes(&mut buf))
+        }
+
+        fn visit_byte_buf<E>(self, mut v: Vec<u8>) -> Result<Key256, E>
+        where
+            E: Error,
+        {
+            let k = self.visit_bytes(&v);
+            v.zeroize();
+            k
+        }
+
+        fn visit_seq<A>(self, mut seq: A) -> Result<Key256, A::Error>
+        where
+            A: SeqAccess<'de>,
+        {
+            let mut buf = Zeroizing::new([0u8; 32]);
+            for (i, b) in buf.iter_mut().enumerate() {
+                *b = seq
+                    .next_element()?
+                    .ok_or_else(|| A::Error::invalid_length(i, &self))?;
+            }
+            // reject longer inputs
+            let mut len = Key256::KEY_SIZE;
+            while seq.next_element::<u8>()?.is_some() {
+                len += 1;
+            }
+            if len != Key256::KEY_SIZE {
+                return Err(A::Error::invalid_length(len, &self));
+            }
+            Ok(Key256::from_bytes(&mut buf))
+        }
+    }
+
+    impl<'de> Deserialize<'de> for Key256 {
+        fn deserialize<D>(deserializer: D) -> Result<Key256, D::Error>
+        where
+            D: Deserializer<'de>,
+        {
+            deserializer.deserialize_bytes(Key256Visitor)
+        }
+    }
+}
+
+/// A 128 bits (16 bytes) secret key. The key is zeroed upon drop.
+pub struct Key128 {
+    content: Zeroizing<[u8; 16]>,
+    _marker: std::marker::PhantomPinned,
+}
+
+impl Key128 {
+    /// Construct a `Key128` key from a slice of bytes and zero the slice.
+    ///
+    /// # Warning
+    /// The input slice `randomness` will be zero after the function returns.
+    ///
+    /// # Example
+    /// ```
+    /// # extern crate crypto_tk_rs;
+    /// use crypto_tk_rs::Key128;
+    ///
+    /// /// Initializes a new key with bytes all set to `0x01`
+    /// let mut buf = [1u8;16];
+    /// let k = Key128::from_bytes(&mut buf);
+    /// /// buf is set to all 0
+    /// assert_eq!(buf, [0u8; 16]);
+    /// ```
+    pub fn from_bytes(randomness: &mut [u8; 16]) -> Key128 {
+        let k = Key128 {
+            content: Zeroizing::new(*randomness),
+            _marker: std::marker::PhantomPinned,
+        };
+        randomness.zeroize();
+        k
+    }
+}
+
+impl Key for Key128 {
+    const KEY_SIZE: usize = 16;
+
+    fn generate<R>(csprng: &mut R) -> Self
+    where
+        R: CryptoRng + RngCore,
+    {
+        let mut k = Key128 {
+            content: Zeroizing::new([0u8; 16]),
+            _marker: std::marker::PhantomPinned,
+        };
+        csprng.fill_bytes(&mut *k.content);
+        k
+    }
+
+    fn new() -> Self {
+        #[cfg(test)]
+        if let Some(k) = deterministic::with_rng(Key128::generate) {
+            return k;
+        }
+
+        let mut rng = thread_rng();
+        Key128::generate(&mut rng)
+    }
+
+    fn from_slice(bytes: &mut [u8]) -> Self {
+        let mut k = Self {
+            content: Zeroizing::new([0u8; 16]),
+            _marker: std::marker::PhantomPinned,
+        };
+
+        k.content.copy_from_slice(bytes);
+        bytes.zeroize();
+
+        k
+    }
+
+    fn ct_eq(&self, other: &Self) -> bool {
+        self.content().ct_eq(other.content()).into()
+    }
+}
+
+impl Zeroize for Key128 {
+    fn zeroize(&mut self) {
+        self.content.zeroize();
+    }
+}
+
+impl InsecureClone for Key128 {
+    fn insecure_clone(&self) -> Self {
+        Self {
+            content: self.content.clone(),
+            _marker: std::marker::PhantomPinned,
+        }
+    }
+}
+
+impl KeyAccessor for Key128 {
+    /// Get the content of the key
+    /// This accessor in only available to `crypto-tk` crate.
+    fn content(&self) -> &[u8] {
+        &*self.content
+    }
+}
+
+impl SerializableCleartextContent for Key128 {
+    fn serialization_content_byte_size(&self) -> usize {
+        Key128::KEY_SIZE
+    }
+
+    fn serialize_content(
+        &self,
+        writer: &mut dyn std::io::Write,
+    ) -> Result<usize, std::io::Error> {
+        match writer.write_all(self.content()) {
+            Ok(()) => Ok(Key128::KEY_SIZE),
+            Err(e) => Err(e),
+        }
+    }
+}
+
+impl DeserializableCleartextContent for Key128 {
+    fn deserialize_content(
+        reader: &mut dyn std::io::Read,
+    ) -> Result<Self, CleartextContentDeserializationError> {
+        let mut k = Key128 {
+            content: Zeroizing::new([0u8; 16]),
+            _marker: std::marker::PhantomPinned,
+        };
+
+        reader.read_exact(&mut (*k.content))?;
+        Ok(k)
+    }
+}
+
 #[cfg(test)]
 mod tests {
+    #![allow(clippy::unwrap_used)]
     use super::*;
 
     #[test]
@@ -202,4 +637,173 @@ mod tests {
         assert_eq!(buf, [0u8; 32]);
         assert_eq!(buf_copy_2, [0u8; 32]);
     }
+
+    #[cfg(all(feature = "serde", feature = "expose-key-bytes"))]
+    #[test]
+    fn serde_roundtrip() {
+        let k = Key256::new();
+        let serialized = serde_json::to_string(&k).unwrap();
+        let k_deser: Key256 = serde_json::from_str(&serialized).unwrap();
+        assert_eq!(k.content(), k_deser.content());
+    }
+
+    #[cfg(all(feature = "serde", feature = "expose-key-bytes"))]
+    #[test]
+    fn serde_invalid_length() {
+        for len in [0usize, 31, 33] {
+            let serialized = serde_json::to_string(&vec![1u8; len]).unwrap();
+            assert!(serde_json::from_str::<Key256>(&serialized).is_err());
+        }
+    }
+
+    #[test]
+    fn conditional_select() {
+        let a = Key256::new();
+        let b = Key256::new();
+
+        let k_a = Key256::conditional_select(&a, &b, Choice::from(0));
+        let k_b = Key256::conditional_select(&a, &b, Choice::from(1));
+        assert_eq!(k_a.content(), a.content());
+        assert_eq!(k_b.content(), b.content());
+
+        // the selected key owns its own (zeroizable) copy of the content
+        let mut k = Key256::conditional_select(&a, &b, Choice::from(1));
+        k.zeroize();
+        assert_eq!(k.content(), &[0u8; 32]);
+        assert_eq!(k_b.content(), b.content());
+    }
+
+    #[test]
+    fn try_build_from_slice() {
+        let mut buf = [0x42u8; 33];
+
+        // too short
+        match Key256::try_from_slice(&mut buf[..31]) {
+            Err(KeyParseError::InvalidLength { expected, found }) => {
+                assert_eq!(expected, 32);
+                assert_eq!(found, 31);
+            }
+            _ => panic!("Expected an invalid length error"),
+        }
+        // too long
+        match Key256::try_from_slice(&mut buf[..]) {
+            Err(KeyParseError::InvalidLength { expected, found }) => {
+                assert_eq!(expected, 32);
+                assert_eq!(found, 33);
+            }
+            _ => panic!("Expected an invalid length error"),
+        }
+        assert!(Key256::try_from_slice(&mut []).is_err());
+        // the input is untouched on error
+        assert_eq!(buf, [0x42u8; 33]);
+
+        // exact length
+        let k = Key256::try_from_slice(&mut buf[1..]).unwrap();
+        assert_eq!(*k.content, [0x42u8; 32]);
+        assert_eq!(buf[1..], [0u8; 32]);
+        assert_eq!(buf[0], 0x42);
+    }
+
+    #[cfg(feature = "expose-key-bytes")]
+    #[test]
+    fn split_combine() {
+        let k = Key256::new();
+
+        for n in 1..5 {
+            let shares = k.split(n);
+            assert_eq!(shares.len(), n);
+
+            let share_refs: Vec<&[u8; 32]> =
+                shares.iter().map(|s| &**s).collect();
+            assert_eq!(*Key256::combine(&share_refs).content, *k.content);
+        }
+    }
+
+    #[test]
+    fn combine_shares() {
+        assert_eq!(*Key256::combine(&[]).content, [0u8; 32]);
+
+        let shares = [[0x0fu8; 32], [0xffu8; 32], [0x01u8; 32]];
+        let share_refs: Vec<&[u8; 32]> = shares.iter().collect();
+        assert_eq!(*Key256::combine(&share_refs).content, [0xf1u8; 32]);
+    }
+
+    #[cfg(feature = "expose-key-bytes")]
+    #[test]
+    fn split_share_distribution() {
+        // the shares of the all-zero key should look uniformly random: count
+        // the bits set to 1 in the first share over many splits
+        const N_SPLITS: u32 = 200;
+        let k = Key256::from([0u8; 32]);
+
+        let mut ones = 0u32;
+        for _ in 0..N_SPLITS {
+            let shares = k.split(3);
+            for share in &shares {
+                assert_ne!(**share, [0u8; 32]);
+            }
+            ones += shares[0].iter().map(|b| b.count_ones()).sum::<u32>();
+        }
+
+        // the expected value is 25600 and the standard deviation ~113
+        let expected = N_SPLITS * 256 / 2;
+        assert!(ones > expected - 1000 && ones < expected + 1000);
+    }
+
+    #[test]
+    fn build_from_array() {
+        let mut buf = [0u8; 32];
+        for (i, b) in buf.iter_mut().enumerate() {
+            *b = i as u8;
+        }
+
+        let k = Key256::from(buf);
+        assert_eq!(*k.content, buf);
+    }
+
+    #[test]
+    fn deterministic_new() {
+        deterministic::install_seed(0xdead_beef);
+        let k1 = Key256::new();
+        let k2 = Key256::new();
+
+        deterministic::install_seed(0xdead_beef);
+        let k1_dup = Key256::new();
+        let k2_dup = Key256::new();
+
+        deterministic::install_seed(0xdead_bef0);
+        let k_other = Key256::new();
+
+        deterministic::clear_seed();
+        let k_random = Key256::new();
+
+        assert_eq!(k1.content, k1_dup.content);
+        assert_eq!(k2.content, k2_dup.content);
+        assert_ne!(k1.content, k2.content);
+        assert_ne!(k1.content, k_other.content);
+        assert_ne!(k1.content, k_random.content);
+    }
+
+    #[test]
+    fn build_from_128() {
+        let mut buf: [u8; 16] = [
+            0x00, 0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x07, 0x08, 0x09, 0x0A,
+            0x0B, 0x0C, 0x0D, 0x0E, 0x0F,
+        ];
+
+        let buf_copy_1 = buf;
+        let mut buf_copy_2 = buf;
+
+        let k1 = Key128::from_bytes(&mut buf);
+        let k2 = Key128::from_slice(&mut buf_copy_2[..]);
+
+        assert_eq!(*k1.content, buf_copy_1);
+        assert_eq!(*k2.content, buf_copy_1);
+
+        assert_eq!(k1.content, k1.insecure_clone().content);
+
+        // check that the buffer we built the key from has been cleared
+        assert_eq!(buf, [0u8; 16]);
+        assert_eq!(buf_copy_2, [0u8; 16]);
+    }
 }
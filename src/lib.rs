@@ -54,6 +54,7 @@
 mod insecure_clone;
 
 pub mod aead_cipher;
+pub mod aead_stream;
 pub mod cipher;
 pub mod errors;
 pub mod hash;
@@ -63,9 +64,12 @@ pub mod prg;
 pub mod rcprf;
 pub mod serialization;
 pub mod utils;
+#[cfg(feature = "xchacha")]
+pub mod xcipher;
 
 // Export everything public in modules
 pub use crate::aead_cipher::*;
+pub use crate::aead_stream::*;
 pub use crate::cipher::*;
 pub use crate::errors::*;
 pub use crate::hash::*;
@@ -76,3 +80,5 @@ pub use crate::prg::*;
 pub use crate::rcprf::*;
 pub use crate::serialization::*;
 pub use crate::utils::*;
+#[cfg(feature = "xchacha")]
+pub use crate::xcipher::*;
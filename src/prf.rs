@@ -1,12 +1,16 @@
 //! Pseudo-random function
 
+use crate::errors::KeyParseError;
 use crate::insecure_clone::{private::InsecureClone, CryptographyClone};
-use crate::key::{Key, Key256, KeyAccessor};
+use crate::key::{Key, Key128, Key256, KeyAccessor};
 use crate::serialization::cleartext_serialization::*;
 use crate::serialization::errors::*;
+use crate::utils::int_ceil_div;
 
 use clear_on_drop::clear::Clear;
-use zeroize::Zeroize;
+use rand::{CryptoRng, RngCore};
+use subtle::{Choice, ConditionallySelectable, ConstantTimeEq};
+use zeroize::{Zeroize, Zeroizing};
 
 /// Pseudo random function.
 ///
@@ -52,6 +56,70 @@ impl Prf {
         Prf { key }
     }
 
+    /// Construct a PRF from the bytes of a 256 bits key, e.g. coming out of a
+    /// KDF. The input is consumed and zeroized. Returns an error if `bytes`
+    /// is not 32 bytes long.
+    pub fn from_key_bytes(
+        bytes: Zeroizing<Vec<u8>>,
+    ) -> Result<Prf, KeyParseError> {
+        Ok(Prf::from_key(Key256::from_zeroizing_vec(bytes)?))
+    }
+
+    /// Evaluate the PRF keyed with `key` on `input`, with an output of
+    /// `output_len` bytes.
+    /// This is a stable entry point to generate `(key, input, output_len,
+    /// output)` test vectors, e.g. to validate this implementation against the
+    /// original C++ `crypto-tk` implementation. It is equivalent to calling
+    /// `fill_bytes` on a PRF built from `key`.
+    ///
+    /// # Example
+    /// ```
+    /// # extern crate crypto_tk_rs;
+    /// use crypto_tk_rs::{Key256, Prf};
+    ///
+    /// let key = Key256::from_bytes(&mut [0u8; 32]);
+    /// let output = Prf::test_vector(&key, b"input", 16);
+    /// assert_eq!(hex::encode(output), "f365092b1e8a33e9bf3200d2de270e79");
+    /// ```
+    #[must_use]
+    pub fn test_vector(
+        key: &Key256,
+        input: &[u8],
+        output_len: usize,
+    ) -> Vec<u8> {
+        let mut output = vec![0u8; output_len];
+        Prf::from_key(key.insecure_clone()).fill_bytes(input, &mut output);
+        output
+    }
+
+    /// Returns a copy of the key of the PRF, e.g. to construct another
+    /// primitive sharing the same key material when the protocol requires it.
+    /// As for `cryptography_clone`, this deliberately duplicates the secret
+    /// key: using the same key for different primitives can lead to security
+    /// issues, and deriving independent keys should be preferred.
+    ///
+    /// # Example
+    /// ```
+    /// # extern crate crypto_tk_rs;
+    /// use crypto_tk_rs::{Prf, Prg};
+    ///
+    /// let prf = Prf::new();
+    /// let prg = Prg::from_key(prf.to_key());
+    /// ```
+    #[must_use]
+    pub fn to_key(&self) -> Key256 {
+        self.key.insecure_clone()
+    }
+
+    /// Construct a PRF from a new key generated with the `csprng` random
+    /// generator, e.g. for reproducible tests or custom entropy sources
+    pub fn from_rng<R>(csprng: &mut R) -> Prf
+    where
+        R: CryptoRng + RngCore,
+    {
+        Prf::from_key(Key256::generate(csprng))
+    }
+
     /// Construct a PRF from a new random key
     #[allow(clippy::new_without_default)]
     // This is done on purpose to avoid
@@ -63,6 +131,78 @@ impl Prf {
         Prf { key }
     }
 
+    /// Maximum length, in bytes, of the tags accepted by `verify_truncated`
+    pub const MAX_TAG_LENGTH: usize = blake2b_simd::OUTBYTES;
+
+    /// Check, in constant time, that `tag` is the evaluation of the PRF on
+    /// `input`, using an output of `tag.len()` bytes, i.e. that `tag` is a
+    /// valid truncated MAC of `input`.
+    /// Returns `false` if `tag` is empty or longer than `MAX_TAG_LENGTH`.
+    ///
+    /// Note that, as the output length is a parameter of the PRF, a tag of
+    /// length `n` is not the prefix of a tag of length `m > n`: tags must be
+    /// computed directly with the length they are verified with.
+    #[must_use]
+    pub fn verify_truncated(&self, input: &[u8], tag: &[u8]) -> bool {
+        if tag.is_empty() || tag.len() > Prf::MAX_TAG_LENGTH {
+            return false;
+        }
+
+        let mut expected = [0u8; Prf::MAX_TAG_LENGTH];
+        self.fill_bytes(input, &mut expected[..tag.len()]);
+        let valid: bool = expected[..tag.len()].ct_eq(tag).into();
+        expected.zeroize();
+
+        valid
+    }
+
+    /// Length, in bytes, of the tags returned by `mac`
+    pub const MAC_LENGTH: usize = 16;
+
+    /// Returns an authentication tag of `data`, i.e. the evaluation of the
+    /// PRF on `data` with a `MAC_LENGTH` bytes output. The tag is checked
+    /// with `verify_mac`.
+    ///
+    /// # Example
+    /// ```
+    /// # extern crate crypto_tk_rs;
+    /// use crypto_tk_rs::Prf;
+    ///
+    /// let prf = Prf::new();
+    /// let tag = prf.mac(b"stored blob");
+    /// assert!(prf.verify_mac(b"stored blob", &tag));
+    /// assert!(!prf.verify_mac(b"stored blub", &tag));
+    /// ```
+    #[must_use]
+    pub fn mac(&self, data: &[u8]) -> [u8; Prf::MAC_LENGTH] {
+        let mut tag = [0u8; Prf::MAC_LENGTH];
+        self.fill_bytes(data, &mut tag);
+        tag
+    }
+
+    /// Check, in constant time, that `tag` is the authentication tag of
+    /// `data` returned by `mac`.
+    #[must_use]
+    pub fn verify_mac(&self, data: &[u8], tag: &[u8; Prf::MAC_LENGTH]) -> bool {
+        self.verify_truncated(data, tag)
+    }
+
+    /// Number of additional pseudo-random bytes (128 bits) reduced by
+    /// `fill_bytes_reduced`, so as to make the bias of the reduction
+    /// negligible
+    pub const REDUCTION_EXTRA_BYTES: usize = 16;
+
+    /// Returns the number of Blake2b invocations needed by `fill_bytes` to
+    /// produce `output_len` bytes, i.e. the number of `OUTBYTES`-sized blocks
+    /// (the last one possibly being shorter) of the output.
+    #[must_use]
+    pub fn block_count(output_len: usize) -> usize {
+        if output_len == 0 {
+            return 0;
+        }
+        int_ceil_div(output_len, blake2b_simd::OUTBYTES)
+    }
+
     /// Fill a slice with pseudo-random bytes resulting from the PRF evaluation.
     /// The output length is used as a parameter of the PRF (think additional
     /// input data). The PRF is called in counter mode, so as to be able to
@@ -70,40 +210,212 @@ impl Prf {
     /// In this implementation based on Blake2b, the counter is input as the
     /// salt of the PRF evaluation.
     pub fn fill_bytes(&self, input: &[u8], output: &mut [u8]) {
-        let mut remaining_length = output.len();
-        let mut written_bytes = 0;
         let tot_output_len: u64 = output.len() as u64;
+
+        for (i, block) in output.chunks_mut(blake2b_simd::OUTBYTES).enumerate()
+        {
+            self.fill_block(input, tot_output_len, i as u64, block);
+        }
+    }
+
+    /// Fill a slice of at most `blake2b_simd::OUTBYTES` (64) bytes with
+    /// pseudo-random bytes resulting from the PRF evaluation, with a single
+    /// Blake2b invocation. The output is the same as the one of `fill_bytes`.
+    ///
+    /// # Panics
+    /// Panics if `output` is longer than `blake2b_simd::OUTBYTES`.
+    pub fn fill_bytes_single_block(&self, input: &[u8], output: &mut [u8]) {
+        assert!(
+            output.len() <= blake2b_simd::OUTBYTES,
+            "The output must be at most {} bytes long",
+            blake2b_simd::OUTBYTES
+        );
+        if !output.is_empty() {
+            self.fill_block(input, output.len() as u64, 0, output);
+        }
+    }
+
+    /// Fill `output` with the big-endian encoding of a pseudo-random value
+    /// uniformly distributed (up to a statistical distance of 2^-128) between
+    /// 0 and `modulus` (excluded), where `modulus` is big-endian encoded too.
+    ///
+    /// The value is obtained by wide reduction: the PRF is evaluated on
+    /// `input` with an output `REDUCTION_EXTRA_BYTES` bytes longer than
+    /// `modulus` (as with `fill_bytes`), and the result is reduced modulo
+    /// `modulus`. The reduction runs in constant time.
+    ///
+    /// # Panics
+    /// Panics if `output` and `modulus` do not have the same length, or if
+    /// `modulus` is zero.
+    ///
+    /// # Example
+    /// ```
+    /// # extern crate crypto_tk_rs;
+    /// use crypto_tk_rs::Prf;
+    ///
+    /// let prf = Prf::new();
+    /// let modulus = [0x01, 0x00, 0x00]; // 2^16
+    /// let mut scalar = [0u8; 3];
+    /// prf.fill_bytes_reduced(b"input", &modulus, &mut scalar);
+    /// assert_eq!(scalar[0], 0);
+    /// ```
+    pub fn fill_bytes_reduced(
+        &self,
+        input: &[u8],
+        modulus: &[u8],
+        output: &mut [u8],
+    ) {
+        assert_eq!(
+            output.len(),
+            modulus.len(),
+            "The output and the modulus must have the same length"
+        );
+        assert!(
+            modulus.iter().any(|&b| b != 0),
+            "The modulus must not be zero"
+        );
+
+        let mut wide = Zeroizing::new(vec![
+            0u8;
+            modulus.len()
+                + Prf::REDUCTION_EXTRA_BYTES
+        ]);
+        self.fill_bytes(input, &mut wide);
+
+        reduce_be(&wide, modulus, output);
+    }
+
+    /// Fill the slices of `outputs` with pseudo-random bytes resulting from
+    /// the PRF evaluation, as if they were a single contiguous slice whose
+    /// length is the sum of the lengths of the slices: the result is the same
+    /// as calling `fill_bytes` on the concatenation of the slices.
+    ///
+    /// # Example
+    /// ```
+    /// # extern crate crypto_tk_rs;
+    /// use crypto_tk_rs::Prf;
+    ///
+    /// let prf = Prf::new();
+    /// let mut a = [0u8; 10];
+    /// let mut b = [0u8; 70];
+    /// prf.fill_bytes_vectored(b"input", &mut [&mut a[..], &mut b[..]]);
+    ///
+    /// let mut expected = [0u8; 80];
+    /// prf.fill_bytes(b"input", &mut expected);
+    /// assert_eq!(a[..], expected[..10]);
+    /// assert_eq!(b[..], expected[10..]);
+    /// ```
+    pub fn fill_bytes_vectored(&self, input: &[u8], outputs: &mut [&mut [u8]]) {
+        // the total length is a parameter of the PRF: it must be known before
+        // computing the first block
+        let tot_output_len: usize = outputs.iter().map(|o| o.len()).sum();
+        let mut remaining_length = tot_output_len;
+        let mut block = Zeroizing::new([0u8; blake2b_simd::OUTBYTES]);
+        let mut slices = outputs.iter_mut().flat_map(|o| o.iter_mut());
         let mut i = 0u64;
 
         while remaining_length > 0 {
             let out_length = remaining_length.min(blake2b_simd::OUTBYTES);
 
-            let mut params = blake2b_simd::Params::new();
-            params.key(self.key.content());
-            params.hash_length(out_length);
-            params.salt(&i.to_le_bytes());
-            params.personal(&tot_output_len.to_le_bytes());
+            self.fill_block(
+                input,
+                tot_output_len as u64,
+                i,
+                &mut block[..out_length],
+            );
+            // the block must come first, so that no byte of the outputs is
+            // skipped when the block is exhausted
+            block[..out_length]
+                .iter()
+                .zip(slices.by_ref())
+                .for_each(|(b, o)| *o = *b);
 
-            let mut state = params.to_state();
-            state.update(input);
+            remaining_length -= out_length;
+            i += 1;
+        }
+    }
+
+    /// Compute the `i`-th block of the PRF evaluation on `input` for an output
+    /// of length `tot_output_len`. The length of the block is the length of
+    /// `output`, and must be at most `OUTBYTES`.
+    fn fill_block(
+        &self,
+        input: &[u8],
+        tot_output_len: u64,
+        i: u64,
+        output: &mut [u8],
+    ) {
+        debug_assert!(output.len() <= blake2b_simd::OUTBYTES);
 
-            output[written_bytes..written_bytes + out_length]
-                .copy_from_slice(state.finalize().as_bytes());
+        let mut params = blake2b_simd::Params::new();
+        params.key(self.key.content());
+        params.hash_length(output.len());
+        params.salt(&i.to_le_bytes());
+        params.personal(&tot_output_len.to_le_bytes());
 
-            // cleanup
-            params.clear();
-            // we must clean the hash variable
-            // unfortunately, as blake2b_simd::Hash does not implement neither
-            // the Default nor the Zeroize trait, we cannot do that simply
+        let mut state = params.to_state();
+        state.update(input);
 
-            state.clear();
+        output.copy_from_slice(state.finalize().as_bytes());
 
-            remaining_length = remaining_length.saturating_sub(out_length);
-            written_bytes = written_bytes.saturating_add(out_length);
-            i += 1;
+        // cleanup
+        params.clear();
+        // we must clean the hash variable
+        // unfortunately, as blake2b_simd::Hash does not implement neither
+        // the Default nor the Zeroize trait, we cannot do that simply
+
+        state.clear();
+    }
+}
+
+/// Reduce the big-endian encoded `value` modulo the (non-zero) big-endian
+/// encoded `modulus`, and write the result in `output` (of the same length as
+/// `modulus`). The reduction processes `value` bit by bit, without branching on
+/// secret data.
+fn reduce_be(value: &[u8], modulus: &[u8], output: &mut [u8]) {
+    debug_assert_eq!(output.len(), modulus.len());
+
+    // invariant: output < modulus
+    output.fill(0);
+    let mut diff = Zeroizing::new(vec![0u8; modulus.len()]);
+
+    for byte in value {
+        for bit in (0..8).rev() {
+            // output = 2*output + bit, with `carry` being the top bit
+            let mut carry = (byte >> bit) & 1;
+            for limb in output.iter_mut().rev() {
+                let v = (u16::from(*limb) << 1) | u16::from(carry);
+                *limb = v as u8;
+                carry = (v >> 8) as u8;
+            }
+
+            // diff = output - modulus
+            let mut borrow = 0u8;
+            for ((d, o), m) in
+                diff.iter_mut().zip(output.iter()).zip(modulus.iter()).rev()
+            {
+                let v = u16::from(*o)
+                    .wrapping_sub(u16::from(*m) + u16::from(borrow));
+                *d = v as u8;
+                borrow = (v >> 15) as u8;
+            }
+
+            // as output < 2*modulus, one subtraction is enough to restore the
+            // invariant
+            let subtract = Choice::from(carry | (borrow ^ 1));
+            output.iter_mut().zip(diff.iter()).for_each(|(o, d)| {
+                *o = u8::conditional_select(o, d, subtract);
+            });
         }
     }
 }
+
+/// Key derivation input used by `Cipher::rekey` and `AeadCipher::rekey`. Both
+/// ciphers derive their per-message keys from `NONCE_SIZE` bytes long
+/// nonces: as its length differs from `NONCE_SIZE`, this input never collides
+/// with a per-message key derivation.
+pub(crate) const REKEY_CONTEXT: &[u8] = b"rekey";
+
 /// Pseudo random function used to derive cryptographic keys.
 /// See `Prf` for more details of the PRF evaluation.
 pub struct KeyDerivationPrf<KeyType: Key> {
@@ -132,6 +444,8 @@ impl<KeyType: Key> InsecureClone for KeyDerivationPrf<KeyType> {
     }
 }
 
+impl<KeyType: Key> CryptographyClone for KeyDerivationPrf<KeyType> {}
+
 impl<KeyType: Key> KeyDerivationPrf<KeyType> {
     /// Construct a PRF for key derivation from a 256 bits key
     #[must_use]
@@ -163,6 +477,158 @@ impl<KeyType: Key> KeyDerivationPrf<KeyType> {
 
         KeyType::from_slice(&mut buf)
     }
+
+    /// Derive a new pseudo-random key from a `salt` and an
+    /// application-specific `info` string, following HKDF's conventions.
+    ///
+    /// Both parameters are prefixed by their little-endian encoded length
+    /// before being fed to the PRF, so that two different `(salt, info)`
+    /// pairs never result in the same PRF input (_e.g._ `("ab", "c")` and
+    /// `("a", "bc")`).
+    #[must_use]
+    pub fn derive_key_with_context(&self, salt: &[u8], info: &[u8]) -> KeyType {
+        let mut input = Vec::<u8>::with_capacity(
+            2 * std::mem::size_of::<u64>() + salt.len() + info.len(),
+        );
+        input.extend_from_slice(&(salt.len() as u64).to_le_bytes());
+        input.extend_from_slice(salt);
+        input.extend_from_slice(&(info.len() as u64).to_le_bytes());
+        input.extend_from_slice(info);
+
+        self.derive_key(&input)
+    }
+
+    /// Ratchet the PRF: derive the key of the next state of a key chain
+    /// (using the `b"next"` input) and the output key of the current state
+    /// (using the `b"out"` input). Callers are expected to replace their
+    /// current PRF by the one built from the next-state key, and to erase the
+    /// current one, so that compromising a state does not reveal the previous
+    /// outputs.
+    #[must_use]
+    pub fn ratchet(&self) -> (Key256, KeyType) {
+        let mut next_buf = [0u8; Key256::KEY_SIZE];
+        self.prf.fill_bytes(b"next", &mut next_buf);
+
+        (Key256::from_bytes(&mut next_buf), self.derive_key(b"out"))
+    }
+}
+
+/// Short-output pseudo random function, meant for hash-table keying.
+///
+/// ## Evaluation algorithm
+///
+/// `ShortPrf` is based on SipHash-2-4, a fast PRF keyed with a 128 bits key
+/// and with a 64 bits output. It is much faster than `Prf` for small inputs,
+/// but its short output makes it unsuitable for key derivation: use it to
+/// compute keyed indices (_e.g._ in encrypted hash tables), and use `Prf`
+/// everywhere else.
+#[derive(Zeroize)]
+#[zeroize(drop)]
+pub struct ShortPrf {
+    key: Key128,
+}
+
+impl InsecureClone for ShortPrf {
+    fn insecure_clone(&self) -> Self {
+        ShortPrf {
+            key: self.key.insecure_clone(),
+        }
+    }
+}
+
+impl CryptographyClone for ShortPrf {}
+
+impl ShortPrf {
+    /// Construct a short PRF from a 128 bits key
+    #[must_use]
+    pub fn from_key(key: Key128) -> ShortPrf {
+        ShortPrf { key }
+    }
+
+    /// Construct a short PRF from a new random key
+    #[allow(clippy::new_without_default)]
+    // This is done on purpose to avoid
+    // involuntary creation of a PRF with
+    // a random key
+    #[must_use]
+    pub fn new() -> ShortPrf {
+        let key = Key128::new();
+        ShortPrf { key }
+    }
+
+    /// Evaluate the PRF on `input` and return the 64 bits result.
+    #[must_use]
+    pub fn eval_u64(&self, input: &[u8]) -> u64 {
+        siphash24(self.key.content(), input)
+    }
+}
+
+/// A `SipHash` round on the state `v`
+fn sip_round(v: &mut [u64; 4]) {
+    v[0] = v[0].wrapping_add(v[1]);
+    v[1] = v[1].rotate_left(13);
+    v[1] ^= v[0];
+    v[0] = v[0].rotate_left(32);
+    v[2] = v[2].wrapping_add(v[3]);
+    v[3] = v[3].rotate_left(16);
+    v[3] ^= v[2];
+    v[0] = v[0].wrapping_add(v[3]);
+    v[3] = v[3].rotate_left(21);
+    v[3] ^= v[0];
+    v[2] = v[2].wrapping_add(v[1]);
+    v[1] = v[1].rotate_left(17);
+    v[1] ^= v[2];
+    v[2] = v[2].rotate_left(32);
+}
+
+/// Compress a 64 bits block `m` into the `SipHash` state `v`, with 2 rounds
+fn sip_compress(v: &mut [u64; 4], m: u64) {
+    v[3] ^= m;
+    sip_round(v);
+    sip_round(v);
+    v[0] ^= m;
+}
+
+/// SipHash-2-4 with a 16 bytes key, as described in the [original
+/// paper](https://www.aumasson.jp/siphash/siphash.pdf).
+fn siphash24(key: &[u8], input: &[u8]) -> u64 {
+    let mut k = [0u8; 8];
+    k.copy_from_slice(&key[..8]);
+    let k0 = u64::from_le_bytes(k);
+    k.copy_from_slice(&key[8..16]);
+    let k1 = u64::from_le_bytes(k);
+    k.zeroize();
+
+    let mut v = [
+        k0 ^ 0x736f_6d65_7073_6575,
+        k1 ^ 0x646f_7261_6e64_6f6d,
+        k0 ^ 0x6c79_6765_6e65_7261,
+        k1 ^ 0x7465_6462_7974_6573,
+    ];
+
+    let mut chunks = input.chunks_exact(8);
+    for chunk in &mut chunks {
+        let mut m = [0u8; 8];
+        m.copy_from_slice(chunk);
+        sip_compress(&mut v, u64::from_le_bytes(m));
+    }
+
+    // the last block encodes the remaining bytes and the input length
+    let mut last_block = [0u8; 8];
+    let remainder = chunks.remainder();
+    last_block[..remainder.len()].copy_from_slice(remainder);
+    last_block[7] = input.len() as u8;
+    sip_compress(&mut v, u64::from_le_bytes(last_block));
+
+    v[2] ^= 0xff;
+    sip_round(&mut v);
+    sip_round(&mut v);
+    sip_round(&mut v);
+    sip_round(&mut v);
+
+    let res = v[0] ^ v[1] ^ v[2] ^ v[3];
+    v.zeroize();
+    res
 }
 
 impl SerializableCleartextContent for Prf {
@@ -185,6 +651,27 @@ impl DeserializableCleartextContent for Prf {
     }
 }
 
+impl SerializableCleartextContent for ShortPrf {
+    fn serialization_content_byte_size(&self) -> usize {
+        self.key.serialization_content_byte_size()
+    }
+
+    fn serialize_content(
+        &self,
+        writer: &mut dyn std::io::Write,
+    ) -> Result<usize, std::io::Error> {
+        self.key.serialize_content(writer)
+    }
+}
+
+impl DeserializableCleartextContent for ShortPrf {
+    fn deserialize_content(
+        reader: &mut dyn std::io::Read,
+    ) -> Result<Self, CleartextContentDeserializationError> {
+        Ok(ShortPrf::from_key(Key128::deserialize_content(reader)?))
+    }
+}
+
 impl<KeyType: Key> SerializableCleartextContent for KeyDerivationPrf<KeyType> {
     fn serialization_content_byte_size(&self) -> usize {
         self.prf.serialization_content_byte_size()
@@ -212,8 +699,89 @@ impl<KeyType: Key> DeserializableCleartextContent
 
 #[cfg(test)]
 mod tests {
+    #![allow(clippy::unwrap_used)]
     use super::*;
 
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    #[test]
+    fn seeded_generation() {
+        let prf_1 = Prf::from_rng(&mut StdRng::seed_from_u64(0xdead_beef));
+        let prf_2 = Prf::from_rng(&mut StdRng::seed_from_u64(0xdead_beef));
+        let prf_3 = Prf::from_rng(&mut StdRng::seed_from_u64(0xcafe));
+
+        let mut out_1 = [0u8; 32];
+        let mut out_2 = [0u8; 32];
+        let mut out_3 = [0u8; 32];
+        prf_1.fill_bytes(b"input", &mut out_1);
+        prf_2.fill_bytes(b"input", &mut out_2);
+        prf_3.fill_bytes(b"input", &mut out_3);
+
+        assert_eq!(out_1, out_2);
+        assert_ne!(out_1, out_3);
+    }
+
+    #[test]
+    fn known_answer_test_vectors() {
+        let mut key_bytes = [0u8; 32];
+        for (i, b) in key_bytes.iter_mut().enumerate() {
+            *b = i as u8;
+        }
+        let key = Key256::from_bytes(&mut key_bytes);
+
+        // These vectors can be reproduced with any Blake2b implementation
+        // supporting salt and personalization (e.g. Python's `hashlib`)
+        let vectors: [(&[u8], usize, &str); 3] = [
+            (b"", 16, "d7eceff2db086c3318bf325674d662c2"),
+            (
+                b"abc",
+                32,
+                "ee8d9ab374444904975840b1d1988639c6bd165d7094d6eca4538d05620498bc",
+            ),
+            (
+                b"abc",
+                100,
+                "a98a030042baf53502a8a3bbea18a80352cda6f53eb86c94a50c3b02ebceaeca\
+                 96e93782ad91352a081835614ed4e5f7fd8779bbe203bb4dfd76386df41eb316\
+                 b4bcb9083f92fba1aa72d7244f0c5fcbe2589c802f0dd41545b0d96e70a52af8\
+                 3b1ad045",
+            ),
+        ];
+
+        for (input, output_len, expected) in vectors {
+            let output = Prf::test_vector(&key, input, output_len);
+            assert_eq!(hex::encode(&output), expected);
+
+            let mut fill_output = vec![0u8; output_len];
+            Prf::from_key(key.insecure_clone())
+                .fill_bytes(input, &mut fill_output);
+            assert_eq!(output, fill_output);
+        }
+    }
+
+    #[test]
+    fn key_export() {
+        let prf = Prf::new();
+        let prg = crate::Prg::from_key(prf.to_key());
+        let prg_bis = crate::Prg::from_key(prf.to_key());
+
+        let mut out = [0u8; 32];
+        let mut out_bis = [0u8; 32];
+        prg.fill_pseudo_random_bytes(&mut out);
+        prg_bis.fill_pseudo_random_bytes(&mut out_bis);
+        assert_eq!(out, out_bis);
+        assert_ne!(out, [0u8; 32]);
+
+        // the PRF is still usable, and consistent with a PRF built from the
+        // exported key
+        let mut prf_out = [0u8; 32];
+        let mut prf_out_bis = [0u8; 32];
+        prf.fill_bytes(b"input", &mut prf_out);
+        Prf::from_key(prf.to_key()).fill_bytes(b"input", &mut prf_out_bis);
+        assert_eq!(prf_out, prf_out_bis);
+    }
+
     #[test]
     fn output_uniqueness() {
         const N_TRIES: usize = 20;
@@ -233,6 +801,338 @@ mod tests {
         }
     }
 
+    #[test]
+    fn fill_bytes_vectored() {
+        let prf = Prf::new();
+        let input = b"vectored input";
+
+        for lengths in [
+            &[][..],
+            &[0],
+            &[10],
+            &[0, 10, 0],
+            &[64, 64],
+            &[1, 63, 1],
+            &[30, 50, 20, 100, 3],
+            &[200],
+        ] {
+            let tot_len: usize = lengths.iter().sum();
+            let mut expected = vec![0u8; tot_len];
+            prf.fill_bytes(input, &mut expected);
+
+            let mut bufs: Vec<Vec<u8>> =
+                lengths.iter().map(|&l| vec![0u8; l]).collect();
+            let mut slices: Vec<&mut [u8]> =
+                bufs.iter_mut().map(|b| &mut b[..]).collect();
+            prf.fill_bytes_vectored(input, &mut slices);
+
+            assert_eq!(bufs.concat(), expected);
+        }
+    }
+
+    /// Reference reduction for moduli fitting in 64 bits
+    fn reference_reduction(value: &[u8], modulus: u64) -> u64 {
+        value.iter().fold(0u128, |acc, &b| {
+            ((acc << 8) | u128::from(b)) % u128::from(modulus)
+        }) as u64
+    }
+
+    #[test]
+    fn reduction() {
+        for modulus in [1u64, 2, 3, 255, 256, 257, 0xFFFF_FFFF_FFFF_FFC5] {
+            let modulus_bytes = modulus.to_be_bytes();
+            for len in [1usize, 2, 3, 4, 32] {
+                let mut value = vec![0u8; len];
+                rand::RngCore::fill_bytes(&mut rand::thread_rng(), &mut value);
+                let mut out = [0u8; 8];
+                reduce_be(&value, &modulus_bytes, &mut out);
+                assert_eq!(
+                    u64::from_be_bytes(out),
+                    reference_reduction(&value, modulus)
+                );
+            }
+
+            // edge values
+            for value in [[0u8; 8], [0xFFu8; 8], modulus_bytes] {
+                let mut out = [0u8; 8];
+                reduce_be(&value, &modulus_bytes, &mut out);
+                assert_eq!(
+                    u64::from_be_bytes(out),
+                    reference_reduction(&value, modulus)
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn fill_bytes_reduced() {
+        const N_SAMPLES: usize = 6000;
+        let prf = Prf::new();
+
+        // determinism, and consistency with `fill_bytes`
+        let modulus = 0xFFFF_FFFF_FFFF_FFC5u64.to_be_bytes();
+        let mut out_1 = [0u8; 8];
+        let mut out_2 = [0u8; 8];
+        prf.fill_bytes_reduced(b"input", &modulus, &mut out_1);
+        prf.fill_bytes_reduced(b"input", &modulus, &mut out_2);
+        assert_eq!(out_1, out_2);
+        prf.fill_bytes_reduced(b"other input", &modulus, &mut out_2);
+        assert_ne!(out_1, out_2);
+
+        let mut wide = [0u8; 8 + Prf::REDUCTION_EXTRA_BYTES];
+        prf.fill_bytes(b"input", &mut wide);
+        assert_eq!(
+            u64::from_be_bytes(out_1),
+            reference_reduction(&wide, 0xFFFF_FFFF_FFFF_FFC5)
+        );
+
+        // the outputs stay below the modulus, and are roughly uniform
+        let modulus = [0x00, 0x06];
+        let mut counts = [0usize; 6];
+        for i in 0..N_SAMPLES {
+            let mut out = [0u8; 2];
+            prf.fill_bytes_reduced(&i.to_le_bytes(), &modulus, &mut out);
+            let v = usize::from(u16::from_be_bytes(out));
+            assert!(v < 6);
+            counts[v] += 1;
+        }
+        // each count should be around N_SAMPLES/6 = 1000 (with a standard
+        // deviation of about 29)
+        for c in counts {
+            assert!((800..1200).contains(&c), "biased output: {:?}", counts);
+        }
+
+        let modulus = [0x80, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x01];
+        for i in 0..100u32 {
+            let mut out = [0u8; 8];
+            prf.fill_bytes_reduced(&i.to_le_bytes(), &modulus, &mut out);
+            assert!(out < modulus);
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "The modulus must not be zero")]
+    fn fill_bytes_reduced_zero_modulus() {
+        Prf::new().fill_bytes_reduced(b"input", &[0u8; 4], &mut [0u8; 4]);
+    }
+
+    #[test]
+    fn fill_bytes_single_block() {
+        let prf = Prf::new();
+
+        for len in [0usize, 1, 16, 32, 63, 64] {
+            let mut out = vec![0u8; len];
+            let mut expected = vec![0u8; len];
+            prf.fill_bytes_single_block(b"input", &mut out);
+            prf.fill_bytes(b"input", &mut expected);
+            assert_eq!(out, expected);
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "The output must be at most 64 bytes long")]
+    fn fill_bytes_single_block_too_long() {
+        Prf::new().fill_bytes_single_block(b"input", &mut [0u8; 65]);
+    }
+
+    #[test]
+    fn cryptography_clone() {
+        let prf = Prf::new();
+        let prf_dup = prf.cryptography_clone();
+
+        for input in [&b""[..], b"Foo", &[0u8; 64]] {
+            let mut out = [0u8; 100];
+            let mut out_dup = [0u8; 100];
+            prf.fill_bytes(input, &mut out);
+            prf_dup.fill_bytes(input, &mut out_dup);
+            assert_eq!(out, out_dup);
+        }
+
+        let kdf = KeyDerivationPrf::<Key256>::new();
+        let kdf_dup = kdf.cryptography_clone();
+        assert_eq!(
+            kdf.derive_key(b"Foo").content(),
+            kdf_dup.derive_key(b"Foo").content()
+        );
+    }
+
+    #[test]
+    fn no_length_extension() {
+        // Pairs of output lengths (short, long) such that the output of
+        // length `short` must not be a prefix of the output of length `long`.
+        // The pairs in which `short` is a multiple of the block size check
+        // that the total length is part of the evaluation of every block:
+        // without it, the blocks of the shorter output would be exactly the
+        // first blocks of the longer one.
+        const LENGTH_PAIRS: [(usize, usize); 8] = [
+            (8, 16),
+            (16, 32),
+            (32, 64),
+            (63, 64),
+            (64, 65),
+            (64, 128),
+            (128, 200),
+            (128, 256),
+        ];
+        let prf = Prf::new();
+        let input = b"length extension";
+
+        for (short, long) in LENGTH_PAIRS {
+            let mut short_out = vec![0u8; short];
+            let mut long_out = vec![0u8; long];
+
+            prf.fill_bytes(input, &mut short_out);
+            prf.fill_bytes(input, &mut long_out);
+
+            assert_ne!(
+                short_out[..],
+                long_out[..short],
+                "The {short} bytes output is a prefix of the {long} bytes one"
+            );
+
+            // no block of the short output appears in the long one
+            for (short_block, long_block) in short_out
+                .chunks(blake2b_simd::OUTBYTES)
+                .zip(long_out.chunks(blake2b_simd::OUTBYTES))
+            {
+                assert_ne!(short_block, &long_block[..short_block.len()]);
+            }
+        }
+    }
+
+    #[test]
+    fn ratchet() {
+        let k = Key256::new();
+        let prf = KeyDerivationPrf::<Key256>::from_key(k.insecure_clone());
+        let prf_dup = KeyDerivationPrf::<Key256>::from_key(k);
+
+        let (next, out) = prf.ratchet();
+        let (next_dup, out_dup) = prf_dup.ratchet();
+        assert_eq!(next.content(), next_dup.content());
+        assert_eq!(out.content(), out_dup.content());
+        assert_ne!(next.content(), out.content());
+
+        let mut states = vec![next.content().to_vec()];
+        let mut outputs = vec![out.content().to_vec()];
+        let mut state = KeyDerivationPrf::<Key256>::from_key(next);
+        for _ in 0..10 {
+            let (next, out) = state.ratchet();
+            states.push(next.content().to_vec());
+            outputs.push(out.content().to_vec());
+            state = KeyDerivationPrf::<Key256>::from_key(next);
+        }
+
+        for i in 0..states.len() {
+            for j in 0..i {
+                assert_ne!(states[i], states[j]);
+                assert_ne!(outputs[i], outputs[j]);
+            }
+        }
+    }
+
+    #[test]
+    fn from_key_bytes() {
+        let bytes = Zeroizing::new((0..32u8).collect::<Vec<u8>>());
+        let mut key_bytes = [0u8; 32];
+        key_bytes.copy_from_slice(&bytes);
+
+        let prf = Prf::from_key_bytes(bytes).unwrap();
+        let reference = Prf::from_key(Key256::from(key_bytes));
+
+        let mut out = [0u8; 32];
+        let mut out_ref = [0u8; 32];
+        prf.fill_bytes(b"input", &mut out);
+        reference.fill_bytes(b"input", &mut out_ref);
+        assert_eq!(out, out_ref);
+
+        for len in [0usize, 16, 31, 33, 64] {
+            match Prf::from_key_bytes(Zeroizing::new(vec![0u8; len])) {
+                Err(KeyParseError::InvalidLength { expected, found }) => {
+                    assert_eq!(expected, 32);
+                    assert_eq!(found, len);
+                }
+                _ => panic!("Expected an invalid length error"),
+            }
+        }
+    }
+
+    #[test]
+    fn mac() {
+        let prf = Prf::new();
+        let data = b"stored blob";
+
+        let tag = prf.mac(data);
+        assert!(prf.verify_mac(data, &tag));
+        assert_eq!(tag, prf.mac(data));
+
+        // the tag is the 16 bytes output of the PRF
+        let mut out = [0u8; 16];
+        prf.fill_bytes(data, &mut out);
+        assert_eq!(tag, out);
+
+        // tampered data
+        let mut tampered = data.to_vec();
+        for i in 0..tampered.len() {
+            tampered[i] ^= 0x01;
+            assert!(!prf.verify_mac(&tampered, &tag));
+            tampered[i] ^= 0x01;
+        }
+        assert!(!prf.verify_mac(&data[..data.len() - 1], &tag));
+        assert!(!prf.verify_mac(b"", &tag));
+
+        // tampered tag
+        for i in 0..Prf::MAC_LENGTH {
+            let mut tampered_tag = tag;
+            tampered_tag[i] ^= 0x80;
+            assert!(!prf.verify_mac(data, &tampered_tag));
+        }
+
+        // other key
+        assert!(!Prf::new().verify_mac(data, &tag));
+    }
+
+    #[test]
+    fn verify_truncated() {
+        let prf = Prf::new();
+        let input = b"message";
+
+        for len in [1usize, 8, 16, 32, Prf::MAX_TAG_LENGTH] {
+            let mut tag = vec![0u8; len];
+            prf.fill_bytes(input, &mut tag);
+            assert!(prf.verify_truncated(input, &tag));
+
+            // any modification of the tag or of the input is detected (short
+            // tags are too likely to collide for the input check)
+            if len >= 8 {
+                assert!(!prf.verify_truncated(b"messagf", &tag));
+            }
+            tag[len - 1] ^= 1;
+            assert!(!prf.verify_truncated(input, &tag));
+        }
+
+        // a prefix of a longer tag is not a valid truncated tag
+        let mut tag = [0u8; 32];
+        prf.fill_bytes(input, &mut tag);
+        assert!(!prf.verify_truncated(input, &tag[..16]));
+
+        // empty and over-long tags are rejected
+        assert!(!prf.verify_truncated(input, &[]));
+        let mut long_tag = vec![0u8; Prf::MAX_TAG_LENGTH + 1];
+        prf.fill_bytes(input, &mut long_tag);
+        assert!(!prf.verify_truncated(input, &long_tag));
+    }
+
+    #[test]
+    fn block_count() {
+        assert_eq!(Prf::block_count(0), 0);
+        assert_eq!(Prf::block_count(1), 1);
+        assert_eq!(Prf::block_count(63), 1);
+        assert_eq!(Prf::block_count(64), 1);
+        assert_eq!(Prf::block_count(65), 2);
+        assert_eq!(Prf::block_count(128), 2);
+        assert_eq!(Prf::block_count(129), 3);
+    }
+
     fn key_derivation<KeyType: Key + KeyAccessor>() {
         let k = Key256::new();
         let k_dup = k.insecure_clone();
@@ -252,4 +1152,135 @@ mod tests {
     fn key_derivation_256() {
         key_derivation::<Key256>();
     }
+
+    #[test]
+    fn siphash_test_vector() {
+        // Test vector from the appendix of the SipHash paper
+        let key: Vec<u8> = (0u8..16).collect();
+        let input: Vec<u8> = (0u8..15).collect();
+
+        assert_eq!(siphash24(&key, &input), 0xa129_ca61_49be_45e5);
+    }
+
+    #[test]
+    fn siphash_reference_vectors() {
+        // The 64 test vectors of the SipHash reference implementation
+        // (`vectors_sip64` in `vectors.h`), read as little-endian integers:
+        // the i-th vector is the hash of the bytes 0, 1, ..., i-1 with the
+        // key 0, 1, ..., 15.
+        const VECTORS: [u64; 64] = [
+            0x726f_db47_dd0e_0e31,
+            0x74f8_39c5_93dc_67fd,
+            0x0d6c_8009_d9a9_4f5a,
+            0x8567_6696_d7fb_7e2d,
+            0xcf27_94e0_2771_87b7,
+            0x1876_5564_cd99_a68d,
+            0xcbc9_466e_58fe_e3ce,
+            0xab02_00f5_8b01_d137,
+            0x93f5_f579_9a93_2462,
+            0x9e00_82df_0ba9_e4b0,
+            0x7a5d_bbc5_94dd_b9f3,
+            0xf4b3_2f46_226b_ada7,
+            0x751e_8fbc_860e_e5fb,
+            0x14ea_5627_c084_3d90,
+            0xf723_ca90_8e7a_f2ee,
+            0xa129_ca61_49be_45e5,
+            0x3f2a_cc7f_57c2_9bdb,
+            0x699a_e9f5_2cbe_4794,
+            0x4bc1_b3f0_968d_d39c,
+            0xbb6d_c91d_a779_61bd,
+            0xbed6_5cf2_1aa2_ee98,
+            0xd0f2_cbb0_2e3b_67c7,
+            0x9353_6795_e3a3_3e88,
+            0xa80c_038c_cd5c_cec8,
+            0xb8ad_50c6_f649_af94,
+            0xbce1_92de_8a85_b8ea,
+            0x17d8_35b8_5bbb_15f3,
+            0x2f2e_6163_076b_cfad,
+            0xde4d_aaac_a71d_c9a5,
+            0xa6a2_5066_8795_6571,
+            0xad87_a353_5c49_ef28,
+            0x32d8_92fa_d841_c342,
+            0x7127_512f_72f2_7cce,
+            0xa7f3_2346_f959_78e3,
+            0x12e0_b01a_bb05_1238,
+            0x15e0_34d4_0fa1_97ae,
+            0x314d_ffbe_0815_a3b4,
+            0x0279_90f0_2962_3981,
+            0xcadc_d4e5_9ef4_0c4d,
+            0x9abf_d876_6a33_735c,
+            0x0e3e_a96b_5304_a7d0,
+            0xad0c_42d6_fc58_5992,
+            0x1873_06c8_9bc2_15a9,
+            0xd4a6_0abc_f379_2b95,
+            0xf935_451d_e4f2_1df2,
+            0xa953_8f04_1975_5787,
+            0xdb9a_cddf_f56c_a510,
+            0xd06c_98cd_5c09_75eb,
+            0xe612_a3cb_9ecb_a951,
+            0xc766_e62c_fcad_af96,
+            0xee64_435a_9752_fe72,
+            0xa192_d576_b245_165a,
+            0x0a87_87bf_8ecb_74b2,
+            0x81b3_e73d_20b4_9b6f,
+            0x7fa8_220b_a3b2_ecea,
+            0x2457_31c1_3ca4_2499,
+            0xb78d_bfaf_3a8d_83bd,
+            0xea1a_d565_322a_1a0b,
+            0x60e6_1c23_a379_5013,
+            0x6606_d7e4_4628_2b93,
+            0x6ca4_ecb1_5c5f_91e1,
+            0x9f62_6da1_5c96_25f3,
+            0xe51b_3860_8ef2_5f57,
+            0x958a_324c_eb06_4572,
+        ];
+
+        let key: Vec<u8> = (0u8..16).collect();
+        for (i, expected) in VECTORS.iter().enumerate() {
+            let input: Vec<u8> = (0u8..i as u8).collect();
+            assert_eq!(siphash24(&key, &input), *expected, "vector {i}");
+        }
+    }
+
+    #[test]
+    fn short_prf_determinism() {
+        let prf = ShortPrf::new();
+        let prf_dup = prf.cryptography_clone();
+
+        for input in [&b""[..], b"Foo", b"FooBarFooBar", &[0u8; 64]] {
+            assert_eq!(prf.eval_u64(input), prf.eval_u64(input));
+            assert_eq!(prf.eval_u64(input), prf_dup.eval_u64(input));
+        }
+    }
+
+    #[test]
+    fn short_prf_key_dependency() {
+        let prf_1 = ShortPrf::new();
+        let prf_2 = ShortPrf::new();
+
+        for input in [&b""[..], b"Foo", b"FooBarFooBar", &[0u8; 64]] {
+            assert_ne!(prf_1.eval_u64(input), prf_2.eval_u64(input));
+        }
+    }
+
+    #[test]
+    fn key_derivation_with_context() {
+        let derivation_prf = KeyDerivationPrf::<Key256>::new();
+
+        let k1 = derivation_prf.derive_key_with_context(b"salt", b"info 1");
+        let k1_bis = derivation_prf.derive_key_with_context(b"salt", b"info 1");
+        let k2 = derivation_prf.derive_key_with_context(b"salt", b"info 2");
+        let k3 = derivation_prf.derive_key_with_context(b"", b"info 1");
+
+        // the same inputs give the same key
+        assert_eq!(k1.content(), k1_bis.content());
+        // different info or salt give different keys
+        assert_ne!(k1.content(), k2.content());
+        assert_ne!(k1.content(), k3.content());
+
+        // moving bytes between the salt and the info changes the key
+        let k4 = derivation_prf.derive_key_with_context(b"ab", b"c");
+        let k5 = derivation_prf.derive_key_with_context(b"a", b"bc");
+        assert_ne!(k4.content(), k5.content());
+    }
 }
@@ -1,6 +1,7 @@
 //! Pseudo-random generator
 
-use crate::insecure_clone::private::InsecureClone;
+use crate::errors::{KeyDerivationError, KeyParseError};
+use crate::insecure_clone::{private::InsecureClone, CryptographyClone};
 use crate::key::{Key, Key256, KeyAccessor};
 use crate::serialization::cleartext_serialization::*;
 use crate::serialization::errors::*;
@@ -8,8 +9,10 @@ use crate::serialization::errors::*;
 use chacha20::cipher::{KeyIvInit, StreamCipher, StreamCipherSeek};
 use chacha20::ChaCha20;
 use clear_on_drop::clear_stack_on_return;
-use zeroize::Zeroize;
+use rand::{CryptoRng, RngCore};
+use zeroize::{Zeroize, Zeroizing};
 
+use std::convert::TryFrom;
 use std::ops::Range;
 use std::vec::Vec;
 
@@ -32,15 +35,67 @@ use std::vec::Vec;
 #[zeroize(drop)]
 pub struct Prg {
     key: Key256,
+    nonce: [u8; Prg::NONCE_SIZE],
 }
 
 impl Prg {
-    const PRG_NONCE: [u8; 12] = [0u8; 12];
+    /// Size of the nonce of the PRG, in bytes
+    pub const NONCE_SIZE: usize = 12;
+
+    /// Nonce used by default, e.g. by `from_key`
+    const PRG_NONCE: [u8; Prg::NONCE_SIZE] = [0u8; Prg::NONCE_SIZE];
 
     /// Construct a PRG from a 256 bits key
     #[must_use]
     pub fn from_key(key: Key256) -> Prg {
-        Prg { key }
+        Prg::from_key_with_nonce(key, Self::PRG_NONCE)
+    }
+
+    /// Construct a PRG from a 256 bits key and a nonce. PRGs with the same key
+    /// and different nonces produce independent outputs, which allows to
+    /// domain-separate the streams of PRGs built from related keys. `from_key`
+    /// uses the all-zero nonce.
+    ///
+    /// # Example
+    /// ```
+    /// # extern crate crypto_tk_rs;
+    /// use crypto_tk_rs::{Key256, Prg};
+    ///
+    /// let key = Key256::from_bytes(&mut [1u8; 32]);
+    /// let other_key = Key256::from_bytes(&mut [1u8; 32]);
+    /// let prg = Prg::from_key_with_nonce(key, [0u8; 12]);
+    /// let other_prg = Prg::from_key_with_nonce(other_key, [1u8; 12]);
+    ///
+    /// let mut buf = [0u8; 32];
+    /// let mut other_buf = [0u8; 32];
+    /// prg.fill_pseudo_random_bytes(&mut buf);
+    /// other_prg.fill_pseudo_random_bytes(&mut other_buf);
+    /// assert_ne!(buf, other_buf);
+    /// ```
+    #[must_use]
+    pub fn from_key_with_nonce(
+        key: Key256,
+        nonce: [u8; Prg::NONCE_SIZE],
+    ) -> Prg {
+        Prg { key, nonce }
+    }
+
+    /// Construct a PRG from the bytes of a 256 bits key, e.g. coming out of a
+    /// KDF. The input is consumed and zeroized. Returns an error if `bytes`
+    /// is not 32 bytes long.
+    pub fn from_key_bytes(
+        bytes: Zeroizing<Vec<u8>>,
+    ) -> Result<Prg, KeyParseError> {
+        Ok(Prg::from_key(Key256::from_zeroizing_vec(bytes)?))
+    }
+
+    /// Construct a PRG from a new key generated with the `csprng` random
+    /// generator, e.g. for reproducible tests or custom entropy sources
+    pub fn from_rng<R>(csprng: &mut R) -> Prg
+    where
+        R: CryptoRng + RngCore,
+    {
+        Prg::from_key(Key256::generate(csprng))
     }
 
     /// Construct a PRG from a new random key
@@ -50,8 +105,7 @@ impl Prg {
     // a random key
     #[must_use]
     pub fn new() -> Prg {
-        let key = Key256::new();
-        Prg { key }
+        Prg::from_key(Key256::new())
     }
 
     /// Fill a slice with pseudo-random bytes resulting from the PRG evaluation.
@@ -61,7 +115,7 @@ impl Prg {
         clear_stack_on_return(1, || {
             let mut cipher = ChaCha20::new(
                 chacha20::Key::from_slice(self.key.content()),
-                chacha20::Nonce::from_slice(&Self::PRG_NONCE),
+                chacha20::Nonce::from_slice(&self.nonce),
             );
             // set the bytes of the buffer to 0
             output.zeroize();
@@ -95,7 +149,7 @@ impl Prg {
         clear_stack_on_return(1, || {
             let mut cipher = ChaCha20::new(
                 chacha20::Key::from_slice(self.key.content()),
-                chacha20::Nonce::from_slice(&Self::PRG_NONCE),
+                chacha20::Nonce::from_slice(&self.nonce),
             );
 
             cipher.seek(offset);
@@ -104,19 +158,155 @@ impl Prg {
             cipher.apply_keystream(output);
         });
     }
+
+    /// Fill several slices with consecutive pseudo-random bytes of the PRG
+    /// output, using a single evaluation of the PRG: `outputs[0]` gets the
+    /// first `outputs[0].len()` bytes, `outputs[1]` the following ones, and so
+    /// on.
+    ///
+    /// # Example
+    /// ```
+    /// # extern crate crypto_tk_rs;
+    /// use crypto_tk_rs::Prg;
+    ///
+    /// let prg = Prg::new();
+    /// let mut buf = [0u8;32];
+    /// prg.fill_pseudo_random_bytes(&mut buf);
+    ///
+    /// let mut out_1 = [0u8;10];
+    /// let mut out_2 = [0u8;22];
+    /// prg.fill_many(&mut [&mut out_1, &mut out_2]);
+    /// assert_eq!(&buf[..10], &out_1[..]);
+    /// assert_eq!(&buf[10..], &out_2[..]);
+    /// ```
+    pub fn fill_many(&self, outputs: &mut [&mut [u8]]) {
+        let mut stream = self.stream();
+        for output in outputs.iter_mut() {
+            stream.fill_next(output);
+        }
+    }
+
+    /// Returns a stream over the output of the PRG, to be consumed
+    /// sequentially in chunks without restarting the evaluation for every
+    /// chunk.
+    ///
+    /// # Example
+    /// ```
+    /// # extern crate crypto_tk_rs;
+    /// use crypto_tk_rs::Prg;
+    ///
+    /// let prg = Prg::new();
+    /// let mut buf = [0u8;32];
+    /// prg.fill_pseudo_random_bytes(&mut buf);
+    ///
+    /// let mut stream = prg.stream();
+    /// let mut chunk_1 = [0u8;10];
+    /// let mut chunk_2 = [0u8;22];
+    /// stream.fill_next(&mut chunk_1);
+    /// stream.fill_next(&mut chunk_2);
+    /// assert_eq!(&buf[..10], &chunk_1[..]);
+    /// assert_eq!(&buf[10..], &chunk_2[..]);
+    /// ```
+    #[must_use]
+    pub fn stream(&self) -> PrgStream {
+        PrgStream {
+            cipher: ChaCha20::new(
+                chacha20::Key::from_slice(self.key.content()),
+                chacha20::Nonce::from_slice(&self.nonce),
+            ),
+        }
+    }
+}
+
+/// A sequential stream over the output of a `Prg`, obtained with
+/// `Prg::stream`. The state of the underlying stream cipher is zeroized on
+/// drop.
+pub struct PrgStream {
+    cipher: ChaCha20,
+}
+
+impl PrgStream {
+    /// Fill a slice with the next pseudo-random bytes of the PRG output.
+    pub fn fill_next(&mut self, output: &mut [u8]) {
+        let cipher = &mut self.cipher;
+        clear_stack_on_return(1, || {
+            // set the bytes of the buffer to 0
+            output.zeroize();
+            cipher.apply_keystream(output);
+        });
+    }
 }
 
 impl InsecureClone for Prg {
     fn insecure_clone(&self) -> Self {
         Prg {
             key: self.key.insecure_clone(),
+            nonce: self.nonce,
         }
     }
 }
 
+impl CryptographyClone for Prg {}
+
+/// Deterministic random number generator backed by a `Prg`.
+///
+/// `PrgRng` reads the keystream of the underlying PRG sequentially, starting
+/// at offset 0, so that two `PrgRng`s built from the same key produce the
+/// same sequence of values. This allows to use a keyed PRG with `rand`-based
+/// algorithms (_e.g._ for sampling).
+///
+/// As its output is fully determined by its key, `PrgRng` purposely does not
+/// implement `CryptoRng`: it must not be used to generate fresh keys.
+#[derive(Zeroize)]
+#[zeroize(drop)]
+pub struct PrgRng {
+    prg: Prg,
+    offset: usize,
+}
+
+impl PrgRng {
+    /// Construct a PRG-backed RNG from a 256 bits key
+    #[must_use]
+    pub fn from_key(key: Key256) -> PrgRng {
+        Self::from_prg(Prg::from_key(key))
+    }
+
+    /// Construct a RNG reading the keystream of `prg`
+    #[must_use]
+    pub fn from_prg(prg: Prg) -> PrgRng {
+        PrgRng { prg, offset: 0 }
+    }
+}
+
+impl RngCore for PrgRng {
+    fn next_u32(&mut self) -> u32 {
+        let mut buf = [0u8; 4];
+        self.fill_bytes(&mut buf);
+        u32::from_le_bytes(buf)
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        let mut buf = [0u8; 8];
+        self.fill_bytes(&mut buf);
+        u64::from_le_bytes(buf)
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        self.prg.fill_offset_pseudo_random_bytes(self.offset, dest);
+        self.offset += dest.len();
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), rand::Error> {
+        self.fill_bytes(dest);
+        Ok(())
+    }
+}
+
 /// Pseudo random generator used to derive cryptographic keys.
 /// See `Prg` for more details of the PRG evaluation.
 pub struct KeyDerivationPrg<KeyType: Key> {
+    // always built with `Prg::from_key`, i.e. with the default nonce: the
+    // nonce is not serialized
     prg: Prg,
     _marker: std::marker::PhantomData<KeyType>,
 }
@@ -142,7 +332,13 @@ impl<KeyType: Key> InsecureClone for KeyDerivationPrg<KeyType> {
     }
 }
 
+impl<KeyType: Key> CryptographyClone for KeyDerivationPrg<KeyType> {}
+
 impl<KeyType: Key> KeyDerivationPrg<KeyType> {
+    /// Number of keys derived by each task of `par_derive_keys`
+    #[cfg(feature = "rayon")]
+    const PAR_DERIVATION_CHUNK_SIZE: u32 = 512;
+
     /// Construct a PRG intended for key derivation from a 256 bits key
     #[must_use]
     pub fn from_key(key: Key256) -> Self {
@@ -165,6 +361,11 @@ impl<KeyType: Key> KeyDerivationPrg<KeyType> {
         }
     }
 
+    /// Returns the key of the underlying PRG
+    pub(crate) fn key(&self) -> &Key256 {
+        &self.prg.key
+    }
+
     /// Derive a new key using the PRG. Modifying `key_index` parameter
     /// generates keys that are computationally pair-wise independent
     #[must_use]
@@ -186,16 +387,15 @@ impl<KeyType: Key> KeyDerivationPrg<KeyType> {
     pub fn derive_keys(&self, indexes: Range<u32>) -> Vec<KeyType> {
         let index_width = indexes.len();
 
-        let mut res = Vec::<KeyType>::new();
-        res.reserve(index_width);
+        let mut res = Vec::<KeyType>::with_capacity(index_width);
 
-        let mut buf = vec![0u8; (index_width as usize) * KeyType::KEY_SIZE];
+        let mut buf = vec![0u8; index_width * KeyType::KEY_SIZE];
         let offset = (indexes.start as usize) * KeyType::KEY_SIZE;
 
         self.prg.fill_offset_pseudo_random_bytes(offset, &mut buf);
 
         for i in 0..index_width {
-            let range_begin = (i as usize) * KeyType::KEY_SIZE;
+            let range_begin = i * KeyType::KEY_SIZE;
             let range_end = (i + 1) * KeyType::KEY_SIZE;
             res.push(KeyType::from_slice(&mut buf[range_begin..range_end]));
         }
@@ -203,6 +403,54 @@ impl<KeyType: Key> KeyDerivationPrg<KeyType> {
         res
     }
 
+    /// Derive a sequence of new keys using the PRG such that their `key_index`
+    /// is in the `indexes` range, as `derive_keys`, unless there are more than
+    /// `max_count` of them. This avoids huge allocations when the range comes
+    /// from an untrusted source.
+    pub fn try_derive_keys(
+        &self,
+        indexes: Range<u32>,
+        max_count: usize,
+    ) -> Result<Vec<KeyType>, KeyDerivationError> {
+        let requested = indexes.len();
+        if requested > max_count {
+            return Err(KeyDerivationError::TooManyKeys {
+                requested,
+                max_count,
+            });
+        }
+        Ok(self.derive_keys(indexes))
+    }
+
+    /// Derive a sequence of new keys using the PRG such that their `key_index`
+    /// is in the `indexes` range, in parallel. The output is the same as the
+    /// one of `derive_keys`: the index range is split in chunks, and the keys
+    /// of each chunk are derived by seeking the PRG to the chunk's offset.
+    #[cfg(feature = "rayon")]
+    #[must_use]
+    pub fn par_derive_keys(&self, indexes: Range<u32>) -> Vec<KeyType>
+    where
+        KeyType: Send + Sync,
+    {
+        use rayon::iter::{IntoParallelIterator, ParallelIterator};
+
+        let end = indexes.end;
+        let chunks: Vec<Range<u32>> = indexes
+            .step_by(Self::PAR_DERIVATION_CHUNK_SIZE as usize)
+            .map(|start| {
+                start
+                    ..end.min(
+                        start.saturating_add(Self::PAR_DERIVATION_CHUNK_SIZE),
+                    )
+            })
+            .collect();
+
+        chunks
+            .into_par_iter()
+            .flat_map_iter(|chunk| self.derive_keys(chunk))
+            .collect()
+    }
+
     /// Derive a pair of new keys using the PRG. The returned pair of keys
     /// `(k1,k2)` have index `key_index` and `key_index+1` respectively.
     #[must_use]
@@ -219,29 +467,63 @@ impl<KeyType: Key> KeyDerivationPrg<KeyType> {
             KeyType::from_slice(&mut buf[KeyType::KEY_SIZE..]),
         )
     }
+
+    /// Derive an array of `N` new keys using the PRG. The `i`-th key of the
+    /// returned array has index `start_index+i`. This is equivalent to
+    /// `derive_keys(start_index..start_index+N)`, without the allocation of
+    /// the output vector.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the index of the last key, `start_index+N-1`, does not fit
+    /// in a `u32`.
+    #[must_use]
+    pub fn derive_key_array<const N: usize>(
+        &self,
+        start_index: u32,
+    ) -> [KeyType; N] {
+        if let Some(last_offset) = N.checked_sub(1) {
+            assert!(
+                u32::try_from(last_offset)
+                    .ok()
+                    .and_then(|offset| start_index.checked_add(offset))
+                    .is_some(),
+                "The key indexes must fit in a u32"
+            );
+        }
+
+        std::array::from_fn(|i| self.derive_key(start_index + i as u32))
+    }
 }
 
 impl SerializableCleartextContent for Prg {
     fn serialization_content_byte_size(&self) -> usize {
-        self.key.serialization_content_byte_size()
+        self.key.serialization_content_byte_size() + Prg::NONCE_SIZE
     }
     fn serialize_content(
         &self,
         writer: &mut dyn std::io::Write,
     ) -> Result<usize, std::io::Error> {
-        self.key.serialize_content(writer)
+        // the nonce is written after the key material
+        self.key.serialize_content(writer)?;
+        writer.write_all(&self.nonce)?;
+
+        Ok(self.serialization_content_byte_size())
     }
 }
 
+// Key derivation PRGs always use the default nonce: only the key is
+// serialized, so that the serialization of the RcPrf elements is unchanged
 impl<KeyType: Key> SerializableCleartextContent for KeyDerivationPrg<KeyType> {
     fn serialization_content_byte_size(&self) -> usize {
-        self.prg.serialization_content_byte_size()
+        self.prg.key.serialization_content_byte_size()
     }
     fn serialize_content(
         &self,
         writer: &mut dyn std::io::Write,
     ) -> Result<usize, std::io::Error> {
-        self.prg.serialize_content(writer)
+        debug_assert_eq!(self.prg.nonce, Prg::PRG_NONCE);
+        self.prg.key.serialize_content(writer)
     }
 }
 
@@ -249,7 +531,27 @@ impl DeserializableCleartextContent for Prg {
     fn deserialize_content(
         reader: &mut dyn std::io::Read,
     ) -> Result<Self, CleartextContentDeserializationError> {
-        Ok(Prg::from_key(Key256::deserialize_content(reader)?))
+        Self::deserialize_content_versioned(
+            reader,
+            SERIALIZATION_FORMAT_VERSION,
+        )
+    }
+
+    fn deserialize_content_versioned(
+        reader: &mut dyn std::io::Read,
+        version: u8,
+    ) -> Result<Self, CleartextContentDeserializationError> {
+        let key = Key256::deserialize_content(reader)?;
+
+        // before version 2, only the key was serialized, and the nonce was
+        // always the default one
+        if version < 2 {
+            return Ok(Prg::from_key(key));
+        }
+        let mut nonce = [0u8; Prg::NONCE_SIZE];
+        reader.read_exact(&mut nonce)?;
+
+        Ok(Prg::from_key_with_nonce(key, nonce))
     }
 }
 
@@ -260,7 +562,7 @@ impl<KeyType: Key> DeserializableCleartextContent
         reader: &mut dyn std::io::Read,
     ) -> Result<Self, CleartextContentDeserializationError> {
         Ok(KeyDerivationPrg::<KeyType> {
-            prg: Prg::deserialize_content(reader)?,
+            prg: Prg::from_key(Key256::deserialize_content(reader)?),
             _marker: std::marker::PhantomData,
         })
     }
@@ -268,7 +570,65 @@ impl<KeyType: Key> DeserializableCleartextContent
 
 #[cfg(test)]
 mod tests {
+    #![allow(clippy::unwrap_used)]
     use super::*;
+    use crate::Key128;
+
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    #[test]
+    fn nonce_domain_separation() {
+        let mut key_bytes = [0u8; 32];
+        rand::thread_rng().fill_bytes(&mut key_bytes);
+        let mut key_bytes_bis = key_bytes;
+
+        let prg = Prg::from_key_with_nonce(
+            Key256::from_bytes(&mut key_bytes),
+            *b"domain 1\0\0\0\0",
+        );
+        let other_prg = Prg::from_key_with_nonce(
+            Key256::from_bytes(&mut key_bytes_bis),
+            *b"domain 2\0\0\0\0",
+        );
+
+        let mut out = [0u8; 64];
+        let mut other_out = [0u8; 64];
+        prg.fill_pseudo_random_bytes(&mut out);
+        other_prg.fill_pseudo_random_bytes(&mut other_out);
+        assert_ne!(out, other_out);
+
+        // offsets and streams use the nonce too
+        let mut offset_out = [0u8; 32];
+        prg.fill_offset_pseudo_random_bytes(32, &mut offset_out);
+        assert_eq!(offset_out, out[32..]);
+        let mut stream_out = [0u8; 64];
+        other_prg.stream().fill_next(&mut stream_out);
+        assert_eq!(stream_out, other_out);
+
+        // the zero nonce is the default one
+        let k = Key256::new();
+        let mut default_out = [0u8; 64];
+        let mut zero_nonce_out = [0u8; 64];
+        Prg::from_key(k.insecure_clone())
+            .fill_pseudo_random_bytes(&mut default_out);
+        Prg::from_key_with_nonce(k, [0u8; Prg::NONCE_SIZE])
+            .fill_pseudo_random_bytes(&mut zero_nonce_out);
+        assert_eq!(default_out, zero_nonce_out);
+    }
+
+    #[test]
+    fn seeded_generation() {
+        let prg_1 = Prg::from_rng(&mut StdRng::seed_from_u64(0xdead_beef));
+        let prg_2 = Prg::from_rng(&mut StdRng::seed_from_u64(0xdead_beef));
+
+        let mut out_1 = [0u8; 64];
+        let mut out_2 = [0u8; 64];
+        prg_1.fill_pseudo_random_bytes(&mut out_1);
+        prg_2.fill_pseudo_random_bytes(&mut out_2);
+
+        assert_eq!(out_1, out_2);
+    }
 
     fn test_offset_correctness(offset: usize, buf_len: usize) -> bool {
         let prg = Prg::new();
@@ -281,6 +641,47 @@ mod tests {
         v1[offset..] == v2[..]
     }
 
+    #[test]
+    fn fill_many() {
+        let prg = Prg::new();
+        let lengths = [0usize, 1, 63, 64, 65, 200, 0, 7];
+
+        let mut bufs: Vec<Vec<u8>> =
+            lengths.iter().map(|&l| vec![0u8; l]).collect();
+        let mut slices: Vec<&mut [u8]> =
+            bufs.iter_mut().map(|b| &mut b[..]).collect();
+        prg.fill_many(&mut slices);
+
+        let mut offset = 0;
+        for buf in &bufs {
+            let mut expected = vec![0u8; buf.len()];
+            prg.fill_offset_pseudo_random_bytes(offset, &mut expected);
+            assert_eq!(buf, &expected);
+            offset += buf.len();
+        }
+
+        prg.fill_many(&mut []);
+    }
+
+    #[test]
+    fn cryptography_clone() {
+        let prg = Prg::new();
+        let prg_dup = prg.cryptography_clone();
+
+        let mut out = [0u8; 1000];
+        let mut out_dup = [0u8; 1000];
+        prg.fill_pseudo_random_bytes(&mut out);
+        prg_dup.fill_pseudo_random_bytes(&mut out_dup);
+        assert_eq!(out, out_dup);
+
+        let kdf = KeyDerivationPrg::<Key256>::new();
+        let kdf_dup = kdf.cryptography_clone();
+        assert_eq!(
+            kdf.derive_key(3).content(),
+            kdf_dup.derive_key(3).content()
+        );
+    }
+
     #[test]
     fn offset_correctness() {
         const TEST_BUF_LEN: usize = 1024_usize;
@@ -311,6 +712,42 @@ mod tests {
         }
     }
 
+    fn key_comparison<KeyType: Key>() {
+        let k = Key256::new();
+        let derivation_prg =
+            KeyDerivationPrg::<KeyType>::from_key(k.insecure_clone());
+        let derivation_prg_dup = KeyDerivationPrg::<KeyType>::from_key(k);
+        let other_derivation_prg = KeyDerivationPrg::<KeyType>::new();
+
+        for key_index in [0u32, 1, 2, 100] {
+            let k_deriv = derivation_prg.derive_key(key_index);
+
+            assert!(k_deriv.ct_eq(&derivation_prg_dup.derive_key(key_index)));
+            assert!(!k_deriv.ct_eq(&derivation_prg.derive_key(key_index + 1)));
+            assert!(!k_deriv.ct_eq(&other_derivation_prg.derive_key(key_index)));
+        }
+    }
+
+    fn bounded_key_derivation<KeyType: Key + KeyAccessor>() {
+        let derivation_prg = KeyDerivationPrg::<KeyType>::new();
+
+        let keys = derivation_prg.try_derive_keys(10..20, 10).unwrap();
+        for (k, k_ref) in keys.iter().zip(derivation_prg.derive_keys(10..20)) {
+            assert_eq!(k.content(), k_ref.content());
+        }
+        assert!(derivation_prg.try_derive_keys(5..5, 0).unwrap().is_empty());
+
+        assert!(matches!(
+            derivation_prg.try_derive_keys(10..21, 10),
+            Err(KeyDerivationError::TooManyKeys {
+                requested: 11,
+                max_count: 10,
+            })
+        ));
+        // the limit is checked before any allocation
+        assert!(derivation_prg.try_derive_keys(0..u32::MAX, 1000).is_err());
+    }
+
     fn key_pairs<KeyType: Key + KeyAccessor>() {
         let key_range = 0..300u32;
 
@@ -333,9 +770,152 @@ mod tests {
         }
     }
 
+    #[test]
+    fn prg_stream() {
+        let prg = Prg::new();
+        let mut expected = [0u8; 1000];
+        prg.fill_pseudo_random_bytes(&mut expected);
+
+        // chunks of various sizes, not aligned on the Chacha20 blocks
+        let chunk_sizes = [0usize, 1, 63, 64, 65, 7, 200, 600];
+        assert_eq!(chunk_sizes.iter().sum::<usize>(), expected.len());
+
+        let mut stream = prg.stream();
+        let mut offset = 0;
+        for size in chunk_sizes {
+            let mut chunk = vec![0xFFu8; size];
+            stream.fill_next(&mut chunk);
+            assert_eq!(chunk[..], expected[offset..offset + size]);
+            offset += size;
+        }
+    }
+
+    #[test]
+    fn from_key_bytes() {
+        let bytes = Zeroizing::new(vec![0x42u8; 32]);
+
+        let prg = Prg::from_key_bytes(bytes).unwrap();
+        let reference = Prg::from_key(Key256::from([0x42u8; 32]));
+
+        let mut out = [0u8; 64];
+        let mut out_ref = [0u8; 64];
+        prg.fill_pseudo_random_bytes(&mut out);
+        reference.fill_pseudo_random_bytes(&mut out_ref);
+        assert_eq!(out, out_ref);
+
+        assert!(Prg::from_key_bytes(Zeroizing::new(vec![0u8; 31])).is_err());
+        assert!(Prg::from_key_bytes(Zeroizing::new(vec![0u8; 33])).is_err());
+    }
+
+    #[test]
+    fn prg_rng_determinism() {
+        let k = Key256::new();
+        let k_dup = k.insecure_clone();
+        let mut rng_1 = PrgRng::from_key(k);
+        let mut rng_2 = PrgRng::from_key(k_dup);
+
+        let seq_1: Vec<u64> = (0..100).map(|_| rng_1.next_u64()).collect();
+        let seq_2: Vec<u64> = (0..100).map(|_| rng_2.next_u64()).collect();
+        assert_eq!(seq_1, seq_2);
+
+        // the values are pairwise distinct
+        for i in 0..seq_1.len() {
+            for j in 0..i {
+                assert_ne!(seq_1[i], seq_1[j]);
+            }
+        }
+    }
+
+    #[test]
+    fn prg_rng_keystream() {
+        let k = Key256::new();
+        let prg = Prg::from_key(k.insecure_clone());
+        let mut rng = PrgRng::from_key(k);
+
+        let mut expected = [0u8; 44];
+        prg.fill_pseudo_random_bytes(&mut expected);
+
+        let mut buf = [0u8; 32];
+        let a = rng.next_u32();
+        let b = rng.next_u64();
+        rng.fill_bytes(&mut buf);
+
+        assert_eq!(a.to_le_bytes(), expected[..4]);
+        assert_eq!(b.to_le_bytes(), expected[4..12]);
+        assert_eq!(buf, expected[12..]);
+    }
+
+    fn key_arrays<KeyType: Key + KeyAccessor>() {
+        let derivation_prg = KeyDerivationPrg::<KeyType>::new();
+
+        for start in [0u32, 1, 17, 300] {
+            let keys = derivation_prg.derive_keys(start..start + 4);
+            let array = derivation_prg.derive_key_array::<4>(start);
+
+            for (k, k_array) in keys.iter().zip(array.iter()) {
+                assert_eq!(k.content(), k_array.content());
+            }
+
+            let [k_0, k_1] = derivation_prg.derive_key_array::<2>(start);
+            let (k_pair_0, k_pair_1) = derivation_prg.derive_key_pair(start);
+            assert_eq!(k_0.content(), k_pair_0.content());
+            assert_eq!(k_1.content(), k_pair_1.content());
+
+            let empty = derivation_prg.derive_key_array::<0>(start);
+            assert!(empty.is_empty());
+        }
+
+        // the last key can have the largest index
+        let array = derivation_prg.derive_key_array::<4>(u32::MAX - 3);
+        assert_eq!(
+            array[3].content(),
+            derivation_prg.derive_key(u32::MAX).content()
+        );
+        let empty = derivation_prg.derive_key_array::<0>(u32::MAX);
+        assert!(empty.is_empty());
+    }
+
+    #[test]
+    #[should_panic(expected = "The key indexes must fit in a u32")]
+    fn key_array_index_overflow() {
+        let derivation_prg = KeyDerivationPrg::<Key256>::new();
+        // the last index would be u32::MAX + 1
+        let _ = derivation_prg.derive_key_array::<4>(u32::MAX - 4 + 2);
+    }
+
+    #[cfg(feature = "rayon")]
+    fn parallel_key_derivation<KeyType: Key + KeyAccessor + Send + Sync>() {
+        let derivation_prg = KeyDerivationPrg::<KeyType>::new();
+
+        for indexes in [0..10_000, 17..10_017, 3..3, 5..6, 1000..1512] {
+            let keys = derivation_prg.derive_keys(indexes.clone());
+            let par_keys = derivation_prg.par_derive_keys(indexes);
+
+            assert_eq!(keys.len(), par_keys.len());
+            for (k, k_par) in keys.iter().zip(par_keys.iter()) {
+                assert_eq!(k.content(), k_par.content());
+            }
+        }
+
+        // the last keys of the index space
+        let last_keys =
+            derivation_prg.par_derive_keys(u32::MAX - 600..u32::MAX);
+        assert_eq!(last_keys.len(), 600);
+        assert_eq!(
+            last_keys[599].content(),
+            derivation_prg.derive_key(u32::MAX - 1).content()
+        );
+    }
+
     #[test]
     fn key_derivation_256() {
         key_derivation::<Key256>();
+        key_comparison::<Key256>();
+        key_comparison::<Key128>();
+        bounded_key_derivation::<Key256>();
         key_pairs::<Key256>();
+        key_arrays::<Key256>();
+        #[cfg(feature = "rayon")]
+        parallel_key_derivation::<Key256>();
     }
 }
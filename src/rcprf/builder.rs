@@ -0,0 +1,263 @@
+//! Step-by-step construction of the tree underlying a `RcPrf`
+
+use super::*;
+use crate::Prf;
+
+/// Builder exposing the derivation steps of the tree of a `RcPrf`.
+///
+/// The tree of a `RcPrf` of height `h` is a GGM-like binary tree: the key of a
+/// node is expanded with a PRG into the keys of its two children, and the
+/// value of a leaf is computed with a PRF keyed with the leaf's key. Nodes are
+/// identified by their depth (`0` for the root, `h-1` for the leaves) and
+/// their index amongst the `2^depth` nodes at that depth, from left to right.
+///
+/// The builder gives access to these steps, for custom constructions or
+/// testing, and can be turned into the corresponding `RcPrf` with `build`.
+///
+/// # Example
+/// ```
+/// # extern crate crypto_tk_rs;
+/// use crypto_tk_rs::{Key, Key256, RangePrf, RcPrfBuilder};
+///
+/// let builder = RcPrfBuilder::new(Key256::new(), 3).unwrap();
+///
+/// // manually derive the value of the leaf 2 (right child, then left child)
+/// let (_, right) = RcPrfBuilder::split_key(&builder.derive_node_key(0, 0).unwrap());
+/// let (leaf_key, _) = RcPrfBuilder::split_key(&right);
+/// let mut out = [0u8; 16];
+/// RcPrfBuilder::eval_leaf_key(&leaf_key, &mut out);
+///
+/// let rcprf = builder.build();
+/// let mut expected = [0u8; 16];
+/// rcprf.eval(2, &mut expected).unwrap();
+/// assert_eq!(out, expected);
+/// ```
+pub struct RcPrfBuilder {
+    root: Key256,
+    height: u8,
+}
+
+impl RcPrfBuilder {
+    /// Returns a new builder for a tree of height `height`, with the given
+    /// root key.
+    /// Returns an error if `height` is not between `MIN_HEIGHT` and
+    /// `MAX_HEIGHT`.
+    pub fn new(root: Key256, height: u8) -> Result<Self, RcPrfError> {
+        if !(MIN_HEIGHT..=MAX_HEIGHT).contains(&height) {
+            return Err(RcPrfError::InvalidTreeHeight(
+                height, MIN_HEIGHT, MAX_HEIGHT,
+            ));
+        }
+        Ok(RcPrfBuilder { root, height })
+    }
+
+    /// Returns the height of the tree
+    #[must_use]
+    pub fn height(&self) -> u8 {
+        self.height
+    }
+
+    /// Derive the keys of the two children of the node with key `key`
+    /// (one step of the GGM construction).
+    #[must_use]
+    pub fn split_key(key: &Key256) -> (Key256, Key256) {
+        KeyDerivationPrg::<Key256>::from_key(key.insecure_clone())
+            .derive_key_pair(0)
+    }
+
+    /// Compute the value of the leaf with key `key`, and put it in `output`
+    pub fn eval_leaf_key(key: &Key256, output: &mut [u8]) {
+        Prf::from_key(key.insecure_clone()).fill_bytes(&[0u8], output);
+    }
+
+    /// Returns the range of the leaves below the node at depth `depth` and
+    /// index `index`.
+    /// Returns an error if there is no such node in the tree.
+    pub fn node_range(
+        &self,
+        depth: u8,
+        index: u64,
+    ) -> Result<RcPrfRange, RcPrfError> {
+        self.check_node(depth, index)?;
+
+        if depth == 0 {
+            return Ok(RcPrfRange::new(0, max_leaf_index(self.height)));
+        }
+        let shift = self.height - 1 - depth;
+        let min = index << shift;
+        Ok(RcPrfRange::new(min, min + max_leaf_index(shift + 1)))
+    }
+
+    /// Derive the key of the node at depth `depth` and index `index`, by
+    /// walking down the tree from the root.
+    /// Returns an error if there is no such node in the tree.
+    pub fn derive_node_key(
+        &self,
+        depth: u8,
+        index: u64,
+    ) -> Result<Key256, RcPrfError> {
+        self.check_node(depth, index)?;
+
+        let mut key = self.root.insecure_clone();
+        for level in (0..depth).rev() {
+            let child = (index >> level) & 1;
+            key = KeyDerivationPrg::<Key256>::from_key(key)
+                .derive_key(child as u32);
+        }
+        Ok(key)
+    }
+
+    /// Build the `RcPrf` whose tree has been described by the builder
+    #[must_use]
+    pub fn build(self) -> RcPrf {
+        // the height has been checked when creating the builder
+        RcPrf {
+            root: ConstrainedRcPrfInnerElement {
+                prg: KeyDerivationPrg::from_key(self.root),
+                rcprf_height: self.height,
+                range: RcPrfRange::from(0..=max_leaf_index(self.height)),
+                subtree_height: self.height,
+            },
+        }
+    }
+
+    fn check_node(&self, depth: u8, index: u64) -> Result<(), RcPrfError> {
+        if depth >= self.height {
+            return Err(RcPrfError::InvalidNodeDepth(depth, self.height));
+        }
+        let level_range = RcPrfRange::new(0, max_leaf_index(depth + 1));
+        if !level_range.contains_leaf(index) {
+            return Err(RcPrfError::InvalidEvalPoint(index, level_range));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used)]
+    use super::*;
+    use rand::prelude::*;
+
+    #[test]
+    fn manual_tree() {
+        let h = 6u8;
+        let root = Key256::new();
+        let builder = RcPrfBuilder::new(root.insecure_clone(), h).unwrap();
+        let rcprf = RcPrf::from_key(root, h).unwrap();
+
+        for leaf in 0..=max_leaf_index(h) {
+            // walk down the tree using the GGM steps only
+            let mut key = builder.derive_node_key(0, 0).unwrap();
+            for depth in 0..(h - 1) {
+                let (left, right) = RcPrfBuilder::split_key(&key);
+                key = if (leaf >> (h - 2 - depth)) & 1 == 0 {
+                    left
+                } else {
+                    right
+                };
+            }
+            let mut out = [0u8; 32];
+            RcPrfBuilder::eval_leaf_key(&key, &mut out);
+
+            let mut expected = [0u8; 32];
+            rcprf.eval(leaf, &mut expected).unwrap();
+            assert_eq!(out, expected);
+
+            // direct derivation of the leaf key
+            let mut out_direct = [0u8; 32];
+            RcPrfBuilder::eval_leaf_key(
+                &builder.derive_node_key(h - 1, leaf).unwrap(),
+                &mut out_direct,
+            );
+            assert_eq!(out_direct, expected);
+        }
+
+        let built_rcprf = builder.build();
+        let mut out = [0u8; 32];
+        let mut expected = [0u8; 32];
+        for leaf in 0..=max_leaf_index(h) {
+            built_rcprf.eval(leaf, &mut out).unwrap();
+            rcprf.eval(leaf, &mut expected).unwrap();
+            assert_eq!(out, expected);
+        }
+    }
+
+    #[test]
+    fn subtrees() {
+        let h = 12u8;
+        let root = Key256::new();
+        let builder = RcPrfBuilder::new(root.insecure_clone(), h).unwrap();
+        let rcprf = RcPrf::from_key(root, h).unwrap();
+        let mut rng = thread_rng();
+
+        for depth in 0..(h - 1) {
+            let index = rng.gen_range(0..(1u64 << depth));
+            let (min, _) = builder.node_range(depth, index).unwrap().bounds();
+
+            // a subtree is itself a RcPrf rooted at the node's key
+            let subtree = RcPrf::from_key(
+                builder.derive_node_key(depth, index).unwrap(),
+                h - depth,
+            )
+            .unwrap();
+            assert_eq!(
+                subtree.range().width(),
+                builder.node_range(depth, index).unwrap().width()
+            );
+
+            let mut out = [0u8; 16];
+            let mut expected = [0u8; 16];
+            for offset in 0..=max_leaf_index(h - depth) {
+                subtree.eval(offset, &mut out).unwrap();
+                rcprf.eval(min + offset, &mut expected).unwrap();
+                assert_eq!(out, expected);
+            }
+        }
+    }
+
+    #[test]
+    fn node_ranges() {
+        let builder = RcPrfBuilder::new(Key256::new(), 4).unwrap();
+        assert_eq!(builder.height(), 4);
+        assert_eq!(builder.node_range(0, 0).unwrap(), RcPrfRange::new(0, 7));
+        assert_eq!(builder.node_range(1, 1).unwrap(), RcPrfRange::new(4, 7));
+        assert_eq!(builder.node_range(2, 1).unwrap(), RcPrfRange::new(2, 3));
+        assert_eq!(builder.node_range(3, 5).unwrap(), RcPrfRange::new(5, 5));
+
+        let builder = RcPrfBuilder::new(Key256::new(), MAX_HEIGHT).unwrap();
+        assert_eq!(
+            builder.node_range(0, 0).unwrap(),
+            RcPrfRange::new(0, u64::MAX)
+        );
+        assert_eq!(
+            builder.node_range(1, 1).unwrap(),
+            RcPrfRange::new(1u64 << 63, u64::MAX)
+        );
+        assert_eq!(
+            builder.node_range(MAX_HEIGHT - 1, u64::MAX).unwrap(),
+            RcPrfRange::new(u64::MAX, u64::MAX)
+        );
+    }
+
+    #[test]
+    fn invalid_nodes() {
+        assert!(matches!(
+            RcPrfBuilder::new(Key256::new(), 1),
+            Err(RcPrfError::InvalidTreeHeight(..))
+        ));
+        assert!(RcPrfBuilder::new(Key256::new(), MAX_HEIGHT + 1).is_err());
+
+        let builder = RcPrfBuilder::new(Key256::new(), 4).unwrap();
+        assert!(matches!(
+            builder.derive_node_key(4, 0),
+            Err(RcPrfError::InvalidNodeDepth(4, 4))
+        ));
+        assert!(matches!(
+            builder.derive_node_key(2, 4),
+            Err(RcPrfError::InvalidEvalPoint(4, _))
+        ));
+        assert!(builder.node_range(0, 1).is_err());
+        assert!(builder.node_range(3, 8).is_err());
+    }
+}
@@ -1,4 +1,5 @@
 use crate::rcprf::rcprf_range::*;
+use std::ops::Bound;
 use thiserror::Error;
 
 /// RCPRF-related errors
@@ -15,16 +16,68 @@ pub enum RcPrfError {
     /// Invalid range width
     #[error("Incompatible range width ({0}) and outputs length ({1}).")]
     InvalidRangeWidth(usize, u64),
+    /// Invalid length of a flat output buffer
+    #[error("Invalid output length ({0}): expected {1} outputs of {2} bytes.")]
+    InvalidFlatOutputLength(usize, u64, usize),
+    /// Empty output slice (a zero-length evaluation is almost always a bug)
+    #[error("Invalid empty output slice.")]
+    EmptyOutput,
     /// Invalid constrain range error ( out of range)
     #[error(
         "Invalid constrain range: {0} is not contained in the valid range {1}"
     )]
     InvalidConstrainRange(RcPrfRange, RcPrfRange),
-    /// Invalid tree height (height is too large)
+    /// Invalid tree height (height is too small or too large)
+    #[error("Invalid tree height: height ({0}) must be between {1} and {2}.")]
+    InvalidTreeHeight(u8, u8, u8),
+    /// Invalid node depth (the depth is larger than the depth of the leaves)
     #[error(
-        "Invalid tree height: height ({0}) is too large. The maximum height is {1}."
+        "Invalid node depth: depth ({0}) is too large for a tree of height {1}."
     )]
-    InvalidTreeHeight(u8, u8),
+    InvalidNodeDepth(u8, u8),
+    /// The range is not the range of a subtree (an aligned block of leaves
+    /// whose width is a power of two)
+    #[error("Invalid subtree range: {0} is not the range of a subtree.")]
+    InvalidSubtreeRange(RcPrfRange),
+    /// Invalid bit path (its length is not the depth of the leaves)
+    #[error(
+        "Invalid bit path: {0} bits do not lead to a leaf of a tree of height {1}."
+    )]
+    InvalidBitPathLength(usize, u8),
+    /// Empty point set (there is no leaf to constrain the `RcPrf` on)
+    #[error("Invalid empty set of points.")]
+    EmptyPointSet,
+    /// Empty constrained `RcPrf` (all its elements have been zeroized or
+    /// popped)
+    #[error("Invalid empty constrained RcPrf: it has no element left.")]
+    EmptyConstrainedRcPrf,
+    /// Empty range error (the start bound is larger than the end bound)
+    #[error("Invalid empty range: ({0:?}, {1:?}) does not contain any value.")]
+    EmptyRange(Bound<u64>, Bound<u64>),
+    /// Unbounded range error (one of the bounds of the range is missing)
+    #[error("Invalid unbounded range: ({0:?}, {1:?}) must have both bounds.")]
+    UnboundedRange(Bound<u64>, Bound<u64>),
+    /// The cover of the range has too many elements
+    #[error(
+        "The cover of range {0} is made of {1} elements, more than the maximum of {2}."
+    )]
+    CoverTooLarge(RcPrfRange, u64, usize),
+    /// Invalid delta ranges (the new range does not extend the old range on
+    /// exactly one side)
+    #[error(
+        "Invalid delta ranges: {1} does not extend {0} on exactly one side."
+    )]
+    InvalidDeltaRange(RcPrfRange, RcPrfRange),
+    /// The `RcPrf`s to be merged are not based on trees of the same height
+    #[error(
+        "The RcPrfs to be merged are based on trees of different heights ({0} and {1})."
+    )]
+    TreeHeightMismatch(u8, u8),
+    /// Invalid split index (the index is not in the range, or is its maximum)
+    #[error(
+        "Invalid split index: {0} must be in {1}, and smaller than its maximum."
+    )]
+    InvalidSplitIndex(u64, RcPrfRange),
     /// Non-consecutive merge ranges
     #[error(
         "Ranges of the RcPrfs to be merged ({0} and {1}) are not consecutive."
@@ -1,5 +1,6 @@
 use crate::private::{RcPrfElement, RcPrfElementPair};
 use crate::rcprf::*;
+use crate::serialization::byte_order::ByteOrder;
 use crate::serialization::errors::CleartextContentDeserializationError;
 use crate::Prf;
 
@@ -32,13 +33,7 @@ impl RcPrfElement for ConstrainedRcPrfInnerElement {
     fn split_node(&self) -> RcPrfElementPair {
         let (subkey_left, subkey_right) = self.prg.derive_key_pair(0);
         if self.subtree_height > 2 {
-            let half_width = self.range().width() / 2;
-            let range_left = RcPrfRange::from(
-                self.range().min()..self.range().min() + half_width,
-            );
-            let range_right = RcPrfRange::from(
-                self.range().min() + half_width..self.range().max(),
-            );
+            let (range_left, range_right) = self.child_ranges();
             (
                 Box::pin(ConstrainedRcPrfInnerElement {
                     prg: KeyDerivationPrg::from_key(subkey_left),
@@ -55,21 +50,35 @@ impl RcPrfElement for ConstrainedRcPrfInnerElement {
             )
         } else {
             debug_assert_eq!(self.subtree_height, 2);
+            let (min, max) = self.range.bounds();
 
             (
                 Box::pin(ConstrainedRcPrfLeafElement {
                     prf: Prf::from_key(subkey_left),
-                    index: self.range().min(),
+                    index: min,
                     rcprf_height: self.rcprf_height,
                 }),
                 Box::pin(ConstrainedRcPrfLeafElement {
                     prf: Prf::from_key(subkey_right),
-                    index: self.range().max(),
+                    index: max,
                     rcprf_height: self.rcprf_height,
                 }),
             )
         }
     }
+
+    fn serialize_content_with_byte_order(
+        &self,
+        writer: &mut dyn std::io::Write,
+        byte_order: ByteOrder,
+    ) -> Result<usize, std::io::Error> {
+        writer.write_all(&self.rcprf_height.to_le_bytes())?;
+        writer.write_all(&self.subtree_height.to_le_bytes())?;
+        self.range.serialize_with_byte_order(writer, byte_order)?;
+        self.prg.serialize_content(writer)?;
+
+        Ok(self.serialization_content_byte_size())
+    }
 }
 
 impl private::UncheckedRangePrf for ConstrainedRcPrfInnerElement {
@@ -77,13 +86,12 @@ impl private::UncheckedRangePrf for ConstrainedRcPrfInnerElement {
         let child = self
             .get_child_node(leaf, self.tree_height() - self.subtree_height());
 
-        let half_width = 1u64 << (self.subtree_height() - 2);
-        let submin = self.range.min() + (child as u64) * half_width;
-        let submax = submin + half_width;
-        let r = RcPrfRange::from(submin..submax);
-
-        debug_assert!(self.range().contains_range(&r), "{} {}", self.range, r);
-        debug_assert_eq!(self.range().width() / 2, half_width);
+        let (left_range, right_range) = self.child_ranges();
+        let r = match child {
+            RcPrfTreeNodeChild::LeftChild => left_range,
+            RcPrfTreeNodeChild::RightChild => right_range,
+        };
+        debug_assert!(self.range.contains_range(&r), "{} {}", self.range, r);
 
         let subkey = self.prg.derive_key(child as u32);
 
@@ -97,11 +105,11 @@ impl private::UncheckedRangePrf for ConstrainedRcPrfInnerElement {
             child_node.unchecked_eval(leaf, output);
         } else {
             debug_assert_eq!(self.subtree_height, 2);
-            debug_assert_eq!(half_width, 1);
+            debug_assert_eq!(r.width(), 1);
 
             let child_node = ConstrainedRcPrfLeafElement {
                 prf: Prf::from_key(subkey),
-                index: r.min(),
+                index: leaf,
                 rcprf_height: self.rcprf_height,
             };
             child_node.unchecked_eval(leaf, output);
@@ -114,16 +122,11 @@ impl private::UncheckedRangePrf for ConstrainedRcPrfInnerElement {
         outputs: &mut [&mut [u8]],
     ) {
         if self.subtree_height() > 2 {
-            let half_width = 1u64 << (self.subtree_height() - 2);
+            let (left_range, right_range) = self.child_ranges();
             let mut out_offset = 0usize;
 
             // use scopes to avoid any mixups between left and right subtrees
             {
-                let left_range = RcPrfRange::new(
-                    self.range().min(),
-                    self.range().min() + half_width - 1,
-                );
-
                 match left_range.intersection(range) {
                     None => (),
                     Some(r) => {
@@ -144,11 +147,6 @@ impl private::UncheckedRangePrf for ConstrainedRcPrfInnerElement {
             }
 
             {
-                let right_range = RcPrfRange::new(
-                    self.range().min() + half_width,
-                    self.range().max(),
-                );
-
                 match right_range.intersection(range) {
                     None => (),
                     Some(r) => {
@@ -171,8 +169,9 @@ impl private::UncheckedRangePrf for ConstrainedRcPrfInnerElement {
             // we are getting a leaf
             debug_assert!(range.width() <= 2);
 
+            let (min, max) = self.range.bounds();
             let mut out_offset = 0usize;
-            if range.contains_leaf(self.range().min()) {
+            if range.contains_leaf(min) {
                 let subkey = self.prg.derive_key(0);
 
                 let child_node = ConstrainedRcPrfLeafElement {
@@ -180,11 +179,11 @@ impl private::UncheckedRangePrf for ConstrainedRcPrfInnerElement {
                     index: range.min(),
                     rcprf_height: self.rcprf_height,
                 };
-                child_node.unchecked_eval(self.range().min(), outputs[0]);
+                child_node.unchecked_eval(min, outputs[0]);
                 out_offset += 1;
             }
 
-            if range.contains_leaf(self.range().max()) {
+            if range.contains_leaf(max) {
                 let subkey = self.prg.derive_key(1);
 
                 let child_node = ConstrainedRcPrfLeafElement {
@@ -192,8 +191,7 @@ impl private::UncheckedRangePrf for ConstrainedRcPrfInnerElement {
                     index: range.max(),
                     rcprf_height: self.rcprf_height,
                 };
-                child_node
-                    .unchecked_eval(self.range().max(), outputs[out_offset]);
+                child_node.unchecked_eval(max, outputs[out_offset]);
             }
         }
     }
@@ -205,7 +203,7 @@ impl private::UncheckedRangePrf for ConstrainedRcPrfInnerElement {
         outputs: &mut [&mut [u8]],
     ) {
         if self.subtree_height() > 2 {
-            let half_width = 1u64 << (self.subtree_height() - 2);
+            let (left_range, right_range) = self.child_ranges();
 
             rayon::scope(move |s| {
                 let mut current = outputs;
@@ -213,11 +211,6 @@ impl private::UncheckedRangePrf for ConstrainedRcPrfInnerElement {
                 // use scopes to avoid any mixups between left and right
                 // subtrees
                 {
-                    let left_range = RcPrfRange::new(
-                        self.range().min(),
-                        self.range().min() + half_width - 1,
-                    );
-
                     match left_range.intersection(range) {
                         None => (),
                         Some(r) => {
@@ -242,11 +235,6 @@ impl private::UncheckedRangePrf for ConstrainedRcPrfInnerElement {
                 }
 
                 {
-                    let right_range = RcPrfRange::new(
-                        self.range().min() + half_width,
-                        self.range().max(),
-                    );
-
                     match right_range.intersection(range) {
                         None => (),
                         Some(r) => {
@@ -268,8 +256,9 @@ impl private::UncheckedRangePrf for ConstrainedRcPrfInnerElement {
             // do not parallelize this, it is not worth it
             debug_assert!(range.width() <= 2);
 
+            let (min, max) = self.range.bounds();
             let mut out_offset = 0usize;
-            if range.contains_leaf(self.range().min()) {
+            if range.contains_leaf(min) {
                 let subkey = self.prg.derive_key(0);
 
                 let child_node = ConstrainedRcPrfLeafElement {
@@ -277,11 +266,11 @@ impl private::UncheckedRangePrf for ConstrainedRcPrfInnerElement {
                     index: range.min(),
                     rcprf_height: self.rcprf_height,
                 };
-                child_node.unchecked_eval(self.range().min(), outputs[0]);
+                child_node.unchecked_eval(min, outputs[0]);
                 out_offset += 1;
             }
 
-            if range.contains_leaf(self.range().max()) {
+            if range.contains_leaf(max) {
                 let subkey = self.prg.derive_key(1);
 
                 let child_node = ConstrainedRcPrfLeafElement {
@@ -289,8 +278,7 @@ impl private::UncheckedRangePrf for ConstrainedRcPrfInnerElement {
                     index: range.max(),
                     rcprf_height: self.rcprf_height,
                 };
-                child_node
-                    .unchecked_eval(self.range().max(), outputs[out_offset]);
+                child_node.unchecked_eval(max, outputs[out_offset]);
             }
         }
     }
@@ -299,21 +287,13 @@ impl private::UncheckedRangePrf for ConstrainedRcPrfInnerElement {
         debug_assert!(self.range().contains_range(range));
 
         if self.range() == *range {
-            return ConstrainedRcPrf {
-                elements: vec![Box::pin(self.insecure_clone())],
-            };
+            return ConstrainedRcPrf::from_element(Box::pin(
+                self.insecure_clone(),
+            ));
         }
 
         if self.subtree_height() > 2 {
-            let half_width = 1u64 << (self.subtree_height() - 2);
-            let left_range = RcPrfRange::new(
-                self.range().min(),
-                self.range().min() + half_width - 1,
-            );
-            let right_range = RcPrfRange::new(
-                self.range().min() + half_width,
-                self.range().max(),
-            );
+            let (left_range, right_range) = self.child_ranges();
 
             let left_constrained = match left_range.intersection(range) {
                 None => None,
@@ -383,9 +363,7 @@ impl private::UncheckedRangePrf for ConstrainedRcPrfInnerElement {
                 rcprf_height: self.rcprf_height,
             };
 
-            ConstrainedRcPrf {
-                elements: vec![Box::pin(child_node)],
-            }
+            ConstrainedRcPrf::from_element(Box::pin(child_node))
         }
     }
 }
@@ -417,18 +395,38 @@ impl SerializableCleartextContent for ConstrainedRcPrfInnerElement {
         &self,
         writer: &mut dyn std::io::Write,
     ) -> Result<usize, std::io::Error> {
-        writer.write_all(&self.rcprf_height.to_le_bytes())?;
-        writer.write_all(&self.subtree_height.to_le_bytes())?;
-        self.range.serialize_content(writer)?;
-        self.prg.serialize_content(writer)?;
-
-        Ok(self.serialization_content_byte_size())
+        self.serialize_content_with_byte_order(writer, ByteOrder::default())
     }
 }
 
 impl DeserializableCleartextContent for ConstrainedRcPrfInnerElement {
     fn deserialize_content(
         reader: &mut dyn std::io::Read,
+    ) -> Result<Self, CleartextContentDeserializationError> {
+        Self::deserialize_content_with_byte_order(reader, ByteOrder::default())
+    }
+}
+
+impl ConstrainedRcPrfInnerElement {
+    /// Returns the ranges of the left and the right children of the element,
+    /// which must not be a leaf
+    fn child_ranges(&self) -> (RcPrfRange, RcPrfRange) {
+        // do not use `width()`, which overflows for a tree of height
+        // `MAX_HEIGHT`, and use inclusive bounds, as the range of such a tree
+        // ends at `u64::MAX`
+        let half_width = 1u64 << (self.subtree_height - 2);
+        let (min, max) = self.range.bounds();
+        (
+            RcPrfRange::new(min, min + (half_width - 1)),
+            RcPrfRange::new(min + half_width, max),
+        )
+    }
+
+    /// Deserialize the content of an element whose integers are encoded with
+    /// the `byte_order` byte order
+    pub(crate) fn deserialize_content_with_byte_order(
+        reader: &mut dyn std::io::Read,
+        byte_order: ByteOrder,
     ) -> Result<Self, CleartextContentDeserializationError> {
         let mut h_bytes = [0u8; 1];
         reader.read_exact(&mut h_bytes)?;
@@ -438,7 +436,17 @@ impl DeserializableCleartextContent for ConstrainedRcPrfInnerElement {
         reader.read_exact(&mut sub_h_bytes)?;
         let subtree_height = u8::from_le_bytes(sub_h_bytes);
 
-        let range = RcPrfRange::deserialize_content(reader)?;
+        if subtree_height < MIN_HEIGHT
+            || subtree_height > rcprf_height
+            || rcprf_height > MAX_HEIGHT
+        {
+            return Err(CleartextContentDeserializationError::ContentError(
+                format!("Invalid heights for an inner element of a RcPrf: subtree height {subtree_height} and tree height {rcprf_height}"),
+            ));
+        }
+
+        let range =
+            RcPrfRange::deserialize_with_byte_order(reader, byte_order)?;
 
         Ok(ConstrainedRcPrfInnerElement {
             prg: KeyDerivationPrg::<Key256>::deserialize_content(reader)?,
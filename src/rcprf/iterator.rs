@@ -1,5 +1,6 @@
 use crate::rcprf::*;
 use std::collections::VecDeque;
+use std::convert::TryFrom;
 
 /// The output generator (as an iterator) for [`RcPrf`]
 pub struct RcPrfIterator {
@@ -15,7 +16,7 @@ impl Iterator for RcPrfIterator {
             loop {
                 if elt.is_leaf() {
                     let mut result = vec![0u8; self.output_size];
-                    let x = elt.range().min();
+                    let (x, _) = elt.range().bounds();
                     // we can use `unchecked_eval` here because we know the
                     // function will not panic as `x` is the minimum value of
                     // the element's range (and hence in the range)
@@ -38,16 +39,8 @@ impl Iterator for RcPrfIterator {
     }
 
     fn size_hint(&self) -> (usize, Option<usize>) {
-        let back = self.node_queue.back();
-        let front = self.node_queue.front();
-
-        match (back, front) {
-            (Some(back), Some(front)) => {
-                let s = (back.range().max() - front.range().min() + 1) as usize;
-                (s, Some(s))
-            }
-            _ => (0, Some(0)),
-        }
+        usize::try_from(self.remaining_leaves())
+            .map_or((usize::MAX, None), |s| (s, Some(s)))
     }
 }
 
@@ -58,7 +51,7 @@ impl DoubleEndedIterator for RcPrfIterator {
                 if elt.is_leaf() {
                     let mut result = vec![0u8; self.output_size];
 
-                    let x = elt.range().max();
+                    let (_, x) = elt.range().bounds();
                     // we can use `unchecked_eval` here because we know the
                     // function will not panic as `x` is the maximum value of
                     // the element's range (and hence in the range)
@@ -80,7 +73,126 @@ impl DoubleEndedIterator for RcPrfIterator {
     }
 }
 
-impl ExactSizeIterator for RcPrfIterator {}
+/// The length of the iterator saturates at `usize::MAX`: the whole range of a
+/// tree of height `MAX_HEIGHT` has 2^64 leaves, one more than `usize::MAX` on
+/// 64 bits platforms. This is the only case where `len` is not exact (and
+/// where `size_hint` returns `(usize::MAX, None)`).
+impl ExactSizeIterator for RcPrfIterator {
+    fn len(&self) -> usize {
+        usize::try_from(self.remaining_leaves()).unwrap_or(usize::MAX)
+    }
+}
+
+impl RcPrfIterator {
+    /// Returns the number of leaves that have not been produced by the
+    /// iterator yet (from either end). This does not evaluate any leaf.
+    ///
+    /// The count saturates at `u64::MAX`: the whole range of a tree of height
+    /// `MAX_HEIGHT` has 2^64 leaves, which does not fit in a `u64`.
+    #[must_use]
+    pub fn remaining(&self) -> u64 {
+        u64::try_from(self.remaining_leaves()).unwrap_or(u64::MAX)
+    }
+
+    /// Exact number of leaves that have not been produced by the iterator
+    /// yet, counted in 128 bits to avoid the saturation of `remaining`
+    fn remaining_leaves(&self) -> u128 {
+        match (self.node_queue.front(), self.node_queue.back()) {
+            (Some(front), Some(back)) => {
+                let (min, _) = front.range().bounds();
+                let (_, max) = back.range().bounds();
+                u128::from(max - min) + 1
+            }
+            _ => 0,
+        }
+    }
+
+    /// Transform the iterator into one whose values are zeroized when they
+    /// are dropped. This should be preferred when the values are used as key
+    /// material.
+    ///
+    /// # Example
+    /// ```
+    /// # extern crate crypto_tk_rs;
+    /// use crypto_tk_rs::{RangePrf, RcPrf, RcPrfRange};
+    ///
+    /// let rcprf = RcPrf::new(4).unwrap();
+    /// let range = RcPrfRange::new(2, 5);
+    ///
+    /// let iter = rcprf.value_range_iter(&range, 16).unwrap().into_zeroizing();
+    /// for (x, value) in iter {
+    ///     let mut expected = [0u8; 16];
+    ///     rcprf.eval(x, &mut expected).unwrap();
+    ///     assert_eq!(value[..], expected);
+    /// }
+    /// ```
+    #[must_use]
+    pub fn into_zeroizing(
+        self,
+    ) -> impl ExactSizeIterator<Item = (u64, Zeroizing<Vec<u8>>)> + DoubleEndedIterator
+    {
+        self.map(|(x, value)| (x, Zeroizing::new(value)))
+    }
+}
+
+/// The output generator (as an iterator) for [`RcPrf`], only producing the
+/// outputs of every `stride`-th leaf of the range
+///
+/// The number of remaining outputs might not fit in a `usize` (e.g. for the
+/// whole range of a tree of height `MAX_HEIGHT` with a stride of 1): in that
+/// case, `size_hint` returns `(usize::MAX, None)`. Hence, this iterator does
+/// not implement `ExactSizeIterator`.
+pub struct RcPrfStridedIterator {
+    pub(crate) node_queue: VecDeque<Pin<Box<dyn private::RcPrfElement>>>,
+    pub(crate) output_size: usize,
+    pub(crate) stride: u64,
+    /// The next leaf to be evaluated, or `None` if the iteration is over
+    pub(crate) next_leaf: Option<u64>,
+}
+
+impl Iterator for RcPrfStridedIterator {
+    type Item = (u64, Vec<u8>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let leaf = self.next_leaf?;
+
+        while let Some(elt) = self.node_queue.pop_front() {
+            let (_, max) = elt.range().bounds();
+            if max < leaf {
+                // the whole element is skipped (and zeroized on drop)
+                continue;
+            }
+            if elt.is_leaf() {
+                let mut result = vec![0u8; self.output_size];
+                // we can use `unchecked_eval` here because the elements are
+                // sorted and contiguous: the first element whose range ends
+                // after `leaf` contains `leaf`
+                elt.unchecked_eval(leaf, &mut result);
+                self.next_leaf = leaf.checked_add(self.stride);
+                return Some((leaf, result));
+            }
+            // only split the nodes on the path to the next leaf
+            let (left, right) = elt.split_node();
+            self.node_queue.push_front(right);
+            self.node_queue.push_front(left);
+        }
+
+        self.next_leaf = None;
+        None
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        // count in 128 bits: with a stride of 1, a tree of height `MAX_HEIGHT`
+        // has 2^64 leaves
+        let count: u128 = match (self.next_leaf, self.node_queue.back()) {
+            (Some(leaf), Some(back)) if leaf <= back.range().bounds().1 => {
+                u128::from((back.range().bounds().1 - leaf) / self.stride) + 1
+            }
+            _ => 0,
+        };
+        usize::try_from(count).map_or((usize::MAX, None), |s| (s, Some(s)))
+    }
+}
 
 /// Iterator for key-derivation range-constrained PRF
 pub struct KeyDerivationRcPrfIterator<KeyType: Key> {
@@ -110,7 +222,11 @@ impl<KeyType: Key> DoubleEndedIterator for KeyDerivationRcPrfIterator<KeyType> {
     }
 }
 
-impl<KeyType: Key> ExactSizeIterator for KeyDerivationRcPrfIterator<KeyType> {}
+impl<KeyType: Key> ExactSizeIterator for KeyDerivationRcPrfIterator<KeyType> {
+    fn len(&self) -> usize {
+        self.inner.len()
+    }
+}
 
 /// Parallel iterator for [`RcPrf`]s
 #[cfg(feature = "rayon")]
@@ -211,43 +327,15 @@ pub mod parallel_iterator {
                     capacity,
                 );
 
-            let start_index = front.range().min();
+            let (start_index, _) = front.range().bounds();
             let leaf = start_index + index as u64; // do not forget the offset
 
-            self.node_queue.into_iter().for_each(|elt| {
-                if elt.range().max() < leaf {
-                    left_deque.push_back(elt);
-                } else if elt.range().contains_leaf(leaf)
-                    && elt.range().min() != leaf
-                // if min == leaf, we need to step into the next case
-                {
-                    // this is not super efficient as we compute some node
-                    // twice (the nodes of the path from the root to leaf)
-                    // Yet, this is elegant and the asymptotic complexity is
-                    // not affected.
-                    // Also, we can call 'unchecked_constrain' instead of
-                    // 'constrain' as, by construction, the middle of the range,
-                    // 'leaf', is in the range.
-                    let left_subtree = elt.unchecked_constrain(
-                        &RcPrfRange::from(elt.range().min()..leaf),
-                    );
-                    let right_subtree = elt.unchecked_constrain(
-                        &RcPrfRange::from(leaf..=elt.range().max()),
-                    );
-
-                    left_subtree
-                        .elements
-                        .into_iter()
-                        .for_each(|e| left_deque.push_back(e));
-                    right_subtree
-                        .elements
-                        .into_iter()
-                        .for_each(|e| right_deque.push_back(e));
-                } else {
-                    debug_assert!(elt.range().min() >= leaf);
-                    right_deque.push_back(elt);
-                }
-            });
+            split_elements(
+                self.node_queue,
+                leaf,
+                &mut left_deque,
+                &mut right_deque,
+            );
 
             (
                 RcPrfIterator {
@@ -1,6 +1,9 @@
 //! A range-constrained PRF meant for key derivation
 
 use super::*;
+use crate::AeadCipher;
+
+use std::collections::HashMap;
 
 pub(crate) mod key_derivation_private {
     use crate::{Key, RangePrf};
@@ -148,6 +151,29 @@ impl<KeyType: Key> KeyDerivationRcPrf<KeyType> {
         let constrained_rcprf = self.constrain(range)?;
         Ok(constrained_rcprf.into_key_par_iter())
     }
+
+    /// Returns a map associating every index of `range` to the key derived
+    /// from that index.
+    ///
+    /// All the keys of the range are kept in memory at the same time: the map
+    /// has `range.width()` entries, each of them holding a key and its
+    /// index, in addition to the map's own overhead. For large ranges, prefer
+    /// an iterator.
+    pub fn derive_map(
+        &self,
+        range: &RcPrfRange,
+    ) -> Result<HashMap<u64, KeyType>, RcPrfError> {
+        Ok(self.key_range_iter(range)?.collect())
+    }
+}
+
+impl KeyDerivationRcPrf<Key256> {
+    /// Returns an authenticated cipher keyed with the key derived from
+    /// `index`. The derived key is moved into the cipher, and is never exposed
+    /// to the caller.
+    pub fn cipher_at(&self, index: u64) -> Result<AeadCipher, RcPrfError> {
+        Ok(AeadCipher::from_key(self.derive_key(index)?))
+    }
 }
 
 /// A Constrained `KeyDerivationRcPrf` generating keys instead of bytes slices
@@ -190,6 +216,15 @@ impl<KeyType: Key> KeyDerivationConstrainedRcPrf<KeyType> {
         }
     }
 
+    /// Transform the constrained `KeyDerivationRcPrf` into an iterator that
+    /// produces pairs of index and keys derived from that index, by
+    /// decreasing index.
+    pub fn into_key_iter_rev(
+        self,
+    ) -> std::iter::Rev<iterator::KeyDerivationRcPrfIterator<KeyType>> {
+        self.into_key_iter().rev()
+    }
+
     /// Transform the constrained `KeyDerivationRcPrf` into a parallel iterator
     /// that can be used with the `rayon` crate, and which produces pairs of
     /// index and keys derived from that index.
@@ -205,6 +240,25 @@ impl<KeyType: Key> KeyDerivationConstrainedRcPrf<KeyType> {
     }
 }
 
+impl KeyDerivationConstrainedRcPrf<Key256> {
+    /// Transform the constrained `KeyDerivationRcPrf` into an iterator that
+    /// produces pairs of index and bytes of the key derived from that index.
+    ///
+    /// # Warning
+    /// The returned arrays are not zeroized on drop: it is up to the caller
+    /// to erase them once they are not needed anymore.
+    pub fn into_bytes_iter(self) -> impl Iterator<Item = (u64, [u8; 32])> {
+        self.into_inner().into_value_iter(Key256::KEY_SIZE).map(
+            |(i, mut buf)| {
+                let mut bytes = [0u8; 32];
+                bytes.copy_from_slice(&buf);
+                buf.zeroize();
+                (i, bytes)
+            },
+        )
+    }
+}
+
 #[cfg(test)]
 mod tests {
     #![allow(clippy::unwrap_used)]
@@ -249,6 +303,54 @@ mod tests {
         );
     }
 
+    #[test]
+    fn key_derivation_reverse_iterator() {
+        let h = 6u8;
+        let key_derivation = KeyDerivationRcPrf::<Key256>::new(h).unwrap();
+        let range = RcPrfRange::new(3, 28);
+
+        let keys: Vec<_> = key_derivation
+            .constrain(&range)
+            .unwrap()
+            .into_key_iter_rev()
+            .collect();
+
+        assert_eq!(keys.len(), range.width() as usize);
+        assert_eq!((keys[keys.len() - 1].0, keys[0].0), range.bounds());
+        for pair in keys.windows(2) {
+            assert!(pair[0].0 > pair[1].0);
+        }
+        for (i, k) in &keys {
+            assert_eq!(
+                k.content(),
+                key_derivation.derive_key(*i).unwrap().content()
+            );
+        }
+    }
+
+    #[test]
+    fn key_derivation_bytes_iterator() {
+        let h = 6u8;
+        let key_derivation = KeyDerivationRcPrf::<Key256>::new(h).unwrap();
+        let range = RcPrfRange::new(3, 28);
+
+        let keys: Vec<_> = key_derivation
+            .constrain(&range)
+            .unwrap()
+            .into_bytes_iter()
+            .collect();
+
+        assert_eq!(keys.len(), range.width() as usize);
+        for ((i, bytes), expected_index) in keys.iter().zip(range.bounds().0..)
+        {
+            assert_eq!(*i, expected_index);
+            assert_eq!(
+                &bytes[..],
+                key_derivation.derive_key(*i).unwrap().content()
+            );
+        }
+    }
+
     #[test]
     fn par_key_derivation_rcprf_consistency() {
         let h = 6u8;
@@ -279,12 +381,86 @@ mod tests {
             .unwrap()
             .collect();
 
-        keys.into_iter()
-            .zip(par_iter_keys.into_iter())
-            .zip(reference)
-            .for_each(|((k, (_i, k_iter)), reference)| {
+        keys.into_iter().zip(par_iter_keys).zip(reference).for_each(
+            |((k, (_i, k_iter)), reference)| {
                 assert_eq!(k.content(), reference);
                 assert_eq!(k_iter.content(), reference);
-            });
+            },
+        );
+    }
+
+    #[test]
+    fn key_derivation_map() {
+        let h = 6u8;
+        let key_derivation = KeyDerivationRcPrf::<Key256>::new(h).unwrap();
+        let range = RcPrfRange::new(3, 27);
+
+        let map = key_derivation.derive_map(&range).unwrap();
+
+        assert_eq!(map.len(), range.width() as usize);
+        for i in 3..=27 {
+            let k = key_derivation.derive_key(i).unwrap();
+            assert_eq!(map[&i].content(), k.content());
+        }
+    }
+
+    #[test]
+    fn cipher_at() {
+        const PLAINTEXT: &[u8] = b"Test plaintext";
+        let key_derivation = KeyDerivationRcPrf::<Key256>::new(6).unwrap();
+
+        let cipher = key_derivation.cipher_at(4).unwrap();
+        let same_cipher = key_derivation.cipher_at(4).unwrap();
+        let other_cipher = key_derivation.cipher_at(5).unwrap();
+
+        let mut ciphertext =
+            vec![0u8; PLAINTEXT.len() + AeadCipher::CIPHERTEXT_EXPANSION];
+        cipher.encrypt(PLAINTEXT, &mut ciphertext).unwrap();
+
+        assert_eq!(same_cipher.decrypt_to_vec(&ciphertext).unwrap(), PLAINTEXT);
+        assert!(other_cipher.decrypt_to_vec(&ciphertext).is_err());
+
+        assert!(key_derivation.cipher_at(max_leaf_index(6) + 1).is_err());
+    }
+
+    #[test]
+    fn key_derivation_iterator_len() {
+        let h = 6u8;
+        let key_derivation = KeyDerivationRcPrf::<Key256>::new(h).unwrap();
+
+        // iterate over all the possible ranges
+        for start in 0..=max_leaf_index(h) {
+            for end in start..=max_leaf_index(h) {
+                let range = RcPrfRange::new(start, end);
+                let mut iter = key_derivation.key_range_iter(&range).unwrap();
+
+                let mut remaining = range.width() as usize;
+                let (mut front, mut back) = (start, end);
+
+                // interleave calls to `next` and `next_back`
+                for i in 0.. {
+                    assert_eq!(iter.len(), remaining);
+                    assert_eq!(iter.size_hint(), (remaining, Some(remaining)));
+
+                    let item = if i % 3 == 0 {
+                        iter.next_back().map(|(x, _)| {
+                            assert_eq!(x, back);
+                            back = back.wrapping_sub(1);
+                        })
+                    } else {
+                        iter.next().map(|(x, _)| {
+                            assert_eq!(x, front);
+                            front += 1;
+                        })
+                    };
+
+                    if item.is_none() {
+                        break;
+                    }
+                    remaining -= 1;
+                }
+                assert_eq!(remaining, 0);
+            }
+        }
     }
 }
@@ -1,5 +1,6 @@
-use crate::private::RcPrfElementPair;
+use crate::private::{RcPrfElement, RcPrfElementPair};
 use crate::rcprf::*;
+use crate::serialization::byte_order::ByteOrder;
 use crate::Prf;
 
 use zeroize::Zeroize;
@@ -18,7 +19,7 @@ impl TreeBasedPrf for ConstrainedRcPrfLeafElement {
     }
 }
 
-impl private::RcPrfElement for ConstrainedRcPrfLeafElement {
+impl RcPrfElement for ConstrainedRcPrfLeafElement {
     fn is_leaf(&self) -> bool {
         true
     }
@@ -30,12 +31,29 @@ impl private::RcPrfElement for ConstrainedRcPrfLeafElement {
     fn split_node(&self) -> RcPrfElementPair {
         panic!("Invalid tree state: trying to split a leaf!");
     }
+
+    fn serialize_content_with_byte_order(
+        &self,
+        writer: &mut dyn std::io::Write,
+        byte_order: ByteOrder,
+    ) -> Result<usize, std::io::Error> {
+        writer.write_all(&self.rcprf_height.to_le_bytes())?;
+        writer.write_all(&byte_order.u64_to_bytes(self.index))?;
+        self.prf.serialize_content(writer)?;
+
+        Ok(self.serialization_content_byte_size())
+    }
 }
 
 impl private::UncheckedRangePrf for ConstrainedRcPrfLeafElement {
     fn unchecked_eval(&self, x: u64, output: &mut [u8]) {
         debug_assert_eq!(x, self.index);
-        self.prf.fill_bytes(&[0u8], output);
+        // most evaluations fit in a single block of the PRF
+        if output.len() <= blake2b_simd::OUTBYTES {
+            self.prf.fill_bytes_single_block(&[0u8], output);
+        } else {
+            self.prf.fill_bytes(&[0u8], output);
+        }
     }
 
     fn unchecked_eval_range(
@@ -64,9 +82,7 @@ impl private::UncheckedRangePrf for ConstrainedRcPrfLeafElement {
 
         // here, we do have to copy the PRF
         // We do so by getting the key and copying it
-        ConstrainedRcPrf {
-            elements: vec![Box::pin(self.insecure_clone())],
-        }
+        ConstrainedRcPrf::from_element(Box::pin(self.insecure_clone()))
     }
 }
 
@@ -97,17 +113,24 @@ impl SerializableCleartextContent for ConstrainedRcPrfLeafElement {
         &self,
         writer: &mut dyn std::io::Write,
     ) -> Result<usize, std::io::Error> {
-        writer.write_all(&self.rcprf_height.to_le_bytes())?;
-        writer.write_all(&self.index.to_le_bytes())?;
-        self.prf.serialize_content(writer)?;
-
-        Ok(self.serialization_content_byte_size())
+        self.serialize_content_with_byte_order(writer, ByteOrder::default())
     }
 }
 
 impl DeserializableCleartextContent for ConstrainedRcPrfLeafElement {
     fn deserialize_content(
         reader: &mut dyn std::io::Read,
+    ) -> Result<Self, CleartextContentDeserializationError> {
+        Self::deserialize_content_with_byte_order(reader, ByteOrder::default())
+    }
+}
+
+impl ConstrainedRcPrfLeafElement {
+    /// Deserialize the content of an element whose integers are encoded with
+    /// the `byte_order` byte order
+    pub(crate) fn deserialize_content_with_byte_order(
+        reader: &mut dyn std::io::Read,
+        byte_order: ByteOrder,
     ) -> Result<Self, CleartextContentDeserializationError> {
         let mut h_bytes = [0u8; 1];
         reader.read_exact(&mut h_bytes)?;
@@ -115,7 +138,7 @@ impl DeserializableCleartextContent for ConstrainedRcPrfLeafElement {
 
         let mut i_bytes = [0u8; 8];
         reader.read_exact(&mut i_bytes)?;
-        let index = u64::from_le_bytes(i_bytes);
+        let index = byte_order.u64_from_bytes(i_bytes);
 
         Ok(ConstrainedRcPrfLeafElement {
             prf: Prf::deserialize_content(reader)?,
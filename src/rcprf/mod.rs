@@ -1,11 +1,14 @@
 //! Range-constrained PRF
 
+use std::collections::VecDeque;
 use std::pin::Pin;
 
+use crate::hash::Hash;
 use crate::insecure_clone::private::InsecureClone;
 use crate::key::Key256;
+use crate::prf::KeyDerivationPrf;
 use crate::prg::KeyDerivationPrg;
-use crate::rcprf::errors::*;
+use crate::serialization::byte_order::ByteOrder;
 use crate::serialization::cleartext_serialization::*;
 use crate::serialization::errors::*;
 use crate::tags::SerializationTag;
@@ -13,7 +16,7 @@ use crate::tags::SerializationTaggedType;
 use crate::Key;
 
 // use clear_on_drop::clear::Clear;
-use zeroize::Zeroize;
+use zeroize::{Zeroize, Zeroizing};
 
 /// Range structure and functions for use with range-constrained PRFs.
 pub mod rcprf_range;
@@ -34,10 +37,14 @@ pub mod key_derivation;
 /// Specific error types
 pub mod errors;
 
-pub use crate::errors::*;
+/// Step-by-step construction of the tree of range-constrained PRFs
+pub mod builder;
+
+pub use crate::builder::*;
 use crate::inner_element::*;
 pub use crate::key_derivation::*;
 use crate::leaf_element::*;
+pub use crate::rcprf::errors::*;
 pub use crate::rcprf_range::*;
 pub use crate::traits::*;
 
@@ -64,9 +71,14 @@ pub(crate) enum RcPrfTreeNodeChild {
 /// Maximum tree height of a `RcPrf` tree
 pub const MAX_HEIGHT: u8 = 65;
 
+/// Minimum tree height of a `RcPrf` tree (a root and two leaves)
+pub const MIN_HEIGHT: u8 = 2;
+
 /// Returns the maximum leaf index for a `RcPrf` using a tree of height
 /// `height`. It returns 0 for a tree of height 0 and 2^64-1 for a `height`
-/// larger or equal to `MAX_HEIGHT` (65)
+/// larger or equal to `MAX_HEIGHT` (65).
+/// Note that trees of height 0 (and 1) are degenerate, and are rejected by the
+/// `RcPrf` constructors.
 #[must_use]
 pub const fn max_leaf_index(height: u8) -> u64 {
     if height == 0 {
@@ -78,6 +90,121 @@ pub const fn max_leaf_index(height: u8) -> u64 {
     (1u64 << (height - 1)) - 1
 }
 
+/// Returns the number of elements of the `ConstrainedRcPrf` obtained by
+/// constraining a `RcPrf` of height `height` to `range`, without deriving any
+/// key. Returns an error if `range` is not included in the range of the tree.
+pub fn cover_size(height: u8, range: &RcPrfRange) -> Result<u64, RcPrfError> {
+    let tree_range = RcPrfRange::new(0, max_leaf_index(height));
+    if !tree_range.contains_range(range) {
+        return Err(RcPrfError::InvalidConstrainRange(
+            range.clone(),
+            tree_range,
+        ));
+    }
+    // the cover has at most `2*height` nodes, and computing their ranges does
+    // not derive any key
+    let mut nodes = Vec::with_capacity(2 * usize::from(height));
+    subtree_cover_nodes(&tree_range, height, range, &mut nodes);
+    Ok(nodes.len() as u64)
+}
+
+/// Returns an upper bound on the size of the cleartext serialization of the
+/// `ConstrainedRcPrf` obtained by constraining a `RcPrf` of height `height` to
+/// `range`, without building the object. The bound assumes the worst case of
+/// `2*height` elements (or one element per leaf for narrow ranges), all of
+/// them being of the largest element type.
+#[must_use]
+pub fn constrained_rcprf_serialized_size_bound(
+    height: u8,
+    range: &RcPrfRange,
+) -> usize {
+    // tag and format version of each serialized object
+    let header_size =
+        SerializationTag::SERIALIZATION_SIZE + FORMAT_VERSION_SIZE;
+    // key, range, subtree height and tree height
+    let inner_element_size = Key256::KEY_SIZE + 2 * 8 + 2;
+    // key, index and tree height
+    let leaf_element_size = Key256::KEY_SIZE + 8 + 1;
+    let element_size = header_size + inner_element_size.max(leaf_element_size);
+
+    // the width of the full range of a tree of height `MAX_HEIGHT` does not fit
+    // in 64 bits
+    let width = (range.max() - range.min()).saturating_add(1);
+    let max_elements = width.min(2 * u64::from(height));
+
+    // the number of elements is encoded on 64 bits
+    header_size
+        + std::mem::size_of::<u64>()
+        + element_size * max_elements as usize
+}
+
+/// Push to `nodes`, from left to right, the ranges of the nodes of the subtree
+/// of height `subtree_height` spanning `subtree_range` that cover `range`.
+/// Mirrors the recursion of `unchecked_constrain` for inner elements.
+fn subtree_cover_nodes(
+    subtree_range: &RcPrfRange,
+    subtree_height: u8,
+    range: &RcPrfRange,
+    nodes: &mut Vec<RcPrfRange>,
+) {
+    if subtree_range == range || subtree_height <= 2 {
+        // either the whole subtree, or a single leaf
+        nodes.push(range.clone());
+        return;
+    }
+
+    let half_width = 1u64 << (subtree_height - 2);
+    let left_range = RcPrfRange::new(
+        subtree_range.min(),
+        subtree_range.min() + half_width - 1,
+    );
+    let right_range =
+        RcPrfRange::new(subtree_range.min() + half_width, subtree_range.max());
+
+    for r in &[left_range, right_range] {
+        if let Some(sub) = r.intersection(range) {
+            subtree_cover_nodes(r, subtree_height - 1, &sub, nodes);
+        }
+    }
+}
+
+/// Push to `constrained`, from left to right, the complete subtrees of `elt`
+/// whose leaves are all in `points`, splitting the nodes containing both
+/// listed and unlisted leaves. A subtree is appended to the last constrained
+/// `RcPrf` of `constrained` if their ranges are consecutive, and starts a new
+/// constrained `RcPrf` otherwise. `points` must be sorted, deduplicated, and
+/// contained in the range of `elt`.
+fn constrain_element_on_points(
+    elt: Pin<Box<dyn private::RcPrfElement>>,
+    points: &[u64],
+    constrained: &mut Vec<ConstrainedRcPrf>,
+) {
+    if points.is_empty() {
+        // nothing to keep in this subtree (the element is zeroized on drop)
+        return;
+    }
+    let (min, max) = elt.range().bounds();
+    // points are distinct and in the range: the subtree is complete iff there
+    // are as many points as leaves (comparing with the width minus one avoids
+    // an overflow for the full range of the largest trees)
+    if (points.len() - 1) as u64 == max - min {
+        match constrained.last_mut() {
+            Some(last) if last.range().bounds().1 + 1 == min => {
+                last.elements.push_back(elt);
+            }
+            _ => constrained.push(ConstrainedRcPrf::from_element(elt)),
+        }
+        return;
+    }
+
+    // a leaf is always complete, so `elt` is an inner node
+    let (left, right) = elt.split_node();
+    let (_, left_max) = left.range().bounds();
+    let split = points.partition_point(|&p| p <= left_max);
+    constrain_element_on_points(left, &points[..split], constrained);
+    constrain_element_on_points(right, &points[split..], constrained);
+}
+
 fn get_child_node(
     height: u8,
     leaf_index: u64,
@@ -105,8 +232,18 @@ pub struct RcPrf {
 
 /// A *constrained* range PRFs object (obtained after constraining a range PRF -
 /// constrained or not)
+///
+/// ## Zeroization
+///
+/// A constrained `RcPrf` is made of a sequence of elements (inner nodes or
+/// leaves of the tree), each of them owning its own key material. Every
+/// element zeroizes its key when it is dropped, so dropping a
+/// `ConstrainedRcPrf` zeroizes the elements one after the other, in the order
+/// of their ranges. Calling `zeroize` drops (and hence zeroizes) all the
+/// elements immediately, and leaves an empty object behind, which cannot be
+/// evaluated anymore (see `ConstrainedRcPrf::is_empty`).
 pub struct ConstrainedRcPrf {
-    elements: Vec<Pin<Box<dyn private::RcPrfElement>>>,
+    elements: VecDeque<Pin<Box<dyn private::RcPrfElement>>>,
 }
 
 impl TreeBasedPrf for RcPrf {
@@ -156,9 +293,13 @@ impl RcPrf {
 
     /// Returns a new `RcPrf` based on a tree of height `height`, with the given
     /// root key.
+    /// Returns an error if `height` is not between `MIN_HEIGHT` and
+    /// `MAX_HEIGHT`.
     pub fn from_key(root: Key256, height: u8) -> Result<Self, RcPrfError> {
-        if height > MAX_HEIGHT {
-            return Err(RcPrfError::InvalidTreeHeight(height, MAX_HEIGHT));
+        if !(MIN_HEIGHT..=MAX_HEIGHT).contains(&height) {
+            return Err(RcPrfError::InvalidTreeHeight(
+                height, MIN_HEIGHT, MAX_HEIGHT,
+            ));
         }
         Ok(RcPrf {
             root: ConstrainedRcPrfInnerElement {
@@ -170,6 +311,284 @@ impl RcPrf {
         })
     }
 
+    /// Returns a new `RcPrf` based on a tree of height `height`, whose root is
+    /// derived from the `master` key and the domain `label`.
+    ///
+    /// Trees built from the same master key with different labels are
+    /// independent, while using the same master key and label always results
+    /// in the same tree.
+    pub fn from_master(
+        master: &Key256,
+        label: &[u8],
+        height: u8,
+    ) -> Result<Self, RcPrfError> {
+        let root =
+            KeyDerivationPrf::<Key256>::from_key(master.insecure_clone())
+                .derive_key(label);
+        Self::from_key(root, height)
+    }
+
+    /// Constrain the `RcPrf` to `range`, unless the resulting
+    /// `ConstrainedRcPrf` would be made of more than `max_elements` elements.
+    ///
+    /// The size of the cover is computed before deriving any key (see
+    /// `cover_size`), so rejecting a range is cheap. This is meant to be used
+    /// when constraining on ranges coming from untrusted requests.
+    pub fn constrain_bounded(
+        &self,
+        range: &RcPrfRange,
+        max_elements: usize,
+    ) -> Result<ConstrainedRcPrf, RcPrfError> {
+        let size = cover_size(self.tree_height(), range)?;
+        if size > max_elements as u64 {
+            return Err(RcPrfError::CoverTooLarge(
+                range.clone(),
+                size,
+                max_elements,
+            ));
+        }
+        self.constrain(range)
+    }
+
+    /// Returns the ranges of the minimal set of complete subtrees whose union
+    /// is `range`, from left to right, without deriving any key. These are
+    /// the ranges of the elements of the `ConstrainedRcPrf` obtained by
+    /// constraining the `RcPrf` to `range`, and there are at most `2*height`
+    /// of them.
+    /// Returns an error if `range` is not included in the range of the tree.
+    pub fn covering_nodes(
+        &self,
+        range: &RcPrfRange,
+    ) -> Result<Vec<RcPrfRange>, RcPrfError> {
+        let tree_range = self.range();
+        if !tree_range.contains_range(range) {
+            return Err(RcPrfError::InvalidConstrainRange(
+                range.clone(),
+                tree_range,
+            ));
+        }
+        let mut nodes = Vec::new();
+        subtree_cover_nodes(&tree_range, self.tree_height(), range, &mut nodes);
+        Ok(nodes)
+    }
+
+    /// Constrain the `RcPrf` on the set of leaves `points`, which do not need
+    /// to be contiguous. Returns one constrained `RcPrf` per maximal run of
+    /// consecutive points, in increasing order: the range of each of them is
+    /// exactly its run of points. Together, they are made of the minimal set
+    /// of complete subtrees covering exactly the points, and the derivations
+    /// of the common ancestors of these subtrees are only done once.
+    /// Returns an error if `points` is empty, or if one of the points is out
+    /// of the range of the tree.
+    ///
+    /// # Example
+    /// ```
+    /// # extern crate crypto_tk_rs;
+    /// use crypto_tk_rs::{RangePrf, RcPrf, RcPrfRange};
+    ///
+    /// let rcprf = RcPrf::new(5).unwrap();
+    /// let constrained = rcprf.constrain_points(&[2, 3, 9]).unwrap();
+    ///
+    /// assert_eq!(constrained.len(), 2);
+    /// assert_eq!(constrained[0].range(), RcPrfRange::new(2, 3));
+    /// assert_eq!(constrained[1].range(), RcPrfRange::new(9, 9));
+    ///
+    /// let mut out = [0u8; 16];
+    /// let mut expected = [0u8; 16];
+    /// constrained[1].eval(9, &mut out).unwrap();
+    /// rcprf.eval(9, &mut expected).unwrap();
+    /// assert_eq!(out, expected);
+    /// ```
+    pub fn constrain_points(
+        &self,
+        points: &[u64],
+    ) -> Result<Vec<ConstrainedRcPrf>, RcPrfError> {
+        if points.is_empty() {
+            return Err(RcPrfError::EmptyPointSet);
+        }
+        let tree_range = self.range();
+        if let Some(&p) = points.iter().find(|&&p| !tree_range.contains_leaf(p))
+        {
+            return Err(RcPrfError::InvalidConstrainRange(
+                RcPrfRange::new(p, p),
+                tree_range,
+            ));
+        }
+
+        let mut sorted_points = points.to_vec();
+        sorted_points.sort_unstable();
+        sorted_points.dedup();
+
+        let mut constrained = Vec::new();
+        constrain_element_on_points(
+            Box::pin(self.root.insecure_clone()),
+            &sorted_points,
+            &mut constrained,
+        );
+        Ok(constrained)
+    }
+
+    /// Returns the key of the node of the tree whose subtree spans exactly
+    /// `range`. The returned key can be used with `RcPrf::from_key` to build
+    /// the `RcPrf` of that subtree, whose leaves are the ones of `range`
+    /// (shifted to start at 0). For single leaves, this is the key given to
+    /// the leaf PRF.
+    /// Returns an error if `range` is not included in the range of the tree,
+    /// or if it is not the range of a subtree.
+    pub fn subtree_key(
+        &self,
+        range: &RcPrfRange,
+    ) -> Result<Key256, RcPrfError> {
+        let tree_range = self.range();
+        if !tree_range.contains_range(range) {
+            return Err(RcPrfError::InvalidConstrainRange(
+                range.clone(),
+                tree_range,
+            ));
+        }
+        let (index, suffix_bits) = range
+            .as_prefix()
+            .ok_or_else(|| RcPrfError::InvalidSubtreeRange(range.clone()))?;
+
+        // the range is included in the tree's range, so its width is at most
+        // the number of leaves of the tree
+        let depth = self.tree_height() - 1 - suffix_bits;
+        RcPrfBuilder::new(
+            self.root.prg.key().insecure_clone(),
+            self.tree_height(),
+        )?
+        .derive_node_key(depth, index)
+    }
+
+    /// Returns the key of the node at depth `depth`, reached by walking down
+    /// the tree from the root along the `depth` bits of `path`, most
+    /// significant bit first (`0` goes to the left child, and `1` to the
+    /// right child). The node at depth `tree_height() - 1` and path `x` is the
+    /// leaf `x`, and its key is the one given to the leaf PRF.
+    /// Returns an error if `depth` is not smaller than `tree_height()`, or if
+    /// `path` has more than `depth` bits.
+    pub fn node_key(&self, depth: u8, path: u64) -> Result<Key256, RcPrfError> {
+        RcPrfBuilder::new(
+            self.root.prg.key().insecure_clone(),
+            self.tree_height(),
+        )?
+        .derive_node_key(depth, path)
+    }
+
+    /// Evaluate the `RcPrf` on the leaf reached by walking down the tree along
+    /// the path `bits`, starting from the root: `false` goes to the left
+    /// child, and `true` to the right child. The path must be made of
+    /// `tree_height() - 1` bits. The result is put in `output`.
+    ///
+    /// The path of the leaf `x` is the binary representation of `x`, most
+    /// significant bit first. This function lets callers use other encodings
+    /// of the leaves without having to reorder the bits of their indices.
+    /// Returns an error when the length of `bits` is invalid, or when `output`
+    /// is empty.
+    ///
+    /// # Example
+    /// ```
+    /// # extern crate crypto_tk_rs;
+    /// use crypto_tk_rs::{RangePrf, RcPrf};
+    ///
+    /// let rcprf = RcPrf::new(4).unwrap();
+    /// let mut out = [0u8; 16];
+    /// let mut expected = [0u8; 16];
+    ///
+    /// rcprf.eval_bits(&[true, false, true], &mut out).unwrap();
+    /// rcprf.eval(0b101, &mut expected).unwrap();
+    /// assert_eq!(out, expected);
+    /// ```
+    pub fn eval_bits(
+        &self,
+        bits: &[bool],
+        output: &mut [u8],
+    ) -> Result<(), RcPrfError> {
+        if bits.len() != usize::from(self.tree_height() - 1) {
+            return Err(RcPrfError::InvalidBitPathLength(
+                bits.len(),
+                self.tree_height(),
+            ));
+        }
+        if output.is_empty() {
+            return Err(RcPrfError::EmptyOutput);
+        }
+
+        // the path has at most 64 bits, and is the index of the leaf
+        let leaf = bits
+            .iter()
+            .fold(0u64, |index, &bit| (index << 1) | u64::from(bit));
+        let key = self.node_key(self.tree_height() - 1, leaf)?;
+        RcPrfBuilder::eval_leaf_key(&key, output);
+        Ok(())
+    }
+
+    /// Check that `constrained` is a constraint of `self`, by comparing their
+    /// evaluations on `sample_points`. The points that are not in the range of
+    /// `constrained` are ignored.
+    ///
+    /// This is a probabilistic check, not a proof: it only ensures that both
+    /// PRFs agree on the sample points. Returns `false` if the PRFs disagree
+    /// on one of the points, if their trees have different heights, or if no
+    /// sample point is in the range of `constrained`.
+    #[must_use]
+    pub fn is_consistent_with(
+        &self,
+        constrained: &ConstrainedRcPrf,
+        sample_points: &[u64],
+    ) -> bool {
+        if constrained.elements.is_empty()
+            || constrained.tree_height() != self.tree_height()
+        {
+            return false;
+        }
+
+        let constrained_range = constrained.range();
+        let mut checked = false;
+        let mut out = Zeroizing::new([0u8; 16]);
+        let mut constrained_out = Zeroizing::new([0u8; 16]);
+
+        for &x in sample_points
+            .iter()
+            .filter(|&&x| constrained_range.contains_leaf(x))
+        {
+            if self.eval(x, &mut *out).is_err()
+                || constrained.eval(x, &mut *constrained_out).is_err()
+                || *out != *constrained_out
+            {
+                return false;
+            }
+            checked = true;
+        }
+        checked
+    }
+
+    /// Constrain the `RcPrf` to the leaves of `new` that are not in `old`.
+    ///
+    /// This is useful when a range grows from `old` to `new`: the holder of
+    /// the constrained `RcPrf` for `old` only needs the returned delta to be
+    /// able to evaluate the PRF on `new`. `new` must contain `old` and extend
+    /// it on exactly one side (_e.g._ from `[a,b]` to `[a,c]` with `c > b`),
+    /// otherwise an `InvalidDeltaRange` error is returned.
+    pub fn constrain_delta(
+        &self,
+        old: &RcPrfRange,
+        new: &RcPrfRange,
+    ) -> Result<ConstrainedRcPrf, RcPrfError> {
+        let delta = if old.min() == new.min() && old.max() < new.max() {
+            RcPrfRange::new(old.max() + 1, new.max())
+        } else if old.max() == new.max() && new.min() < old.min() {
+            RcPrfRange::new(new.min(), old.min() - 1)
+        } else {
+            return Err(RcPrfError::InvalidDeltaRange(
+                old.clone(),
+                new.clone(),
+            ));
+        };
+
+        self.constrain(&delta)
+    }
+
     /// Returns an iterator of (`index`,`value`) pairs such that `value` is the
     /// evaluation of the `RcPrf` on `index`.
     /// The values generated by this iterator are vectors of `output_width`
@@ -199,6 +618,45 @@ impl RcPrf {
 
         Ok(constrained_rcprf.into_value_par_iter(output_width))
     }
+
+    /// Evaluate the `RcPrf` on the leaves of `range`, in increasing order,
+    /// with outputs of `output_width` bytes, and returns the first leaf (and
+    /// its evaluation) satisfying the predicate `pred`. Returns `None` if no
+    /// leaf of the range satisfies the predicate.
+    /// The evaluation stops as soon as a match is found, and the evaluations
+    /// of non-matching leaves are zeroized.
+    pub fn find_leaf<P>(
+        &self,
+        range: &RcPrfRange,
+        output_width: usize,
+        mut pred: P,
+    ) -> Result<Option<(u64, Vec<u8>)>, RcPrfError>
+    where
+        P: FnMut(u64, &[u8]) -> bool,
+    {
+        for (index, mut value) in self.value_range_iter(range, output_width)? {
+            if pred(index, &value) {
+                return Ok(Some((index, value)));
+            }
+            value.zeroize();
+        }
+        Ok(None)
+    }
+
+    /// Evaluate the `RcPrf` on every value of `range`, with outputs of
+    /// `output_width` bytes, and returns the hashes of these outputs, in the
+    /// order of the range. The raw outputs of the PRF are zeroized once
+    /// hashed, and are never exposed to the caller.
+    pub fn hash_range(
+        &self,
+        range: &RcPrfRange,
+        output_width: usize,
+    ) -> Result<Vec<Hash>, RcPrfError> {
+        Ok(self
+            .value_range_iter(range, output_width)?
+            .map(|(_, value)| Hash::new(&Zeroizing::new(value)))
+            .collect())
+    }
 }
 
 impl private::UncheckedRangePrf for ConstrainedRcPrf {
@@ -254,14 +712,14 @@ impl private::UncheckedRangePrf for ConstrainedRcPrf {
 
     fn unchecked_constrain(&self, range: &RcPrfRange) -> ConstrainedRcPrf {
         let mut constrained_rcprf = ConstrainedRcPrf {
-            elements: Vec::new(),
+            elements: VecDeque::new(),
         };
 
         for elt in &self.elements {
             if let Some(r) = elt.range().intersection(range) {
                 // We are in an 'unchecked' function so it is OK to panic.
                 // Also, if the invariants are respected, consecutive elements
-                // in the `elements` vector have consecutive ranges, and the
+                // in the `elements` deque have consecutive ranges, and the
                 // `merge` function should not return an error
                 #[allow(clippy::unwrap_used)]
                 constrained_rcprf
@@ -275,18 +733,101 @@ impl private::UncheckedRangePrf for ConstrainedRcPrf {
 }
 
 impl TreeBasedPrf for ConstrainedRcPrf {
+    /// Returns the height of the underlying tree, or 0 if the constrained
+    /// `RcPrf` is empty (it is not attached to any tree anymore).
     fn tree_height(&self) -> u8 {
-        debug_assert!(!self.elements.is_empty());
-        self.elements[0].tree_height()
+        self.elements.front().map_or(0, |elt| elt.tree_height())
     }
 }
 
 impl RangePrf for ConstrainedRcPrf {
+    /// Returns the range of the constrained `RcPrf`. An empty constrained
+    /// `RcPrf` has no range: by convention, `[0, 0]` is returned, but none of
+    /// the evaluation functions succeed on that range.
     fn range(&self) -> RcPrfRange {
-        RcPrfRange::new(
-            self.elements[0].range().min(),
-            self.elements[self.elements.len() - 1].range().max(),
-        )
+        match (self.elements.front(), self.elements.back()) {
+            (Some(first), Some(last)) => {
+                let (min, _) = first.range().bounds();
+                let (_, max) = last.range().bounds();
+                RcPrfRange::new(min, max)
+            }
+            _ => RcPrfRange::new(0, 0),
+        }
+    }
+
+    // The following functions are overridden to reject the empty constrained
+    // `RcPrf`s, whose range is only a placeholder
+
+    fn eval(&self, x: u64, output: &mut [u8]) -> Result<(), RcPrfError> {
+        if self.is_empty() {
+            Err(RcPrfError::EmptyConstrainedRcPrf)
+        } else if output.is_empty() {
+            Err(RcPrfError::EmptyOutput)
+        } else if !self.range().contains_leaf(x) {
+            Err(RcPrfError::InvalidEvalPoint(x, self.range()))
+        } else {
+            private::UncheckedRangePrf::unchecked_eval(self, x, output);
+            Ok(())
+        }
+    }
+
+    fn eval_range(
+        &self,
+        range: &RcPrfRange,
+        outputs: &mut [&mut [u8]],
+    ) -> Result<(), RcPrfError> {
+        if self.is_empty() {
+            Err(RcPrfError::EmptyConstrainedRcPrf)
+        } else if !self.range().contains_range(range) {
+            Err(RcPrfError::InvalidEvalRange(range.clone(), self.range()))
+        } else if range.width() != outputs.len() as u64 {
+            Err(RcPrfError::InvalidRangeWidth(outputs.len(), range.width()))
+        } else if outputs.iter().any(|out| out.is_empty()) {
+            Err(RcPrfError::EmptyOutput)
+        } else {
+            private::UncheckedRangePrf::unchecked_eval_range(
+                self, range, outputs,
+            );
+            Ok(())
+        }
+    }
+
+    #[cfg(feature = "rayon")]
+    fn par_eval_range(
+        &self,
+        range: &RcPrfRange,
+        outputs: &mut [&mut [u8]],
+    ) -> Result<(), RcPrfError> {
+        if self.is_empty() {
+            Err(RcPrfError::EmptyConstrainedRcPrf)
+        } else if !self.range().contains_range(range) {
+            Err(RcPrfError::InvalidEvalRange(range.clone(), self.range()))
+        } else if range.width() != outputs.len() as u64 {
+            Err(RcPrfError::InvalidRangeWidth(outputs.len(), range.width()))
+        } else if outputs.iter().any(|out| out.is_empty()) {
+            Err(RcPrfError::EmptyOutput)
+        } else {
+            private::UncheckedRangePrf::unchecked_par_eval_range(
+                self, range, outputs,
+            );
+            Ok(())
+        }
+    }
+
+    fn constrain(
+        &self,
+        range: &RcPrfRange,
+    ) -> Result<ConstrainedRcPrf, RcPrfError> {
+        if self.is_empty() {
+            Err(RcPrfError::EmptyConstrainedRcPrf)
+        } else if !self.range().contains_range(range) {
+            Err(RcPrfError::InvalidConstrainRange(
+                range.clone(),
+                self.range(),
+            ))
+        } else {
+            Ok(private::UncheckedRangePrf::unchecked_constrain(self, range))
+        }
     }
 }
 
@@ -298,6 +839,15 @@ impl Zeroize for ConstrainedRcPrf {
 }
 
 impl ConstrainedRcPrf {
+    /// Returns a constrained `RcPrf` made of the single element `elt`
+    pub(crate) fn from_element(
+        elt: Pin<Box<dyn private::RcPrfElement>>,
+    ) -> Self {
+        ConstrainedRcPrf {
+            elements: std::iter::once(elt).collect(),
+        }
+    }
+
     fn merge(
         &mut self,
         mut merged_rcprf: ConstrainedRcPrf,
@@ -309,15 +859,17 @@ impl ConstrainedRcPrf {
             return Ok(());
         } else if merged_rcprf.elements.is_empty() {
             return Ok(());
-        } else if self.range().max() < merged_rcprf.range().min() {
-            if merged_rcprf.range().min() - self.range().max() == 1 {
+        }
+
+        let (min, max) = self.range().bounds();
+        let (merged_min, merged_max) = merged_rcprf.range().bounds();
+        if max < merged_min {
+            if merged_min - max == 1 {
                 // we must append the elements of merged_rcprf to ours
                 self.elements.append(&mut merged_rcprf.elements);
                 return Ok(());
             }
-        } else if self.range().min() > merged_rcprf.range().max()
-            && self.range().min() - merged_rcprf.range().max() == 1
-        {
+        } else if min > merged_max && min - merged_max == 1 {
             // we must prepend the elements of merged_rcprf to ours
             merged_rcprf.elements.append(&mut self.elements);
             self.elements = merged_rcprf.elements;
@@ -330,14 +882,367 @@ impl ConstrainedRcPrf {
         ))
     }
 
+    /// Concatenate two constrained `RcPrf`s with adjacent ranges (in any
+    /// order) into a single constrained `RcPrf`, whose range is the union of
+    /// both ranges. The two objects must come from the same `RcPrf`: as the
+    /// keys are never compared, this cannot be checked beyond the height of
+    /// the underlying trees.
+    /// Returns an error if the ranges are not consecutive, or if the trees
+    /// have different heights.
+    ///
+    /// # Example
+    /// ```
+    /// # extern crate crypto_tk_rs;
+    /// use crypto_tk_rs::{RangePrf, RcPrf, RcPrfRange};
+    ///
+    /// let rcprf = RcPrf::new(4).unwrap();
+    /// let left = rcprf.constrain(&RcPrfRange::new(0, 3)).unwrap();
+    /// let right = rcprf.constrain(&RcPrfRange::new(4, 7)).unwrap();
+    ///
+    /// let merged = left.concat(right).unwrap();
+    /// assert_eq!(merged.range(), RcPrfRange::new(0, 7));
+    /// ```
+    pub fn concat(
+        mut self,
+        other: ConstrainedRcPrf,
+    ) -> Result<ConstrainedRcPrf, RcPrfError> {
+        if !self.elements.is_empty()
+            && !other.elements.is_empty()
+            && self.tree_height() != other.tree_height()
+        {
+            return Err(RcPrfError::TreeHeightMismatch(
+                self.tree_height(),
+                other.tree_height(),
+            ));
+        }
+        self.merge(other)?;
+        Ok(self)
+    }
+
+    /// Split the constrained `RcPrf` of range `[a,b]` into two constrained
+    /// `RcPrf`s of ranges `[a,index]` and `[index+1,b]`. This is the inverse
+    /// of `concat`. The element containing both `index` and `index+1` (if
+    /// any) is itself split as by `constrain`, so no key material is shared
+    /// between the two halves.
+    /// Returns an error if `index` is not in `[a,b-1]`.
+    ///
+    /// # Example
+    /// ```
+    /// # extern crate crypto_tk_rs;
+    /// use crypto_tk_rs::{RangePrf, RcPrf, RcPrfRange};
+    ///
+    /// let rcprf = RcPrf::new(4).unwrap();
+    /// let constrained = rcprf.constrain(&RcPrfRange::new(1, 6)).unwrap();
+    ///
+    /// let (left, right) = constrained.split_at(2).unwrap();
+    /// assert_eq!(left.range(), RcPrfRange::new(1, 2));
+    /// assert_eq!(right.range(), RcPrfRange::new(3, 6));
+    /// ```
+    pub fn split_at(
+        self,
+        index: u64,
+    ) -> Result<(ConstrainedRcPrf, ConstrainedRcPrf), RcPrfError> {
+        if self.elements.is_empty()
+            || !self.range().contains_leaf(index)
+            || index == self.range().bounds().1
+        {
+            return Err(RcPrfError::InvalidSplitIndex(index, self.range()));
+        }
+
+        let mut left = VecDeque::with_capacity(self.elements.len());
+        let mut right = VecDeque::with_capacity(self.elements.len());
+        // `index + 1` does not overflow as `index` is not the maximum of the
+        // range
+        split_elements(self.elements, index + 1, &mut left, &mut right);
+
+        Ok((
+            ConstrainedRcPrf { elements: left },
+            ConstrainedRcPrf { elements: right },
+        ))
+    }
+
+    /// Returns the range and the subtree height of each of the elements the
+    /// constrained `RcPrf` is made of, in the order of the ranges.
+    /// This does not touch any key material.
+    #[must_use]
+    pub fn element_ranges(&self) -> Vec<(RcPrfRange, u8)> {
+        self.elements
+            .iter()
+            .map(|e| (e.range(), e.subtree_height()))
+            .collect()
+    }
+
+    /// Consume the constrained `RcPrf` and returns the ranges of its elements,
+    /// in order. All the elements (and their key material) are zeroized
+    /// before returning, so only the routing metadata is kept.
+    ///
+    /// # Example
+    /// ```
+    /// # extern crate crypto_tk_rs;
+    /// use crypto_tk_rs::{RangePrf, RcPrf, RcPrfRange};
+    ///
+    /// let rcprf = RcPrf::new(4).unwrap();
+    /// let constrained = rcprf.constrain(&RcPrfRange::new(1, 4)).unwrap();
+    ///
+    /// assert_eq!(
+    ///     constrained.into_range_map(),
+    ///     vec![
+    ///         RcPrfRange::new(1, 1),
+    ///         RcPrfRange::new(2, 3),
+    ///         RcPrfRange::new(4, 4)
+    ///     ]
+    /// );
+    /// ```
+    #[must_use]
+    pub fn into_range_map(mut self) -> Vec<RcPrfRange> {
+        let ranges = self.elements.iter().map(|e| e.range()).collect();
+        self.zeroize();
+        ranges
+    }
+
+    /// Returns `true` if the constrained `RcPrf` has no element left (after
+    /// being zeroized, or once all its elements have been popped with
+    /// `pop_front_element`). An empty constrained `RcPrf` cannot be evaluated.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.elements.is_empty()
+    }
+
+    /// Remove the leftmost element of the constrained `RcPrf`, and returns
+    /// its range together with an iterator over the leaves of that element
+    /// only. Values produced by the iterator are vectors of size `out_size`.
+    /// The element is moved into the iterator, and is zeroized when the
+    /// iterator is dropped. Returns `None` once all the elements have been
+    /// removed.
+    ///
+    /// This allows consuming large constrained `RcPrf`s one element at a time.
+    /// The remaining elements are still usable: the range of the constrained
+    /// `RcPrf` shrinks as its elements are popped.
+    ///
+    /// # Example
+    /// ```
+    /// # extern crate crypto_tk_rs;
+    /// use crypto_tk_rs::{RangePrf, RcPrf, RcPrfRange};
+    ///
+    /// let rcprf = RcPrf::new(4).unwrap();
+    /// let mut constrained = rcprf.constrain(&RcPrfRange::new(1, 4)).unwrap();
+    ///
+    /// let (range, leaves) = constrained.pop_front_element(16).unwrap();
+    /// assert_eq!(range, RcPrfRange::new(1, 1));
+    /// assert_eq!(leaves.len(), 1);
+    /// assert_eq!(constrained.range(), RcPrfRange::new(2, 4));
+    ///
+    /// while constrained.pop_front_element(16).is_some() {}
+    /// assert!(constrained.is_empty());
+    /// ```
+    pub fn pop_front_element(
+        &mut self,
+        out_size: usize,
+    ) -> Option<(RcPrfRange, iterator::RcPrfIterator)> {
+        let elt = self.elements.pop_front()?;
+        Some((
+            elt.range(),
+            iterator::RcPrfIterator {
+                node_queue: std::iter::once(elt).collect(),
+                output_size: out_size,
+            },
+        ))
+    }
+
+    /// Serialize the elements of the constrained `RcPrf` one by one: `f` is
+    /// called, in the order of the ranges, with the range of each element and
+    /// its serialization. This allows storage backends to store and retrieve
+    /// the elements separately. The serialized bytes contain key material, and
+    /// are zeroized once `f` returns.
+    ///
+    /// The constrained `RcPrf` can be rebuilt from the serialized elements
+    /// using `deserialize_elements`.
+    ///
+    /// As the serialized elements give access to the raw key material, this
+    /// function is only available with the `expose-key-bytes` feature.
+    #[cfg(feature = "expose-key-bytes")]
+    pub fn serialize_elements<F>(&self, f: F) -> std::io::Result<()>
+    where
+        F: FnMut(&RcPrfRange, &[u8]) -> std::io::Result<()>,
+    {
+        self.serialize_elements_with_byte_order(ByteOrder::default(), f)
+    }
+
+    /// Serialize the elements of the constrained `RcPrf` one by one, as
+    /// `serialize_elements` does, encoding their integers (including the
+    /// serialization tag of each element) with the `byte_order` byte order.
+    /// Note that the cleartext serialization of the whole constrained `RcPrf`
+    /// (e.g. when wrapping it) always uses the default little-endian byte
+    /// order.
+    ///
+    /// The constrained `RcPrf` can be rebuilt from the serialized elements
+    /// using `deserialize_elements_with_byte_order`, with the same byte order.
+    ///
+    /// # Example
+    /// ```
+    /// # extern crate crypto_tk_rs;
+    /// use crypto_tk_rs::{ByteOrder, ConstrainedRcPrf, RangePrf, RcPrf, RcPrfRange};
+    ///
+    /// let rcprf = RcPrf::new(4).unwrap();
+    /// let constrained = rcprf.constrain(&RcPrfRange::new(1, 6)).unwrap();
+    ///
+    /// let mut parts = vec![];
+    /// constrained
+    ///     .serialize_elements_with_byte_order(ByteOrder::BigEndian, |_, bytes| {
+    ///         parts.push(bytes.to_vec());
+    ///         Ok(())
+    ///     })
+    ///     .unwrap();
+    ///
+    /// let deserialized = ConstrainedRcPrf::deserialize_elements_with_byte_order(
+    ///     parts.iter().map(Vec::as_slice),
+    ///     ByteOrder::BigEndian,
+    /// )
+    /// .unwrap();
+    /// assert_eq!(deserialized.range(), constrained.range());
+    /// ```
+    ///
+    /// As `serialize_elements`, this function is only available with the
+    /// `expose-key-bytes` feature.
+    #[cfg(feature = "expose-key-bytes")]
+    pub fn serialize_elements_with_byte_order<F>(
+        &self,
+        byte_order: ByteOrder,
+        mut f: F,
+    ) -> std::io::Result<()>
+    where
+        F: FnMut(&RcPrfRange, &[u8]) -> std::io::Result<()>,
+    {
+        for elt in &self.elements {
+            f(&elt.range(), &serialize_element(&**elt, byte_order))?;
+        }
+        Ok(())
+    }
+
+    /// Consume the constrained `RcPrf` and returns, for each of its elements,
+    /// in the order of the ranges, the range of the element and its
+    /// serialization (including its key material).
+    ///
+    /// Every part can be turned back into a constrained `RcPrf` on its own
+    /// using `deserialize_elements`. The serialized keys are zeroized on drop.
+    ///
+    /// As the parts give access to the raw key material, this function is
+    /// only available with the `expose-key-bytes` feature.
+    #[cfg(feature = "expose-key-bytes")]
+    #[must_use]
+    pub fn into_parts(self) -> Vec<(RcPrfRange, Zeroizing<Vec<u8>>)> {
+        self.elements
+            .iter()
+            .map(|elt| {
+                (elt.range(), serialize_element(&**elt, ByteOrder::default()))
+            })
+            .collect()
+    }
+
+    /// Rebuild a constrained `RcPrf` from the serialized elements produced by
+    /// `serialize_elements`, given in the order of their ranges. Returns an
+    /// error if one of the elements cannot be deserialized, if there is no
+    /// element, or if the ranges of the elements are not consecutive.
+    pub fn deserialize_elements<'a, I>(
+        serialized_elements: I,
+    ) -> Result<Self, CleartextContentDeserializationError>
+    where
+        I: IntoIterator<Item = &'a [u8]>,
+    {
+        Self::deserialize_elements_with_byte_order(
+            serialized_elements,
+            ByteOrder::default(),
+        )
+    }
+
+    /// Rebuild a constrained `RcPrf` from the serialized elements produced by
+    /// `serialize_elements_with_byte_order` with the `byte_order` byte order,
+    /// as `deserialize_elements` does.
+    pub fn deserialize_elements_with_byte_order<'a, I>(
+        serialized_elements: I,
+        byte_order: ByteOrder,
+    ) -> Result<Self, CleartextContentDeserializationError>
+    where
+        I: IntoIterator<Item = &'a [u8]>,
+    {
+        let mut elements: VecDeque<Pin<Box<dyn private::RcPrfElement>>> =
+            VecDeque::new();
+
+        for (i, mut bytes) in serialized_elements.into_iter().enumerate() {
+            let elt = deserialize_element(&mut bytes, i as u64, byte_order)?;
+
+            if !bytes.is_empty() {
+                return Err(CleartextContentDeserializationError::ContentError(
+                    format!("Trailing bytes after the {i}-th element of the constrained RCPRF")));
+            }
+            if let Some(last) = elements.back() {
+                if last.range().bounds().1.checked_add(1)
+                    != Some(elt.range().bounds().0)
+                {
+                    return Err(CleartextContentDeserializationError::ContentError(
+                        format!("The range of the {i}-th element of the constrained RCPRF ({}) does not follow the previous one ({})", elt.range(), last.range())));
+                }
+            }
+            elements.push_back(elt);
+        }
+
+        if elements.is_empty() {
+            return Err(CleartextContentDeserializationError::ContentError(
+                "A constrained RCPRF must contain at least one element"
+                    .to_string(),
+            ));
+        }
+
+        Ok(ConstrainedRcPrf { elements })
+    }
+
     /// Transform the constrained `RcPrf` into an iterator that produces pairs
     /// of index and evaluation value for that index.
     /// Values produced by that iterator are vectors of size `out_size`.
     #[must_use]
     pub fn into_value_iter(self, out_size: usize) -> iterator::RcPrfIterator {
         iterator::RcPrfIterator {
-            node_queue: self.elements.into_iter().collect(),
+            node_queue: self.elements,
+            output_size: out_size,
+        }
+    }
+
+    /// Transform the constrained `RcPrf` into an iterator that produces pairs
+    /// of index and evaluation value for the indices `min`, `min+stride`,
+    /// `min+2*stride`, ... of the range. The skipped values are never
+    /// computed.
+    /// Values produced by that iterator are vectors of size `out_size`.
+    ///
+    /// # Panics
+    /// Panics if `stride` is 0.
+    ///
+    /// # Example
+    /// ```
+    /// # extern crate crypto_tk_rs;
+    /// use crypto_tk_rs::{RangePrf, RcPrf, RcPrfRange};
+    ///
+    /// let rcprf = RcPrf::new(4).unwrap();
+    /// let constrained = rcprf.constrain(&RcPrfRange::new(1, 7)).unwrap();
+    ///
+    /// let indices: Vec<u64> = constrained
+    ///     .into_strided_value_iter(16, 3)
+    ///     .map(|(i, _)| i)
+    ///     .collect();
+    /// assert_eq!(indices, vec![1, 4, 7]);
+    /// ```
+    #[must_use]
+    pub fn into_strided_value_iter(
+        self,
+        out_size: usize,
+        stride: u64,
+    ) -> iterator::RcPrfStridedIterator {
+        assert!(stride > 0, "The stride must not be zero");
+        let next_leaf = self.elements.front().map(|elt| elt.range().bounds().0);
+        iterator::RcPrfStridedIterator {
+            node_queue: self.elements,
             output_size: out_size,
+            stride,
+            next_leaf,
         }
     }
 
@@ -352,7 +1257,7 @@ impl ConstrainedRcPrf {
         out_size: usize,
     ) -> iterator::RcPrfParallelIterator {
         iterator::RcPrfParallelIterator::new(iterator::RcPrfIterator {
-            node_queue: self.elements.into_iter().collect(),
+            node_queue: self.elements,
             output_size: out_size,
         })
     }
@@ -393,6 +1298,9 @@ impl SerializableCleartextContent for ConstrainedRcPrf {
         &self,
         writer: &mut dyn std::io::Write,
     ) -> Result<usize, std::io::Error> {
+        // The cleartext serialization always uses the default byte order:
+        // `serialize_elements_with_byte_order` is the only big-endian option
+        // for constrained `RcPrf`s
         let elt_len_64 = self.elements.len() as u64;
         writer.write_all(&elt_len_64.to_le_bytes())?;
         let written_bytes: usize = self
@@ -412,47 +1320,134 @@ impl DeserializableCleartextContent for ConstrainedRcPrf {
         reader.read_exact(&mut elt_count_bytes)?;
         let elt_count = u64::from_le_bytes(elt_count_bytes);
 
-        let mut elements = vec![];
+        let mut elements = VecDeque::new();
 
         for i in 0..elt_count {
-            let tag =
-                SerializationTag::read_tag(reader).map_err(|err| CleartextContentDeserializationError::ContentError(
-                        format!("Issue when deserializing the {}-th element of the constrained RCPRF -- error while reading the tag:\n{}", i, err)))?;
-
-            let elt: Pin<Box<dyn private::RcPrfElement>> = match tag {
-                t if t == ConstrainedRcPrfLeafElement::serialization_tag() => {
-                    let elt = ConstrainedRcPrfLeafElement::deserialize_content(
-                        reader,
-                    )?;
-                    Box::pin(elt)
-                }
-                t if t == ConstrainedRcPrfInnerElement::serialization_tag() => {
-                    let elt =
-                        ConstrainedRcPrfInnerElement::deserialize_content(
-                            reader,
-                        )?;
-                    Box::pin(elt)
-                }
-                _ => {
-                    return Err(                    CleartextContentDeserializationError::ContentError(
-                        format!("Issue when deserializing the {}-th element of the constrained RCPRF: the tag of the element was neither ConstrainedRcPrfLeafElement, nor ConstrainedRcPrfInnerElement, but {:?}", i, tag)
-                            ,
-                    ))
-                }
-            };
-
-            elements.push(elt);
+            elements.push_back(deserialize_element(
+                reader,
+                i,
+                ByteOrder::default(),
+            )?);
         }
 
         Ok(ConstrainedRcPrf { elements })
     }
 }
 
+/// Serialize an element of a constrained `RcPrf`, along with its tag, into a
+/// zeroizing buffer. Both the tag and the content of the element are encoded
+/// with the `byte_order` byte order.
+#[cfg(feature = "expose-key-bytes")]
+fn serialize_element(
+    elt: &dyn private::RcPrfElement,
+    byte_order: ByteOrder,
+) -> Zeroizing<Vec<u8>> {
+    let mut buf = Zeroizing::new(Vec::with_capacity(
+        elt.cleartext_serialization_length(),
+    ));
+    // Writing to a vector never fails, and the buffer is allocated with the
+    // right capacity, so its content is never reallocated (and copied)
+    #[allow(clippy::unwrap_used)]
+    {
+        elt.serialization_tag()
+            .serialize_content_with_byte_order(&mut *buf, byte_order)
+            .unwrap();
+        buf.push(SERIALIZATION_FORMAT_VERSION);
+        elt.serialize_content_with_byte_order(&mut *buf, byte_order)
+            .unwrap();
+    }
+    buf
+}
+
+/// Split `elements`, sorted by range, between the ones covering leaves smaller
+/// than `leaf`, appended to `left`, and the ones covering leaves larger or
+/// equal to `leaf`, appended to `right`. The element containing both `leaf-1`
+/// and `leaf` (if any) is itself split as by `constrain`.
+fn split_elements<C>(
+    elements: impl IntoIterator<Item = Pin<Box<dyn private::RcPrfElement>>>,
+    leaf: u64,
+    left: &mut C,
+    right: &mut C,
+) where
+    C: Extend<Pin<Box<dyn private::RcPrfElement>>>,
+{
+    for elt in elements {
+        let (elt_min, elt_max) = elt.range().bounds();
+        if elt_max < leaf {
+            left.extend(std::iter::once(elt));
+        } else if elt_min >= leaf {
+            right.extend(std::iter::once(elt));
+        } else {
+            // this is not super efficient as we compute some node twice (the
+            // nodes of the path from the root to leaf). Yet, the asymptotic
+            // complexity is not affected.
+            // Also, we can call 'unchecked_constrain' instead of 'constrain'
+            // as, by construction, both `leaf-1` and `leaf` are in the range
+            // of the element.
+            left.extend(
+                elt.unchecked_constrain(&RcPrfRange::new(elt_min, leaf - 1))
+                    .elements,
+            );
+            right.extend(
+                elt.unchecked_constrain(&RcPrfRange::new(leaf, elt_max))
+                    .elements,
+            );
+        }
+    }
+}
+
+/// Deserialize the `i`-th element of a constrained `RcPrf`, along with its tag,
+/// both encoded with the `byte_order` byte order
+fn deserialize_element(
+    reader: &mut dyn std::io::Read,
+    i: u64,
+    byte_order: ByteOrder,
+) -> Result<
+    Pin<Box<dyn private::RcPrfElement>>,
+    CleartextContentDeserializationError,
+> {
+    let tag =
+        SerializationTag::read_tag_with_byte_order(reader, byte_order).map_err(|err| CleartextContentDeserializationError::ContentError(
+                format!("Issue when deserializing the {i}-th element of the constrained RCPRF -- error while reading the tag:\n{err}")))?;
+
+    read_format_version(reader).map_err(|err| CleartextContentDeserializationError::ContentError(
+                format!("Issue when deserializing the {i}-th element of the constrained RCPRF -- error while reading the format version:\n{err}")))?;
+
+    let elt: Pin<Box<dyn private::RcPrfElement>> = match tag {
+        t if t == ConstrainedRcPrfLeafElement::serialization_tag() => {
+            let elt = ConstrainedRcPrfLeafElement::deserialize_content_with_byte_order(
+                reader, byte_order,
+            )?;
+            Box::pin(elt)
+        }
+        t if t == ConstrainedRcPrfInnerElement::serialization_tag() => {
+            let elt =
+                ConstrainedRcPrfInnerElement::deserialize_content_with_byte_order(
+                    reader, byte_order,
+                )?;
+            Box::pin(elt)
+        }
+        _ => {
+            return Err(                    CleartextContentDeserializationError::ContentError(
+                format!("Issue when deserializing the {i}-th element of the constrained RCPRF: the tag of the element was neither ConstrainedRcPrfLeafElement, nor ConstrainedRcPrfInnerElement, but {tag:?}")
+                    ,
+            ))
+        }
+    };
+
+    Ok(elt)
+}
+
 #[cfg(test)]
 mod tests {
     #![allow(clippy::unwrap_used)]
     use super::*;
+    use crate::private::RcPrfElement;
+    use crate::{KeyAccessor, Prf};
+    use rand::prelude::*;
     use rayon::iter::ParallelIterator;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::{Arc, Mutex};
 
     #[test]
     fn child_choice() {
@@ -562,7 +1557,7 @@ mod tests {
                     .take(range_width)
                     .zip(constrained_eval.iter())
                     .zip(rcprf.value_range_iter(&range, 16).unwrap())
-                    .zip(par_eval_res.into_iter());
+                    .zip(par_eval_res);
                 triplets.for_each(|(((x, y), (_, z)), (_, t))| {
                     assert_eq!(x, y);
                     assert_eq!(&x[..], &z[..]);
@@ -583,33 +1578,1648 @@ mod tests {
     }
 
     #[test]
-    fn rcprf_errors() {
-        const OUT_VEC_SIZE: usize = 8;
-        assert!(RcPrf::new(MAX_HEIGHT + 1).is_err());
-
-        let h = 8u8;
+    fn rcprf_hash_range() {
+        let h = 6u8;
         let rcprf = RcPrf::new(h).unwrap();
-        let mut output = [0u8; 16];
-        assert!(rcprf.eval(max_leaf_index(h) + 1, &mut output).is_err());
+        let range = RcPrfRange::new(5, 27);
 
-        let mut outs = vec![[0u8; 16]; OUT_VEC_SIZE];
-        let mut slice: Vec<&mut [u8]> =
-            outs.iter_mut().map(|x| &mut x[..]).collect();
+        let hashes = rcprf.hash_range(&range, 16).unwrap();
 
-        // out of range error
-        assert!(rcprf
-            .eval_range(
-                &RcPrfRange::from(
-                    max_leaf_index(h)
-                        ..(max_leaf_index(h) + OUT_VEC_SIZE as u64)
-                ),
-                &mut slice
-            )
-            .is_err());
+        assert_eq!(hashes.len(), range.width() as usize);
+        for (x, hash) in (5..=27).zip(hashes) {
+            let mut out = [0u8; 16];
+            rcprf.eval(x, &mut out).unwrap();
+            assert_eq!(hash, Hash::new(&out));
+        }
 
-        // invalid vector size
         assert!(rcprf
-            .eval_range(&RcPrfRange::from(2..3), &mut slice)
+            .hash_range(&RcPrfRange::new(5, max_leaf_index(h) + 1), 16)
             .is_err());
     }
+
+    #[test]
+    fn constrained_rcprf_element_ranges() {
+        let h = 6u8;
+        let rcprf = RcPrf::new(h).unwrap();
+
+        for start in 0..=max_leaf_index(h) {
+            for end in start..=max_leaf_index(h) {
+                let range = RcPrfRange::new(start, end);
+                let constrained_rcprf = rcprf.constrain(&range).unwrap();
+                let element_ranges = constrained_rcprf.element_ranges();
+
+                assert!(!element_ranges.is_empty());
+                assert_eq!(element_ranges[0].0.bounds().0, start);
+                assert_eq!(
+                    element_ranges[element_ranges.len() - 1].0.bounds().1,
+                    end
+                );
+
+                for pair in element_ranges.windows(2) {
+                    assert_eq!(pair[0].0.bounds().1 + 1, pair[1].0.bounds().0);
+                }
+
+                let total_width: u64 =
+                    element_ranges.iter().map(|(r, _)| r.width()).sum();
+                assert_eq!(total_width, range.width());
+
+                for (r, height) in &element_ranges {
+                    assert!(*height <= h);
+                    assert!(rcprf.range().contains_range(r));
+                }
+            }
+        }
+    }
+
+    #[cfg(feature = "expose-key-bytes")]
+    #[test]
+    fn constrained_rcprf_element_serialization() {
+        let h = 6u8;
+        let rcprf = RcPrf::new(h).unwrap();
+        let range = RcPrfRange::new(3, 28);
+        let constrained_rcprf = rcprf.constrain(&range).unwrap();
+
+        let mut stored: Vec<(RcPrfRange, Vec<u8>)> = vec![];
+        constrained_rcprf
+            .serialize_elements(|r, bytes| {
+                stored.push((r.clone(), bytes.to_vec()));
+                Ok(())
+            })
+            .unwrap();
+
+        assert_eq!(
+            stored.iter().map(|(r, _)| r.clone()).collect::<Vec<_>>(),
+            constrained_rcprf
+                .element_ranges()
+                .into_iter()
+                .map(|(r, _)| r)
+                .collect::<Vec<_>>()
+        );
+
+        let rebuilt = ConstrainedRcPrf::deserialize_elements(
+            stored.iter().map(|(_, bytes)| &bytes[..]),
+        )
+        .unwrap();
+        assert_eq!(rebuilt.range(), range);
+
+        let (min, max) = range.bounds();
+        for x in min..=max {
+            let mut out = [0u8; 16];
+            let mut out_rebuilt = [0u8; 16];
+            constrained_rcprf.eval(x, &mut out).unwrap();
+            rebuilt.eval(x, &mut out_rebuilt).unwrap();
+            assert_eq!(out, out_rebuilt);
+        }
+
+        // missing, reordered, duplicated and truncated elements are rejected
+        assert!(ConstrainedRcPrf::deserialize_elements(
+            stored
+                .iter()
+                .skip(1)
+                .step_by(2)
+                .map(|(_, bytes)| &bytes[..])
+        )
+        .is_err());
+        assert!(ConstrainedRcPrf::deserialize_elements(
+            stored.iter().rev().map(|(_, bytes)| &bytes[..])
+        )
+        .is_err());
+        assert!(ConstrainedRcPrf::deserialize_elements(
+            stored
+                .iter()
+                .chain(stored.iter().take(1))
+                .map(|(_, bytes)| &bytes[..])
+        )
+        .is_err());
+        assert!(ConstrainedRcPrf::deserialize_elements(
+            stored.iter().map(|(_, bytes)| &bytes[..bytes.len() - 1])
+        )
+        .is_err());
+        assert!(
+            ConstrainedRcPrf::deserialize_elements(std::iter::empty()).is_err()
+        );
+    }
+
+    #[cfg(feature = "expose-key-bytes")]
+    #[test]
+    fn constrained_rcprf_into_parts() {
+        let h = 6u8;
+        let rcprf = RcPrf::new(h).unwrap();
+        let range = RcPrfRange::new(1, 30);
+        let constrained_rcprf = rcprf.constrain(&range).unwrap();
+        let element_ranges = constrained_rcprf.element_ranges();
+
+        let parts = constrained_rcprf.into_parts();
+        assert_eq!(parts.len(), element_ranges.len());
+
+        for ((part_range, bytes), (elt_range, _)) in
+            parts.iter().zip(element_ranges)
+        {
+            assert_eq!(*part_range, elt_range);
+
+            let part =
+                ConstrainedRcPrf::deserialize_elements([&bytes[..]]).unwrap();
+            assert_eq!(part.range(), *part_range);
+
+            for x in part_range.min()..=part_range.max() {
+                let mut out = [0u8; 16];
+                let mut out_part = [0u8; 16];
+                rcprf.eval(x, &mut out).unwrap();
+                part.eval(x, &mut out_part).unwrap();
+                assert_eq!(out, out_part);
+            }
+        }
+    }
+
+    #[test]
+    fn rcprf_leaf_count() {
+        for (h, count) in [(2u8, 2u64), (3, 4), (10, 512)] {
+            let rcprf = RcPrf::new(h).unwrap();
+            assert_eq!(rcprf.leaf_count(), count);
+
+            let constrained_rcprf =
+                rcprf.constrain(&RcPrfRange::new(0, 0)).unwrap();
+            assert_eq!(constrained_rcprf.leaf_count(), count);
+        }
+        assert_eq!(RcPrf::new(MAX_HEIGHT).unwrap().leaf_count(), u64::MAX);
+        // trees of height 1 are not supported
+        assert!(RcPrf::new(1).is_err());
+    }
+
+    #[test]
+    fn rcprf_zero_height() {
+        assert_eq!(max_leaf_index(0), 0);
+
+        assert!(matches!(
+            RcPrf::new(0),
+            Err(RcPrfError::InvalidTreeHeight(0, MIN_HEIGHT, MAX_HEIGHT))
+        ));
+        assert!(matches!(
+            RcPrf::from_key(Key256::new(), 0),
+            Err(RcPrfError::InvalidTreeHeight(0, MIN_HEIGHT, MAX_HEIGHT))
+        ));
+        assert!(RcPrf::from_master(&Key256::new(), b"label", 0).is_err());
+        assert!(
+            KeyDerivationRcPrf::<Key256>::from_key(Key256::new(), 0).is_err()
+        );
+        assert!(RcPrfBuilder::new(Key256::new(), 0).is_err());
+    }
+
+    #[test]
+    fn rcprf_small_heights() {
+        for h in [0u8, 1] {
+            match RcPrf::new(h) {
+                Err(RcPrfError::InvalidTreeHeight(height, min, max)) => {
+                    assert_eq!(height, h);
+                    assert_eq!(min, MIN_HEIGHT);
+                    assert_eq!(max, MAX_HEIGHT);
+                }
+                _ => panic!("Expected an invalid tree height error"),
+            }
+            assert!(KeyDerivationRcPrf::<Key256>::new(h).is_err());
+        }
+
+        // a tree of height 2 has two leaves
+        let h = 2u8;
+        let rcprf = RcPrf::new(h).unwrap();
+        assert_eq!(rcprf.range(), RcPrfRange::new(0, 1));
+
+        let mut out_0 = [0u8; 16];
+        let mut out_1 = [0u8; 16];
+        rcprf.eval(0, &mut out_0).unwrap();
+        rcprf.eval(1, &mut out_1).unwrap();
+        assert_ne!(out_0, out_1);
+        assert!(rcprf.eval(2, &mut [0u8; 16]).is_err());
+
+        let mut outs = [[0u8; 16]; 2];
+        let mut slice: Vec<&mut [u8]> =
+            outs.iter_mut().map(|x| &mut x[..]).collect();
+        rcprf
+            .eval_range(&RcPrfRange::new(0, 1), &mut slice)
+            .unwrap();
+        assert_eq!(outs, [out_0, out_1]);
+
+        let values: Vec<_> = rcprf
+            .value_range_iter(&RcPrfRange::new(0, 1), 16)
+            .unwrap()
+            .collect();
+        assert_eq!(values, vec![(0, out_0.to_vec()), (1, out_1.to_vec())]);
+
+        for (i, expected) in [out_0, out_1].iter().enumerate() {
+            let leaf = i as u64;
+            let constrained_rcprf =
+                rcprf.constrain(&RcPrfRange::new(leaf, leaf)).unwrap();
+            let mut out = [0u8; 16];
+            constrained_rcprf.eval(leaf, &mut out).unwrap();
+            assert_eq!(out, *expected);
+        }
+    }
+
+    #[test]
+    fn rcprf_serialized_size_bound() {
+        let mut rng = thread_rng();
+
+        for h in [2u8, 3, 8, 20, MAX_HEIGHT] {
+            let rcprf = RcPrf::new(h).unwrap();
+            let max_leaf = max_leaf_index(h);
+
+            for _ in 0..50 {
+                let a = rng.gen_range(0..=max_leaf);
+                let b = rng.gen_range(0..=max_leaf);
+                let range = RcPrfRange::new(a.min(b), a.max(b));
+
+                let constrained_rcprf = rcprf.constrain(&range).unwrap();
+                assert!(
+                    constrained_rcprf.cleartext_serialization_length()
+                        <= constrained_rcprf_serialized_size_bound(h, &range)
+                );
+            }
+
+            let constrained_rcprf = rcprf.constrain(&rcprf.range()).unwrap();
+            assert!(
+                constrained_rcprf.cleartext_serialization_length()
+                    <= constrained_rcprf_serialized_size_bound(
+                        h,
+                        &rcprf.range()
+                    )
+            );
+        }
+
+        // the size of an element is exact: a single inner element (covering
+        // two leaves) reaches the bound for a single element
+        let rcprf = RcPrf::new(8).unwrap();
+        let constrained_rcprf =
+            rcprf.constrain(&RcPrfRange::new(0, 1)).unwrap();
+        assert_eq!(
+            constrained_rcprf.cleartext_serialization_length(),
+            constrained_rcprf_serialized_size_bound(8, &RcPrfRange::new(0, 0))
+        );
+    }
+
+    #[test]
+    fn rcprf_cover_size() {
+        let h = 6u8;
+        let rcprf = RcPrf::new(h).unwrap();
+
+        for start in 0..=max_leaf_index(h) {
+            for end in start..=max_leaf_index(h) {
+                let range = RcPrfRange::new(start, end);
+                let constrained_rcprf = rcprf.constrain(&range).unwrap();
+
+                assert_eq!(
+                    cover_size(h, &range).unwrap(),
+                    constrained_rcprf.elements.len() as u64
+                );
+            }
+        }
+
+        assert!(
+            cover_size(h, &RcPrfRange::new(0, max_leaf_index(h) + 1)).is_err()
+        );
+        assert_eq!(
+            cover_size(MAX_HEIGHT, &RcPrfRange::new(1, u64::MAX - 1)).unwrap(),
+            2 * 63
+        );
+    }
+
+    #[test]
+    fn rcprf_constrain_bounded() {
+        let h = 6u8;
+        let rcprf = RcPrf::new(h).unwrap();
+
+        // [1, 30] is covered by 8 elements: 1, [2,3], [4,7], [8,15],
+        // [16,23], [24,27], [28,29] and 30
+        let range = RcPrfRange::new(1, 30);
+        assert_eq!(cover_size(h, &range).unwrap(), 8);
+
+        let constrained_rcprf = rcprf.constrain_bounded(&range, 8).unwrap();
+        assert_eq!(constrained_rcprf.range(), range);
+
+        match rcprf.constrain_bounded(&range, 7) {
+            Err(RcPrfError::CoverTooLarge(r, size, max)) => {
+                assert_eq!(r, range);
+                assert_eq!(size, 8);
+                assert_eq!(max, 7);
+            }
+            _ => panic!("Expected a cover size error"),
+        }
+
+        assert!(rcprf
+            .constrain_bounded(&RcPrfRange::new(0, max_leaf_index(h)), 1)
+            .is_ok());
+        assert!(rcprf
+            .constrain_bounded(&RcPrfRange::new(0, max_leaf_index(h) + 1), 100)
+            .is_err());
+    }
+
+    #[test]
+    fn rcprf_covering_nodes() {
+        let mut rng = thread_rng();
+
+        for h in [MIN_HEIGHT, 3, 7, 20, MAX_HEIGHT] {
+            let rcprf = RcPrf::new(h).unwrap();
+            let max = max_leaf_index(h);
+
+            for _ in 0..50 {
+                let a = rng.gen_range(0..=max);
+                let b = rng.gen_range(0..=max);
+                let range = RcPrfRange::new(a.min(b), a.max(b));
+                let nodes = rcprf.covering_nodes(&range).unwrap();
+
+                assert!(!nodes.is_empty());
+                assert!(nodes.len() <= 2 * h as usize);
+                assert_eq!(nodes.len() as u64, cover_size(h, &range).unwrap());
+
+                // the nodes are complete subtrees, disjoint, sorted and tile
+                // the range
+                assert_eq!(nodes[0].bounds().0, range.bounds().0);
+                assert_eq!(nodes[nodes.len() - 1].bounds().1, range.bounds().1);
+                for node in &nodes {
+                    assert!(node.as_prefix().is_some());
+                }
+                for pair in nodes.windows(2) {
+                    assert!(!pair[0].intersects(&pair[1]));
+                    assert_eq!(pair[0].bounds().1 + 1, pair[1].bounds().0);
+                }
+
+                // and match the elements of the constrained RcPrf
+                let constrained = rcprf.constrain(&range).unwrap();
+                let element_ranges: Vec<RcPrfRange> = constrained
+                    .element_ranges()
+                    .into_iter()
+                    .map(|(r, _)| r)
+                    .collect();
+                assert_eq!(element_ranges, nodes);
+            }
+        }
+
+        let rcprf = RcPrf::new(4).unwrap();
+        assert_eq!(
+            rcprf.covering_nodes(&RcPrfRange::new(0, 7)).unwrap(),
+            vec![RcPrfRange::new(0, 7)]
+        );
+        assert_eq!(
+            rcprf.covering_nodes(&RcPrfRange::new(1, 6)).unwrap(),
+            vec![
+                RcPrfRange::new(1, 1),
+                RcPrfRange::new(2, 3),
+                RcPrfRange::new(4, 5),
+                RcPrfRange::new(6, 6)
+            ]
+        );
+        assert!(matches!(
+            rcprf.covering_nodes(&RcPrfRange::new(4, 8)),
+            Err(RcPrfError::InvalidConstrainRange(..))
+        ));
+    }
+
+    #[test]
+    fn rcprf_subtree_key() {
+        let h = 8u8;
+        let rcprf = RcPrf::new(h).unwrap();
+
+        for suffix_bits in 1..h {
+            let prefix_count = 1u64 << (h - 1 - suffix_bits);
+            for prefix in [0, prefix_count / 2, prefix_count - 1] {
+                let range = RcPrfRange::from_prefix(prefix, suffix_bits);
+                let subtree = RcPrf::from_key(
+                    rcprf.subtree_key(&range).unwrap(),
+                    suffix_bits + 1,
+                )
+                .unwrap();
+
+                let mut out = [0u8; 16];
+                let mut expected = [0u8; 16];
+                for offset in 0..=max_leaf_index(suffix_bits + 1) {
+                    subtree.eval(offset, &mut out).unwrap();
+                    rcprf
+                        .eval(range.bounds().0 + offset, &mut expected)
+                        .unwrap();
+                    assert_eq!(out, expected);
+                }
+            }
+        }
+
+        // single leaves
+        let mut out = [0u8; 16];
+        let mut expected = [0u8; 16];
+        for leaf in [0, 17, max_leaf_index(h)] {
+            let key = rcprf.subtree_key(&RcPrfRange::new(leaf, leaf)).unwrap();
+            RcPrfBuilder::eval_leaf_key(&key, &mut out);
+            rcprf.eval(leaf, &mut expected).unwrap();
+            assert_eq!(out, expected);
+        }
+
+        assert!(matches!(
+            rcprf.subtree_key(&RcPrfRange::new(2, 5)),
+            Err(RcPrfError::InvalidSubtreeRange(..))
+        ));
+        assert!(matches!(
+            rcprf.subtree_key(&RcPrfRange::new(1, 2)),
+            Err(RcPrfError::InvalidSubtreeRange(..))
+        ));
+        assert!(matches!(
+            rcprf.subtree_key(&RcPrfRange::new(0, 255)),
+            Err(RcPrfError::InvalidConstrainRange(..))
+        ));
+
+        // full range, including for the largest trees
+        for h in [h, MAX_HEIGHT] {
+            let root = Key256::new();
+            let rcprf = RcPrf::from_key(root.insecure_clone(), h).unwrap();
+            let key = rcprf.subtree_key(&rcprf.range()).unwrap();
+            assert_eq!(key.content(), root.content());
+        }
+    }
+
+    #[test]
+    fn rcprf_node_key() {
+        let h = 8u8;
+        let rcprf = RcPrf::new(h).unwrap();
+
+        let mut out = [0u8; 16];
+        let mut expected = [0u8; 16];
+        for leaf in [0, 1, 17, max_leaf_index(h)] {
+            let key = rcprf.node_key(h - 1, leaf).unwrap();
+            RcPrfBuilder::eval_leaf_key(&key, &mut out);
+            rcprf.eval(leaf, &mut expected).unwrap();
+            assert_eq!(out, expected);
+        }
+
+        for depth in 0..h {
+            let path = (1u64 << depth) - 1;
+            let range = RcPrfRange::from_prefix(path, h - 1 - depth);
+            assert_eq!(
+                rcprf.node_key(depth, path).unwrap().content(),
+                rcprf.subtree_key(&range).unwrap().content()
+            );
+        }
+
+        assert!(matches!(
+            rcprf.node_key(h, 0),
+            Err(RcPrfError::InvalidNodeDepth(..))
+        ));
+        assert!(matches!(
+            rcprf.node_key(2, 4),
+            Err(RcPrfError::InvalidEvalPoint(..))
+        ));
+    }
+
+    #[test]
+    fn rcprf_eval_bits() {
+        let mut rng = thread_rng();
+
+        for h in [MIN_HEIGHT, 5, 20, MAX_HEIGHT - 1] {
+            let rcprf = RcPrf::new(h).unwrap();
+            for _ in 0..20 {
+                let x = rng.gen_range(0..=max_leaf_index(h));
+                let bits: Vec<bool> =
+                    (0..h - 1).rev().map(|i| (x >> i) & 1 == 1).collect();
+
+                let mut out = [0u8; 16];
+                let mut expected = [0u8; 16];
+                rcprf.eval_bits(&bits, &mut out).unwrap();
+                rcprf.eval(x, &mut expected).unwrap();
+                assert_eq!(out, expected);
+            }
+
+            assert!(matches!(
+                rcprf.eval_bits(&vec![false; h as usize], &mut [0u8; 16]),
+                Err(RcPrfError::InvalidBitPathLength(..))
+            ));
+            assert!(matches!(
+                rcprf.eval_bits(&vec![true; h as usize - 2], &mut [0u8; 16]),
+                Err(RcPrfError::InvalidBitPathLength(..))
+            ));
+            assert!(matches!(
+                rcprf.eval_bits(&vec![true; h as usize - 1], &mut []),
+                Err(RcPrfError::EmptyOutput)
+            ));
+        }
+    }
+
+    #[test]
+    fn big_endian_element_serialization() {
+        let h = 6u8;
+        let rcprf = RcPrf::new(h).unwrap();
+
+        for byte_order in [ByteOrder::LittleEndian, ByteOrder::BigEndian] {
+            let mut bytes = vec![];
+            rcprf
+                .root
+                .serialize_content_with_byte_order(&mut bytes, byte_order)
+                .unwrap();
+            let inner_elt =
+                ConstrainedRcPrfInnerElement::deserialize_content_with_byte_order(
+                    &mut &bytes[..],
+                    byte_order,
+                )
+                .unwrap();
+            assert_eq!(inner_elt.range(), rcprf.range());
+
+            let leaf_elt = ConstrainedRcPrfLeafElement {
+                prf: Prf::new(),
+                index: 3,
+                rcprf_height: h,
+            };
+            let mut bytes = vec![];
+            leaf_elt
+                .serialize_content_with_byte_order(&mut bytes, byte_order)
+                .unwrap();
+            let deser_leaf =
+                ConstrainedRcPrfLeafElement::deserialize_content_with_byte_order(
+                    &mut &bytes[..],
+                    byte_order,
+                )
+                .unwrap();
+            assert_eq!(deser_leaf.range(), leaf_elt.range());
+
+            let mut out = [0u8; 16];
+            let mut expected = [0u8; 16];
+            for x in [0, 9, 31] {
+                rcprf.eval(x, &mut expected).unwrap();
+                inner_elt.eval(x, &mut out).unwrap();
+                assert_eq!(out, expected);
+            }
+            leaf_elt.eval(3, &mut expected).unwrap();
+            deser_leaf.eval(3, &mut out).unwrap();
+            assert_eq!(out, expected);
+        }
+    }
+
+    #[cfg(feature = "expose-key-bytes")]
+    #[test]
+    fn big_endian_constrained_rcprf_element_serialization() {
+        let rcprf = RcPrf::new(8).unwrap();
+        let constrained = rcprf.constrain(&RcPrfRange::new(3, 100)).unwrap();
+
+        let serialize = |byte_order| {
+            let mut parts = vec![];
+            constrained
+                .serialize_elements_with_byte_order(byte_order, |_, bytes| {
+                    parts.push(bytes.to_vec());
+                    Ok(())
+                })
+                .unwrap();
+            parts
+        };
+        let be_parts = serialize(ByteOrder::BigEndian);
+        let le_parts = serialize(ByteOrder::LittleEndian);
+
+        // the default byte order is little-endian
+        let mut default_parts = vec![];
+        constrained
+            .serialize_elements(|_, bytes| {
+                default_parts.push(bytes.to_vec());
+                Ok(())
+            })
+            .unwrap();
+        assert_eq!(default_parts, le_parts);
+        assert_ne!(be_parts, le_parts);
+
+        // the element tags are also encoded in big-endian
+        for (be, le) in be_parts.iter().zip(le_parts.iter()) {
+            assert_eq!([be[0], be[1]], [le[1], le[0]]);
+        }
+
+        let deserialized =
+            ConstrainedRcPrf::deserialize_elements_with_byte_order(
+                be_parts.iter().map(Vec::as_slice),
+                ByteOrder::BigEndian,
+            )
+            .unwrap();
+        assert_eq!(deserialized.range(), constrained.range());
+        let mut out = [0u8; 16];
+        let mut expected = [0u8; 16];
+        for x in 3..=100 {
+            rcprf.eval(x, &mut expected).unwrap();
+            deserialized.eval(x, &mut out).unwrap();
+            assert_eq!(out, expected);
+        }
+    }
+
+    #[test]
+    fn rcprf_zeroizing_iterator() {
+        let rcprf = RcPrf::new(6).unwrap();
+        let range = RcPrfRange::new(5, 27);
+
+        let values: Vec<(u64, Vec<u8>)> =
+            rcprf.value_range_iter(&range, 32).unwrap().collect();
+        let zeroizing_iter =
+            rcprf.value_range_iter(&range, 32).unwrap().into_zeroizing();
+        assert_eq!(zeroizing_iter.len(), values.len());
+
+        let zeroizing_values: Vec<(u64, Zeroizing<Vec<u8>>)> =
+            zeroizing_iter.collect();
+        assert_eq!(zeroizing_values.len(), values.len());
+        for ((x, v), (zx, zv)) in values.iter().zip(zeroizing_values.iter()) {
+            assert_eq!(x, zx);
+            assert_eq!(v, &**zv);
+        }
+
+        // the adapter also works backwards, and on constrained RcPrfs
+        let constrained = rcprf.constrain(&range).unwrap();
+        let (x, value) = constrained
+            .into_value_iter(32)
+            .into_zeroizing()
+            .next_back()
+            .unwrap();
+        assert_eq!(x, range.bounds().1);
+        assert_eq!(&*value, &values[values.len() - 1].1);
+    }
+
+    #[test]
+    fn rcprf_iterator_remaining() {
+        let rcprf = RcPrf::new(6).unwrap();
+        let range = RcPrfRange::new(5, 27);
+
+        let mut iter = rcprf.value_range_iter(&range, 16).unwrap();
+        let mut expected = range.width();
+        assert_eq!(iter.remaining(), expected);
+        while expected > 0 {
+            // alternate between both ends to partially split the nodes
+            if expected.is_multiple_of(3) {
+                iter.next_back().unwrap();
+            } else {
+                iter.next().unwrap();
+            }
+            expected -= 1;
+            assert_eq!(iter.remaining(), expected);
+            assert_eq!(iter.len() as u64, expected);
+        }
+        assert!(iter.next().is_none());
+        assert_eq!(iter.remaining(), 0);
+
+        // the 2^64 leaves of a tree of maximum height saturate the counts
+        let rcprf = RcPrf::new(MAX_HEIGHT).unwrap();
+        let mut iter = rcprf.value_range_iter(&rcprf.range(), 16).unwrap();
+        assert_eq!(iter.remaining(), u64::MAX);
+        assert_eq!(iter.size_hint(), (usize::MAX, None));
+        assert_eq!(iter.len(), usize::MAX);
+        assert_eq!(iter.next_back().map(|(x, _)| x), Some(u64::MAX));
+        assert_eq!(iter.remaining(), u64::MAX);
+        assert_eq!(iter.size_hint(), (usize::MAX, Some(usize::MAX)));
+    }
+
+    #[test]
+    fn rcprf_constrain_points() {
+        let h = 8u8;
+        let rcprf = RcPrf::new(h).unwrap();
+
+        let points: Vec<u64> = vec![3, 4, 5, 6, 7, 40, 8, 100, 101, 127, 5];
+        let constrained = rcprf.constrain_points(&points).unwrap();
+
+        // one constrained RcPrf per run of consecutive points
+        assert_eq!(
+            constrained.iter().map(RangePrf::range).collect::<Vec<_>>(),
+            vec![
+                RcPrfRange::new(3, 8),
+                RcPrfRange::new(40, 40),
+                RcPrfRange::new(100, 101),
+                RcPrfRange::new(127, 127)
+            ]
+        );
+        // made of [3], [4,7], [8], [40], [100,101] and [127], which is the
+        // cover of their ranges
+        assert_eq!(
+            constrained.iter().map(|c| c.elements.len()).sum::<usize>(),
+            6
+        );
+        for c in &constrained {
+            assert_eq!(
+                c.element_ranges()
+                    .into_iter()
+                    .map(|(r, _)| r)
+                    .collect::<Vec<_>>(),
+                rcprf.covering_nodes(&c.range()).unwrap()
+            );
+        }
+
+        let mut out = [0u8; 16];
+        let mut expected = [0u8; 16];
+        for x in 0..=max_leaf_index(h) {
+            let containing: Vec<&ConstrainedRcPrf> = constrained
+                .iter()
+                .filter(|c| c.range().contains_leaf(x))
+                .collect();
+            if points.contains(&x) {
+                assert_eq!(containing.len(), 1);
+                containing[0].eval(x, &mut out).unwrap();
+                rcprf.eval(x, &mut expected).unwrap();
+                assert_eq!(out, expected);
+            } else {
+                assert!(containing.is_empty());
+            }
+        }
+
+        // a contiguous set of points gives the same constrained RcPrf as the
+        // range
+        let constrained = rcprf.constrain_points(&[9, 10, 11, 12]).unwrap();
+        assert_eq!(constrained.len(), 1);
+        assert_eq!(constrained[0].range(), RcPrfRange::new(9, 12));
+        assert_eq!(
+            constrained[0]
+                .element_ranges()
+                .into_iter()
+                .map(|(r, _)| r)
+                .collect::<Vec<_>>(),
+            rcprf.covering_nodes(&RcPrfRange::new(9, 12)).unwrap()
+        );
+
+        assert!(matches!(
+            rcprf.constrain_points(&[]),
+            Err(RcPrfError::EmptyPointSet)
+        ));
+        assert!(matches!(
+            rcprf.constrain_points(&[1, max_leaf_index(h) + 1]),
+            Err(RcPrfError::InvalidConstrainRange(..))
+        ));
+    }
+
+    #[test]
+    fn rcprf_constrain_random_points() {
+        let h = 8u8;
+        let rcprf = RcPrf::new(h).unwrap();
+        let mut rng = thread_rng();
+        let mut expected = [0u8; 16];
+
+        for _ in 0..20 {
+            let points: Vec<u64> = (0..rng.gen_range(1..30))
+                .map(|_| rng.gen_range(0..=max_leaf_index(h)))
+                .collect();
+            let constrained = rcprf.constrain_points(&points).unwrap();
+
+            // the constrained RcPrfs are sorted, separated by gaps, and cover
+            // exactly the points
+            for pair in constrained.windows(2) {
+                let (_, left_max) = pair[0].range().bounds();
+                let (right_min, _) = pair[1].range().bounds();
+                assert!(left_max + 1 < right_min);
+            }
+            let mut sorted_points = points.clone();
+            sorted_points.sort_unstable();
+            sorted_points.dedup();
+            let covered: Vec<u64> = constrained
+                .iter()
+                .flat_map(|c| {
+                    let (min, max) = c.range().bounds();
+                    min..=max
+                })
+                .collect();
+            assert_eq!(covered, sorted_points);
+
+            for c in constrained {
+                for (x, v) in c.into_value_iter(16) {
+                    rcprf.eval(x, &mut expected).unwrap();
+                    assert_eq!(v, expected);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn rcprf_strided_iterator() {
+        let h = 6u8;
+        let rcprf = RcPrf::new(h).unwrap();
+
+        for range in [
+            RcPrfRange::new(0, max_leaf_index(h)),
+            RcPrfRange::new(5, 28),
+            RcPrfRange::new(17, 18),
+            RcPrfRange::new(31, 31),
+        ] {
+            let constrained = rcprf.constrain(&range).unwrap();
+            let iter = constrained.into_strided_value_iter(16, 3);
+
+            let (min, max) = range.bounds();
+            let expected_indices: Vec<u64> = (min..=max).step_by(3).collect();
+            let n = expected_indices.len();
+            assert_eq!(iter.size_hint(), (n, Some(n)));
+
+            let (indices, values): (Vec<u64>, Vec<Vec<u8>>) = iter.unzip();
+            assert_eq!(indices, expected_indices);
+
+            let mut expected = [0u8; 16];
+            for (i, v) in indices.iter().zip(values.iter()) {
+                rcprf.eval(*i, &mut expected).unwrap();
+                assert_eq!(&v[..], &expected[..]);
+            }
+        }
+    }
+
+    #[test]
+    fn rcprf_strided_iterator_max_height() {
+        let rcprf = RcPrf::new(MAX_HEIGHT).unwrap();
+
+        // 2^64 leaves do not fit in a usize
+        let constrained = rcprf.constrain(&rcprf.range()).unwrap();
+        let mut iter = constrained.into_strided_value_iter(16, 1);
+        assert_eq!(iter.size_hint(), (usize::MAX, None));
+        assert_eq!(iter.next().map(|(x, _)| x), Some(0));
+        assert_eq!(iter.size_hint(), (usize::MAX, Some(usize::MAX)));
+
+        let constrained = rcprf.constrain(&rcprf.range()).unwrap();
+        let iter = constrained.into_strided_value_iter(16, 1 << 62);
+        assert_eq!(iter.size_hint(), (4, Some(4)));
+        let (indices, values): (Vec<u64>, Vec<Vec<u8>>) = iter.unzip();
+        assert_eq!(indices, vec![0, 1 << 62, 2 << 62, 3 << 62]);
+
+        let mut expected = [0u8; 16];
+        for (i, v) in indices.iter().zip(values.iter()) {
+            rcprf.eval(*i, &mut expected).unwrap();
+            assert_eq!(&v[..], &expected[..]);
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "The stride must not be zero")]
+    fn rcprf_strided_iterator_zero_stride() {
+        let rcprf = RcPrf::new(6).unwrap();
+        let constrained = rcprf.constrain(&RcPrfRange::new(0, 10)).unwrap();
+        let _ = constrained.into_strided_value_iter(16, 0);
+    }
+
+    #[test]
+    fn constrained_rcprf_split() {
+        let h = 6u8;
+        let rcprf = RcPrf::new(h).unwrap();
+        let mut rng = thread_rng();
+
+        for range in [
+            RcPrfRange::new(0, max_leaf_index(h)),
+            RcPrfRange::new(3, 27),
+            RcPrfRange::new(8, 15),
+            RcPrfRange::new(30, 31),
+        ] {
+            let (min, max) = range.bounds();
+            for index in min..max {
+                let constrained = rcprf.constrain(&range).unwrap();
+                let (left, right) = constrained.split_at(index).unwrap();
+                assert_eq!(left.range(), RcPrfRange::new(min, index));
+                assert_eq!(right.range(), RcPrfRange::new(index + 1, max));
+
+                let mut out = [0u8; 16];
+                let mut expected = [0u8; 16];
+                for x in min..=max {
+                    rcprf.eval(x, &mut expected).unwrap();
+                    if x <= index {
+                        left.eval(x, &mut out).unwrap();
+                        assert!(right.eval(x, &mut out).is_err());
+                    } else {
+                        right.eval(x, &mut out).unwrap();
+                        assert!(left.eval(x, &mut out).is_err());
+                    }
+                    assert_eq!(out, expected);
+                }
+
+                // splitting and concatenating gives back the same range
+                let merged = left.concat(right).unwrap();
+                assert_eq!(merged.range(), range);
+            }
+
+            // invalid split indices
+            for index in [max, max + 1, rng.gen_range(32..100)] {
+                let constrained = rcprf.constrain(&range).unwrap();
+                assert!(matches!(
+                    constrained.split_at(index),
+                    Err(RcPrfError::InvalidSplitIndex(..))
+                ));
+            }
+            if min > 0 {
+                let constrained = rcprf.constrain(&range).unwrap();
+                assert!(matches!(
+                    constrained.split_at(min - 1),
+                    Err(RcPrfError::InvalidSplitIndex(..))
+                ));
+            }
+        }
+    }
+
+    #[test]
+    fn rcprf_std_ranges() {
+        let rcprf = RcPrf::new(6).unwrap();
+
+        let mut expected = [[0u8; 16]; 4];
+        let mut expected_slices: Vec<&mut [u8]> =
+            expected.iter_mut().map(|o| &mut o[..]).collect();
+        rcprf
+            .eval_range(&RcPrfRange::new(2, 5), &mut expected_slices)
+            .unwrap();
+
+        let mut out = [[0u8; 16]; 4];
+        {
+            let mut outputs: Vec<&mut [u8]> =
+                out[..3].iter_mut().map(|o| &mut o[..]).collect();
+            rcprf.eval_std_range(2..5, &mut outputs).unwrap();
+        }
+        assert_eq!(out[..3], expected[..3]);
+
+        let mut outputs: Vec<&mut [u8]> =
+            out.iter_mut().map(|o| &mut o[..]).collect();
+        rcprf.eval_std_range(2..=5, &mut outputs).unwrap();
+        assert_eq!(out, expected);
+
+        assert_eq!(
+            rcprf.constrain_std_range(2..5).unwrap().range(),
+            RcPrfRange::new(2, 4)
+        );
+        let constrained = rcprf.constrain_std_range(2..=5).unwrap();
+        assert_eq!(constrained.range(), RcPrfRange::new(2, 5));
+        let mut outputs: Vec<&mut [u8]> =
+            out.iter_mut().map(|o| &mut o[..]).collect();
+        constrained.eval_std_range(2..=5, &mut outputs).unwrap();
+        assert_eq!(out, expected);
+
+        let mut outputs: Vec<&mut [u8]> =
+            out.iter_mut().map(|o| &mut o[..]).collect();
+        assert!(matches!(
+            rcprf.eval_std_range(2.., &mut outputs),
+            Err(RcPrfError::UnboundedRange(..))
+        ));
+        assert!(matches!(
+            rcprf.eval_std_range(..4, &mut outputs),
+            Err(RcPrfError::UnboundedRange(..))
+        ));
+        assert!(matches!(
+            rcprf.constrain_std_range(..),
+            Err(RcPrfError::UnboundedRange(..))
+        ));
+        assert!(matches!(
+            rcprf.constrain_std_range(4..4),
+            Err(RcPrfError::EmptyRange(..))
+        ));
+    }
+
+    #[test]
+    fn rcprf_eval_range_flat() {
+        let rcprf = RcPrf::new(7).unwrap();
+        let constrained = rcprf.constrain(&RcPrfRange::new(5, 40)).unwrap();
+
+        for (prf, range) in [
+            (&rcprf as &dyn RangePrf, RcPrfRange::new(0, 63)),
+            (&rcprf, RcPrfRange::new(3, 17)),
+            (&constrained, RcPrfRange::new(5, 40)),
+            (&constrained, RcPrfRange::new(20, 20)),
+        ] {
+            for out_width in [1usize, 16, 33, 64, 100] {
+                let n = range.width() as usize;
+
+                let mut flat = vec![0u8; n * out_width];
+                prf.eval_range_flat(&range, out_width, &mut flat).unwrap();
+
+                let mut bufs = vec![vec![0u8; out_width]; n];
+                let mut slices: Vec<&mut [u8]> =
+                    bufs.iter_mut().map(|b| &mut b[..]).collect();
+                prf.eval_range(&range, &mut slices).unwrap();
+
+                assert_eq!(flat, bufs.concat());
+            }
+        }
+
+        let range = RcPrfRange::new(3, 6);
+        for len in [0, 63, 65, 128] {
+            assert!(matches!(
+                rcprf.eval_range_flat(&range, 16, &mut vec![0u8; len]),
+                Err(RcPrfError::InvalidFlatOutputLength(..))
+            ));
+        }
+        assert!(matches!(
+            rcprf.eval_range_flat(&range, 0, &mut []),
+            Err(RcPrfError::EmptyOutput)
+        ));
+        assert!(matches!(
+            constrained.eval_range_flat(
+                &RcPrfRange::new(0, 3),
+                16,
+                &mut [0u8; 64]
+            ),
+            Err(RcPrfError::InvalidEvalRange(..))
+        ));
+    }
+
+    #[test]
+    fn rcprf_consistency_check() {
+        let h = 8u8;
+        let rcprf = RcPrf::new(h).unwrap();
+        let other_rcprf = RcPrf::new(h).unwrap();
+        let range = RcPrfRange::new(10, 100);
+        let samples = [0, 10, 42, 77, 100, 200];
+
+        let constrained = rcprf.constrain(&range).unwrap();
+        assert!(rcprf.is_consistent_with(&constrained, &samples));
+        assert!(rcprf.is_consistent_with(&constrained, &[50]));
+
+        let other_constrained = other_rcprf.constrain(&range).unwrap();
+        assert!(!rcprf.is_consistent_with(&other_constrained, &samples));
+        assert!(!rcprf.is_consistent_with(&other_constrained, &[42]));
+
+        // a constraint of a sub-tree of the same root is not a constraint of
+        // the RcPrf: it is already rejected as its tree height differs
+        let subtree = RcPrf::from_key(
+            rcprf.subtree_key(&RcPrfRange::new(0, 63)).unwrap(),
+            7,
+        )
+        .unwrap();
+        let subtree_constrained =
+            subtree.constrain(&RcPrfRange::new(10, 20)).unwrap();
+        assert!(!rcprf.is_consistent_with(&subtree_constrained, &[15]));
+
+        // with the same tree height, its root is unrelated to the RcPrf's, and
+        // so are its values
+        let same_height_subtree = RcPrf::from_key(
+            rcprf.subtree_key(&RcPrfRange::new(0, 63)).unwrap(),
+            h,
+        )
+        .unwrap();
+        let same_height_constrained =
+            same_height_subtree.constrain(&range).unwrap();
+        assert_eq!(same_height_constrained.tree_height(), h);
+        assert!(!rcprf.is_consistent_with(&same_height_constrained, &samples));
+        assert!(!rcprf.is_consistent_with(&same_height_constrained, &[15]));
+
+        // nothing to check
+        assert!(!rcprf.is_consistent_with(&constrained, &[]));
+        assert!(!rcprf.is_consistent_with(&constrained, &[0, 9, 101]));
+    }
+
+    #[test]
+    fn rcprf_concat() {
+        let h = 6u8;
+        let rcprf = RcPrf::new(h).unwrap();
+        let full_range = RcPrfRange::new(0, max_leaf_index(h));
+
+        for (left_range, right_range) in [
+            (RcPrfRange::new(0, 15), RcPrfRange::new(16, 31)),
+            (RcPrfRange::new(3, 10), RcPrfRange::new(11, 11)),
+            (RcPrfRange::new(0, 0), RcPrfRange::new(1, 30)),
+        ] {
+            let (min, _) = left_range.bounds();
+            let (_, max) = right_range.bounds();
+            let joined_range = RcPrfRange::new(min, max);
+
+            // the order of the operands does not matter
+            for swap in [false, true] {
+                let left = rcprf.constrain(&left_range).unwrap();
+                let right = rcprf.constrain(&right_range).unwrap();
+                let merged = if swap {
+                    right.concat(left).unwrap()
+                } else {
+                    left.concat(right).unwrap()
+                };
+                assert_eq!(merged.range(), joined_range);
+
+                for x in min..=max {
+                    let mut out = [0u8; 16];
+                    let mut out_merged = [0u8; 16];
+                    rcprf.eval(x, &mut out).unwrap();
+                    merged.eval(x, &mut out_merged).unwrap();
+                    assert_eq!(out, out_merged);
+                }
+                assert!(full_range.contains_range(&merged.range()));
+            }
+        }
+
+        // non consecutive ranges
+        for (left_range, right_range) in [
+            (RcPrfRange::new(0, 3), RcPrfRange::new(5, 7)),
+            (RcPrfRange::new(0, 4), RcPrfRange::new(4, 7)),
+            (RcPrfRange::new(0, 7), RcPrfRange::new(2, 3)),
+        ] {
+            let left = rcprf.constrain(&left_range).unwrap();
+            let right = rcprf.constrain(&right_range).unwrap();
+            assert!(matches!(
+                left.concat(right),
+                Err(RcPrfError::NonConsecutiveMergeRanges(..))
+            ));
+        }
+
+        // different tree heights
+        let other_rcprf = RcPrf::new(h + 1).unwrap();
+        let left = rcprf.constrain(&RcPrfRange::new(0, 3)).unwrap();
+        let right = other_rcprf.constrain(&RcPrfRange::new(4, 7)).unwrap();
+        assert!(matches!(
+            left.concat(right),
+            Err(RcPrfError::TreeHeightMismatch(6, 7))
+        ));
+    }
+
+    #[test]
+    fn rcprf_constrain_delta() {
+        let h = 6u8;
+        let rcprf = RcPrf::new(h).unwrap();
+
+        let check_delta = |old: RcPrfRange, new: RcPrfRange| {
+            let mut token = rcprf.constrain(&old).unwrap();
+            let delta = rcprf.constrain_delta(&old, &new).unwrap();
+
+            assert!(!delta.range().intersects(&old));
+            token.merge(delta).unwrap();
+            assert_eq!(token.range(), new);
+
+            let (min, max) = new.bounds();
+            for x in min..=max {
+                let mut out = [0u8; 16];
+                let mut out_token = [0u8; 16];
+                rcprf.eval(x, &mut out).unwrap();
+                token.eval(x, &mut out_token).unwrap();
+                assert_eq!(out, out_token);
+            }
+        };
+
+        // growing on the right
+        check_delta(RcPrfRange::new(3, 10), RcPrfRange::new(3, 25));
+        check_delta(RcPrfRange::new(0, 0), RcPrfRange::new(0, 1));
+        check_delta(RcPrfRange::new(0, 15), RcPrfRange::new(0, 31));
+        // growing on the left
+        check_delta(RcPrfRange::new(17, 25), RcPrfRange::new(2, 25));
+        check_delta(RcPrfRange::new(1, 31), RcPrfRange::new(0, 31));
+
+        // the new range must extend the old one on exactly one side
+        for (old, new) in [
+            (RcPrfRange::new(3, 10), RcPrfRange::new(3, 10)),
+            (RcPrfRange::new(3, 10), RcPrfRange::new(2, 11)),
+            (RcPrfRange::new(3, 10), RcPrfRange::new(4, 12)),
+            (RcPrfRange::new(3, 10), RcPrfRange::new(3, 9)),
+            (RcPrfRange::new(3, 10), RcPrfRange::new(12, 20)),
+        ] {
+            assert!(rcprf.constrain_delta(&old, &new).is_err());
+        }
+
+        // the delta must be in the range of the RcPrf
+        assert!(rcprf
+            .constrain_delta(
+                &RcPrfRange::new(3, 10),
+                &RcPrfRange::new(3, max_leaf_index(h) + 1)
+            )
+            .is_err());
+    }
+
+    #[test]
+    fn rcprf_find_leaf() {
+        let h = 6u8;
+        let rcprf = RcPrf::new(h).unwrap();
+        let range = RcPrfRange::new(3, 28);
+
+        let mut target = [0u8; 16];
+        rcprf.eval(17, &mut target).unwrap();
+
+        // match on the value
+        let mut evaluated = vec![];
+        let found = rcprf
+            .find_leaf(&range, 16, |i, v| {
+                evaluated.push(i);
+                v == target
+            })
+            .unwrap();
+        assert_eq!(found, Some((17, target.to_vec())));
+        // the evaluation stopped at the first match
+        assert_eq!(evaluated, (3..=17).collect::<Vec<u64>>());
+
+        // first match on the index
+        let (index, value) = rcprf
+            .find_leaf(&range, 16, |i, _| i % 5 == 0)
+            .unwrap()
+            .unwrap();
+        assert_eq!(index, 5);
+        let mut expected = [0u8; 16];
+        rcprf.eval(5, &mut expected).unwrap();
+        assert_eq!(value, expected);
+
+        assert_eq!(rcprf.find_leaf(&range, 16, |_, _| false).unwrap(), None);
+        assert_eq!(
+            rcprf
+                .find_leaf(&RcPrfRange::new(18, 28), 16, |_, v| v == target)
+                .unwrap(),
+            None
+        );
+
+        assert!(rcprf
+            .find_leaf(
+                &RcPrfRange::new(3, max_leaf_index(h) + 1),
+                16,
+                |_, _| { true }
+            )
+            .is_err());
+    }
+
+    #[test]
+    fn rcprf_from_master() {
+        let h = 6u8;
+        let master = Key256::new();
+
+        let rcprf_a = RcPrf::from_master(&master, b"label a", h).unwrap();
+        let rcprf_a_dup = RcPrf::from_master(&master, b"label a", h).unwrap();
+        let rcprf_b = RcPrf::from_master(&master, b"label b", h).unwrap();
+
+        for x in 0..=max_leaf_index(h) {
+            let mut out_a = [0u8; 16];
+            let mut out_a_dup = [0u8; 16];
+            let mut out_b = [0u8; 16];
+
+            rcprf_a.eval(x, &mut out_a).unwrap();
+            rcprf_a_dup.eval(x, &mut out_a_dup).unwrap();
+            rcprf_b.eval(x, &mut out_b).unwrap();
+
+            assert_eq!(out_a, out_a_dup);
+            assert_ne!(out_a, out_b);
+        }
+
+        assert!(
+            RcPrf::from_master(&master, b"label a", MAX_HEIGHT + 1).is_err()
+        );
+    }
+
+    /// Wrapper around an element, recording every zeroization of the
+    /// element (and the range of the zeroized element, in order), and
+    /// checking that the key material has actually been erased.
+    struct DropTracker<T: private::RcPrfElement> {
+        inner: T,
+        zeroizations: Arc<AtomicUsize>,
+        zeroized_ranges: Arc<Mutex<Vec<RcPrfRange>>>,
+        erased: Arc<AtomicUsize>,
+    }
+
+    impl<T: private::RcPrfElement> Zeroize for DropTracker<T> {
+        fn zeroize(&mut self) {
+            self.zeroized_ranges
+                .lock()
+                .unwrap()
+                .push(self.inner.range());
+            self.inner.zeroize();
+            self.zeroizations.fetch_add(1, Ordering::SeqCst);
+
+            // the serialized content of the element includes its key
+            let mut content = vec![];
+            self.inner.serialize_content(&mut content).unwrap();
+            if content.iter().all(|&b| b == 0) {
+                self.erased.fetch_add(1, Ordering::SeqCst);
+            }
+        }
+    }
+
+    impl<T: private::RcPrfElement> Drop for DropTracker<T> {
+        fn drop(&mut self) {
+            self.zeroize();
+        }
+    }
+
+    impl<T: private::RcPrfElement> TreeBasedPrf for DropTracker<T> {
+        fn tree_height(&self) -> u8 {
+            self.inner.tree_height()
+        }
+    }
+
+    impl<T: private::RcPrfElement> private::UncheckedRangePrf for DropTracker<T> {
+        fn unchecked_eval(&self, x: u64, output: &mut [u8]) {
+            self.inner.unchecked_eval(x, output);
+        }
+
+        fn unchecked_eval_range(
+            &self,
+            range: &RcPrfRange,
+            outputs: &mut [&mut [u8]],
+        ) {
+            self.inner.unchecked_eval_range(range, outputs);
+        }
+
+        #[cfg(feature = "rayon")]
+        fn unchecked_par_eval_range(
+            &self,
+            range: &RcPrfRange,
+            outputs: &mut [&mut [u8]],
+        ) {
+            self.inner.unchecked_par_eval_range(range, outputs);
+        }
+
+        fn unchecked_constrain(&self, range: &RcPrfRange) -> ConstrainedRcPrf {
+            self.inner.unchecked_constrain(range)
+        }
+    }
+
+    impl<T: private::RcPrfElement> RangePrf for DropTracker<T> {
+        fn range(&self) -> RcPrfRange {
+            self.inner.range()
+        }
+    }
+
+    impl<T: private::RcPrfElement> SerializableCleartextContent for DropTracker<T> {
+        fn serialization_content_byte_size(&self) -> usize {
+            self.inner.serialization_content_byte_size()
+        }
+
+        fn serialize_content(
+            &self,
+            writer: &mut dyn std::io::Write,
+        ) -> Result<usize, std::io::Error> {
+            self.inner.serialize_content(writer)
+        }
+    }
+
+    impl<T> SerializationTaggedType for DropTracker<T>
+    where
+        T: private::RcPrfElement + SerializationTaggedType,
+    {
+        fn serialization_tag() -> SerializationTag {
+            <T as SerializationTaggedType>::serialization_tag()
+        }
+    }
+
+    impl<T> private::RcPrfElement for DropTracker<T>
+    where
+        T: private::RcPrfElement + SerializationTaggedType,
+    {
+        fn is_leaf(&self) -> bool {
+            self.inner.is_leaf()
+        }
+
+        fn subtree_height(&self) -> u8 {
+            self.inner.subtree_height()
+        }
+
+        fn split_node(&self) -> private::RcPrfElementPair {
+            self.inner.split_node()
+        }
+
+        fn serialize_content_with_byte_order(
+            &self,
+            writer: &mut dyn std::io::Write,
+            byte_order: ByteOrder,
+        ) -> Result<usize, std::io::Error> {
+            self.inner
+                .serialize_content_with_byte_order(writer, byte_order)
+        }
+    }
+
+    /// Build a constrained `RcPrf` of height 6 whose elements are all tracked.
+    /// The range of the `RcPrf` is [0, 20].
+    fn tracked_constrained_rcprf(
+        zeroizations: &Arc<AtomicUsize>,
+        zeroized_ranges: &Arc<Mutex<Vec<RcPrfRange>>>,
+        erased: &Arc<AtomicUsize>,
+    ) -> (ConstrainedRcPrf, usize) {
+        let h = 6u8;
+        let mut elements: VecDeque<Pin<Box<dyn private::RcPrfElement>>> =
+            VecDeque::new();
+
+        // inner elements covering [0, 15] and [16, 19]
+        for (range, subtree_height) in
+            [(RcPrfRange::new(0, 15), 5), (RcPrfRange::new(16, 19), 3)]
+        {
+            elements.push_back(Box::pin(DropTracker {
+                inner: ConstrainedRcPrfInnerElement {
+                    prg: KeyDerivationPrg::new(),
+                    range,
+                    subtree_height,
+                    rcprf_height: h,
+                },
+                zeroizations: zeroizations.clone(),
+                zeroized_ranges: zeroized_ranges.clone(),
+                erased: erased.clone(),
+            }));
+        }
+        // a leaf for 20
+        elements.push_back(Box::pin(DropTracker {
+            inner: ConstrainedRcPrfLeafElement {
+                prf: crate::Prf::new(),
+                index: 20,
+                rcprf_height: h,
+            },
+            zeroizations: zeroizations.clone(),
+            zeroized_ranges: zeroized_ranges.clone(),
+            erased: erased.clone(),
+        }));
+
+        let n_elements = elements.len();
+        (ConstrainedRcPrf { elements }, n_elements)
+    }
+
+    #[test]
+    fn constrained_rcprf_drop_zeroization() {
+        let zeroizations = Arc::new(AtomicUsize::new(0));
+        let zeroized_ranges = Arc::new(Mutex::new(vec![]));
+        let erased = Arc::new(AtomicUsize::new(0));
+
+        let (constrained_rcprf, n_elements) =
+            tracked_constrained_rcprf(&zeroizations, &zeroized_ranges, &erased);
+        assert_eq!(constrained_rcprf.range(), RcPrfRange::new(0, 20));
+        assert_eq!(zeroizations.load(Ordering::SeqCst), 0);
+
+        let ranges: Vec<RcPrfRange> = constrained_rcprf
+            .element_ranges()
+            .into_iter()
+            .map(|(r, _)| r)
+            .collect();
+
+        drop(constrained_rcprf);
+
+        assert_eq!(zeroizations.load(Ordering::SeqCst), n_elements);
+        assert_eq!(erased.load(Ordering::SeqCst), n_elements);
+        // the elements are zeroized in the order of their ranges
+        assert_eq!(*zeroized_ranges.lock().unwrap(), ranges);
+    }
+
+    #[test]
+    fn constrained_rcprf_zeroize() {
+        let zeroizations = Arc::new(AtomicUsize::new(0));
+        let zeroized_ranges = Arc::new(Mutex::new(vec![]));
+        let erased = Arc::new(AtomicUsize::new(0));
+
+        let (mut constrained_rcprf, n_elements) =
+            tracked_constrained_rcprf(&zeroizations, &zeroized_ranges, &erased);
+
+        let ranges: Vec<RcPrfRange> = constrained_rcprf
+            .element_ranges()
+            .into_iter()
+            .map(|(r, _)| r)
+            .collect();
+
+        constrained_rcprf.zeroize();
+
+        assert!(constrained_rcprf.elements.is_empty());
+        assert_eq!(zeroizations.load(Ordering::SeqCst), n_elements);
+        assert_eq!(erased.load(Ordering::SeqCst), n_elements);
+        assert_eq!(*zeroized_ranges.lock().unwrap(), ranges);
+
+        // dropping the zeroized object must not zeroize the elements again
+        drop(constrained_rcprf);
+        assert_eq!(zeroizations.load(Ordering::SeqCst), n_elements);
+    }
+
+    #[test]
+    fn constrained_rcprf_range_map() {
+        let zeroizations = Arc::new(AtomicUsize::new(0));
+        let zeroized_ranges = Arc::new(Mutex::new(vec![]));
+        let erased = Arc::new(AtomicUsize::new(0));
+
+        let (constrained_rcprf, n_elements) =
+            tracked_constrained_rcprf(&zeroizations, &zeroized_ranges, &erased);
+        let expected: Vec<RcPrfRange> = constrained_rcprf
+            .element_ranges()
+            .into_iter()
+            .map(|(r, _)| r)
+            .collect();
+
+        let ranges = constrained_rcprf.into_range_map();
+        assert_eq!(ranges, expected);
+        assert_eq!(zeroizations.load(Ordering::SeqCst), n_elements);
+        assert_eq!(erased.load(Ordering::SeqCst), n_elements);
+
+        let rcprf = RcPrf::new(8).unwrap();
+        let range = RcPrfRange::new(3, 100);
+        let constrained = rcprf.constrain(&range).unwrap();
+        assert_eq!(
+            constrained.into_range_map(),
+            rcprf.covering_nodes(&range).unwrap()
+        );
+    }
+
+    #[test]
+    fn constrained_rcprf_pop_front_element() {
+        let rcprf = RcPrf::new(8).unwrap();
+        let range = RcPrfRange::new(3, 100);
+        let mut constrained = rcprf.constrain(&range).unwrap();
+        let expected_ranges = rcprf.covering_nodes(&range).unwrap();
+
+        let mut ranges = vec![];
+        let (min, max) = range.bounds();
+        let mut leaves = min..=max;
+        while let Some((elt_range, iter)) = constrained.pop_front_element(16) {
+            assert_eq!(iter.len() as u64, elt_range.width());
+            ranges.push(elt_range);
+            for (x, v) in iter {
+                assert_eq!(Some(x), leaves.next());
+                let mut expected = [0u8; 16];
+                rcprf.eval(x, &mut expected).unwrap();
+                assert_eq!(v, expected);
+            }
+            // the remaining elements can still be evaluated
+            if let Some(next) = leaves.clone().next() {
+                assert!(!constrained.is_empty());
+                assert_eq!(constrained.range(), RcPrfRange::new(next, max));
+                let mut out = [0u8; 16];
+                let mut expected = [0u8; 16];
+                constrained.eval(next, &mut out).unwrap();
+                rcprf.eval(next, &mut expected).unwrap();
+                assert_eq!(out, expected);
+            }
+        }
+        assert_eq!(leaves.next(), None);
+        assert_eq!(ranges, expected_ranges);
+        assert!(constrained.pop_front_element(16).is_none());
+
+        // popped elements are zeroized
+        let zeroizations = Arc::new(AtomicUsize::new(0));
+        let zeroized_ranges = Arc::new(Mutex::new(vec![]));
+        let erased = Arc::new(AtomicUsize::new(0));
+        let (mut constrained_rcprf, n_elements) =
+            tracked_constrained_rcprf(&zeroizations, &zeroized_ranges, &erased);
+        let mut popped = 0;
+        while let Some((_, iter)) = constrained_rcprf.pop_front_element(16) {
+            drop(iter);
+            popped += 1;
+            assert_eq!(zeroizations.load(Ordering::SeqCst), popped);
+            assert_eq!(erased.load(Ordering::SeqCst), popped);
+        }
+        assert_eq!(popped, n_elements);
+    }
+
+    #[test]
+    fn empty_constrained_rcprf() {
+        let rcprf = RcPrf::new(8).unwrap();
+        let mut drained = rcprf.constrain(&RcPrfRange::new(3, 100)).unwrap();
+        while drained.pop_front_element(16).is_some() {}
+        let mut zeroized = rcprf.constrain(&RcPrfRange::new(3, 100)).unwrap();
+        zeroized.zeroize();
+
+        // empty objects do not panic, and cannot be evaluated
+        for constrained in [drained, zeroized] {
+            assert!(constrained.is_empty());
+            assert_eq!(constrained.tree_height(), 0);
+            assert_eq!(constrained.range(), RcPrfRange::new(0, 0));
+            let mut out = [0u8; 16];
+            assert!(matches!(
+                constrained.eval(0, &mut out),
+                Err(RcPrfError::EmptyConstrainedRcPrf)
+            ));
+            assert!(matches!(
+                constrained.eval_range(&RcPrfRange::new(0, 0), &mut [&mut out]),
+                Err(RcPrfError::EmptyConstrainedRcPrf)
+            ));
+            assert!(matches!(
+                constrained.constrain(&RcPrfRange::new(0, 0)),
+                Err(RcPrfError::EmptyConstrainedRcPrf)
+            ));
+            assert_eq!(constrained.into_value_iter(16).count(), 0);
+        }
+    }
+
+    #[test]
+    fn rcprf_errors() {
+        const OUT_VEC_SIZE: usize = 8;
+        assert!(RcPrf::new(MAX_HEIGHT + 1).is_err());
+
+        let h = 8u8;
+        let rcprf = RcPrf::new(h).unwrap();
+        let mut output = [0u8; 16];
+        assert!(rcprf.eval(max_leaf_index(h) + 1, &mut output).is_err());
+
+        let mut outs = [[0u8; 16]; OUT_VEC_SIZE];
+        let mut slice: Vec<&mut [u8]> =
+            outs.iter_mut().map(|x| &mut x[..]).collect();
+
+        // out of range error
+        assert!(rcprf
+            .eval_range(
+                &RcPrfRange::from(
+                    max_leaf_index(h)
+                        ..(max_leaf_index(h) + OUT_VEC_SIZE as u64)
+                ),
+                &mut slice
+            )
+            .is_err());
+
+        // invalid vector size
+        assert!(rcprf
+            .eval_range(&RcPrfRange::from(2..3), &mut slice)
+            .is_err());
+    }
+
+    #[test]
+    fn rcprf_empty_output() {
+        const OUT_VEC_SIZE: usize = 8;
+        let h = 8u8;
+        let rcprf = RcPrf::new(h).unwrap();
+        let constrained_rcprf =
+            rcprf.constrain(&RcPrfRange::new(3, 40)).unwrap();
+
+        assert!(matches!(
+            rcprf.eval(0, &mut []),
+            Err(RcPrfError::EmptyOutput)
+        ));
+        assert!(matches!(
+            constrained_rcprf.eval(5, &mut []),
+            Err(RcPrfError::EmptyOutput)
+        ));
+
+        // a single empty output is enough to trigger the error
+        let mut outs = [[0u8; 16]; OUT_VEC_SIZE];
+        let mut empty = [0u8; 0];
+        let mut slice: Vec<&mut [u8]> =
+            outs.iter_mut().map(|x| &mut x[..]).collect();
+        slice[3] = &mut empty;
+        let range = RcPrfRange::from(5..(5 + OUT_VEC_SIZE as u64));
+
+        for prf in [&rcprf as &dyn RangePrf, &constrained_rcprf] {
+            assert!(matches!(
+                prf.eval_range(&range, &mut slice),
+                Err(RcPrfError::EmptyOutput)
+            ));
+            assert!(matches!(
+                prf.par_eval_range(&range, &mut slice),
+                Err(RcPrfError::EmptyOutput)
+            ));
+        }
+        // the other outputs have not been written
+        assert_eq!(outs, [[0u8; 16]; OUT_VEC_SIZE]);
+    }
 }
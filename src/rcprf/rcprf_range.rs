@@ -1,3 +1,5 @@
+use crate::rcprf::errors::RcPrfError;
+use crate::serialization::byte_order::ByteOrder;
 use crate::serialization::cleartext_serialization::*;
 use crate::serialization::errors::CleartextContentDeserializationError;
 // use std::ops::Bound::*;
@@ -6,11 +8,22 @@ use std::ops::{Bound, RangeBounds};
 use zeroize::Zeroize;
 
 /// Structure encoding the domain of a range-constrained PRF.
-#[derive(Clone, Debug, PartialEq, Eq)]
+///
+/// Ranges are ordered by their minimum, and then by their maximum. Note that
+/// `RcPrfRange` does not implement `Ord`: `Ord::min` and `Ord::max` would
+/// shadow the `min` and `max` methods of owned ranges. Use `RcPrfRange::bounds`
+/// as the key to sort ranges or to use them in ordered collections.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
 pub struct RcPrfRange {
     pub(crate) range: std::ops::RangeInclusive<u64>,
 }
 
+impl PartialOrd for RcPrfRange {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        self.bounds().partial_cmp(&other.bounds())
+    }
+}
+
 impl From<std::ops::Range<u64>> for RcPrfRange {
     fn from(range: std::ops::Range<u64>) -> Self {
         assert!(
@@ -66,6 +79,145 @@ impl RcPrfRange {
         RcPrfRange { range: (min..=max) }
     }
 
+    /// Creates a new range spanning from `min` to `max` (included).
+    /// Unlike `new`, returns an error instead of panicking if `min > max`.
+    ///
+    /// # Example
+    /// ```
+    /// # extern crate crypto_tk_rs;
+    /// use crypto_tk_rs::RcPrfRange;
+    /// assert_eq!(RcPrfRange::try_new(4,6).unwrap(), RcPrfRange::new(4,6));
+    /// assert!(RcPrfRange::try_new(6,4).is_err());
+    /// ```
+    pub fn try_new(min: u64, max: u64) -> Result<Self, RcPrfError> {
+        if min > max {
+            return Err(RcPrfError::EmptyRange(
+                Bound::Included(min),
+                Bound::Included(max),
+            ));
+        }
+        Ok(RcPrfRange { range: (min..=max) })
+    }
+
+    /// Creates a new range from a `start..end` range (`end` excluded).
+    /// Unlike the `From` implementation, returns an error instead of panicking
+    /// if the input range is empty.
+    ///
+    /// # Example
+    /// ```
+    /// # extern crate crypto_tk_rs;
+    /// use crypto_tk_rs::RcPrfRange;
+    /// assert_eq!(RcPrfRange::try_from_range(4..7).unwrap(), RcPrfRange::new(4,6));
+    /// assert!(RcPrfRange::try_from_range(4..4).is_err());
+    /// ```
+    pub fn try_from_range(
+        range: std::ops::Range<u64>,
+    ) -> Result<Self, RcPrfError> {
+        Self::from_bounds(range)
+    }
+
+    /// Creates a new range from any standard range. Unbounded start and end
+    /// are respectively clamped to `0` and `u64::MAX`.
+    /// Returns an error if the input range is empty.
+    ///
+    /// # Example
+    /// ```
+    /// # extern crate crypto_tk_rs;
+    /// use crypto_tk_rs::RcPrfRange;
+    /// assert_eq!(RcPrfRange::from_bounds(4..7).unwrap(), RcPrfRange::new(4,6));
+    /// assert_eq!(RcPrfRange::from_bounds(..7).unwrap(), RcPrfRange::new(0,6));
+    /// assert_eq!(RcPrfRange::from_bounds(4..).unwrap(), RcPrfRange::new(4,u64::MAX));
+    /// assert!(RcPrfRange::from_bounds(4..4).is_err());
+    /// ```
+    pub fn from_bounds<R>(r: R) -> Result<Self, RcPrfError>
+    where
+        R: RangeBounds<u64>,
+    {
+        let empty_range_error = || {
+            RcPrfError::EmptyRange(
+                r.start_bound().cloned(),
+                r.end_bound().cloned(),
+            )
+        };
+
+        let min = match r.start_bound() {
+            Bound::Unbounded => 0,
+            Bound::Included(&a) => a,
+            Bound::Excluded(&a) => {
+                a.checked_add(1).ok_or_else(empty_range_error)?
+            }
+        };
+
+        let max = match r.end_bound() {
+            Bound::Unbounded => u64::MAX,
+            Bound::Included(&a) => a,
+            Bound::Excluded(&a) => {
+                a.checked_sub(1).ok_or_else(empty_range_error)?
+            }
+        };
+
+        if min > max {
+            return Err(empty_range_error());
+        }
+        Ok(RcPrfRange { range: (min..=max) })
+    }
+
+    /// Creates the aligned range of the `2^suffix_bits` leaves whose index
+    /// starts with the bit prefix `prefix`, i.e. the range
+    /// `[prefix << suffix_bits, ((prefix+1) << suffix_bits) - 1]`.
+    ///
+    /// # Panics
+    /// Panics if `suffix_bits` is larger than 64, or if the range does not fit
+    /// in 64 bits integers.
+    ///
+    /// # Example
+    /// ```
+    /// # extern crate crypto_tk_rs;
+    /// use crypto_tk_rs::RcPrfRange;
+    /// assert_eq!(RcPrfRange::from_prefix(3, 2), RcPrfRange::new(12, 15));
+    /// assert_eq!(RcPrfRange::from_prefix(5, 0), RcPrfRange::new(5, 5));
+    /// ```
+    #[must_use]
+    pub fn from_prefix(prefix: u64, suffix_bits: u8) -> Self {
+        assert!(suffix_bits <= 64, "Invalid number of suffix bits");
+        if suffix_bits == 64 {
+            assert!(prefix == 0, "Range overflow");
+            return RcPrfRange::new(0, u64::MAX);
+        }
+        assert!(prefix <= (u64::MAX >> suffix_bits), "Range overflow");
+
+        let min = prefix << suffix_bits;
+        let max = min | ((1u64 << suffix_bits) - 1);
+        RcPrfRange::new(min, max)
+    }
+
+    /// Returns the `(prefix, suffix_bits)` pair such that the range is equal
+    /// to `RcPrfRange::from_prefix(prefix, suffix_bits)`, or `None` if the
+    /// range is not such an aligned block of leaves.
+    ///
+    /// # Example
+    /// ```
+    /// # extern crate crypto_tk_rs;
+    /// use crypto_tk_rs::RcPrfRange;
+    /// assert_eq!(RcPrfRange::new(12, 15).as_prefix(), Some((3, 2)));
+    /// assert_eq!(RcPrfRange::new(5, 5).as_prefix(), Some((5, 0)));
+    /// assert_eq!(RcPrfRange::new(11, 14).as_prefix(), None);
+    /// assert_eq!(RcPrfRange::new(12, 14).as_prefix(), None);
+    /// ```
+    #[must_use]
+    pub fn as_prefix(&self) -> Option<(u64, u8)> {
+        if self.min() == 0 && self.max() == u64::MAX {
+            return Some((0, 64));
+        }
+        let width = self.width();
+        if !width.is_power_of_two() || self.min() & (width - 1) != 0 {
+            return None;
+        }
+        #[allow(clippy::cast_possible_truncation)]
+        let suffix_bits = width.trailing_zeros() as u8;
+        Some((self.min() >> suffix_bits, suffix_bits))
+    }
+
     /// Returns the minimum value in the range
     ///
     /// # Example
@@ -94,6 +246,25 @@ impl RcPrfRange {
         *self.range.end()
     }
 
+    /// Returns the `(min, max)` pair of the range, which is totally ordered
+    /// consistently with the `PartialOrd` implementation of `RcPrfRange`.
+    ///
+    /// # Example
+    /// ```
+    /// # extern crate crypto_tk_rs;
+    /// use crypto_tk_rs::RcPrfRange;
+    /// let (min, max) = RcPrfRange::from(4..7).bounds();
+    /// assert_eq!((min, max), (4, 6));
+    ///
+    /// let mut ranges = vec![RcPrfRange::new(4, 6), RcPrfRange::new(2, 8)];
+    /// ranges.sort_unstable_by_key(RcPrfRange::bounds);
+    /// assert_eq!(ranges, vec![RcPrfRange::new(2, 8), RcPrfRange::new(4, 6)]);
+    /// ```
+    #[must_use]
+    pub fn bounds(&self) -> (u64, u64) {
+        (self.min(), self.max())
+    }
+
     /// Returns the width of the range
     ///
     /// # Example
@@ -147,23 +318,11 @@ impl RcPrfRange {
     where
         R: RangeBounds<u64>,
     {
-        let cond1: bool = match r.start_bound() {
-            Bound::Unbounded => true,
-            Bound::Included(&a) => self.max() >= a,
-            Bound::Excluded(&a) => self.max() > a,
-        };
-
-        let cond2: bool = match r.end_bound() {
-            Bound::Unbounded => true,
-            Bound::Included(&a) => self.min() <= a,
-            Bound::Excluded(&a) => self.min() < a,
-        };
-
-        cond1 && cond2
+        self.intersection(r).is_some()
     }
 
     /// Returns the intersection with `r` or `None` if ranges do
-    ///  not intersect.
+    ///  not intersect. An empty `r` (e.g. `..0`) never intersects the range.
     ///
     /// # Example
     /// ```
@@ -184,36 +343,18 @@ impl RcPrfRange {
     where
         R: RangeBounds<u64>,
     {
-        let mut intersects = true;
-        let r_start: u64 = match r.start_bound() {
-            Bound::Unbounded => 0,
-            Bound::Included(&a) if self.max() >= a => a,
-            // if the next condition is true, we are sure that a+1 is not
-            // overflowing as a < u64::MAX
-            Bound::Excluded(&a) if self.max() > a => a + 1,
-            _ => {
-                intersects = false;
-                0
-            }
-        };
-
-        let r_end: u64 = match r.end_bound() {
-            Bound::Unbounded => u64::max_value(),
-            Bound::Included(&a) if self.min() <= a => a,
-            // if the next condition is true, we are sure that a-1 is not
-            // underflowing as a > 0 <=> a > 1
-            Bound::Excluded(&a) if self.min() < a => a - 1,
-            _ => {
-                intersects = false;
-                0
-            }
-        };
+        // normalizing the bounds takes care of the overflowing and
+        // underflowing excluded bounds, such as `..0`
+        let r = Self::normalize_bounds(r)?;
+        let (r_min, r_max) = r.bounds();
+        let min = r_min.max(self.min());
+        let max = r_max.min(self.max());
 
-        intersects.then(|| {
-            RcPrfRange::new(r_start.max(self.min()), r_end.min(self.max()))
-        })
+        (min <= max).then(|| RcPrfRange::new(min, max))
     }
-    /// Returns `true` if `r` is contained in the range
+
+    /// Returns `true` if `r` is contained in the range. An empty `r` (e.g.
+    /// `..0`) is never contained in the range.
     ///
     /// # Example
     /// ```
@@ -237,22 +378,108 @@ impl RcPrfRange {
     where
         R: RangeBounds<u64>,
     {
-        let cond1: bool = match r.start_bound() {
-            Bound::Unbounded => self.min() == 0,
-            Bound::Included(&a) => self.min() <= a,
-            Bound::Excluded(&a) => self.min() < a,
-        };
+        Self::normalize_bounds(r).is_some_and(|r| {
+            let (min, max) = r.bounds();
+            self.min() <= min && self.max() >= max
+        })
+    }
 
-        let cond2: bool = match r.end_bound() {
-            Bound::Unbounded => self.max() == u64::max_value(),
-            Bound::Included(&a) => self.max() >= a,
-            Bound::Excluded(&0) => false,
-            // This never underflows as a >= 1 (the case a == 0) is already
-            // matched
-            Bound::Excluded(&a) => self.max() >= a - 1,
-        };
+    /// Converts any standard range to a `RcPrfRange`, or returns `None` if it
+    /// is empty.
+    fn normalize_bounds<R>(r: &R) -> Option<RcPrfRange>
+    where
+        R: RangeBounds<u64>,
+    {
+        Self::from_bounds((r.start_bound().cloned(), r.end_bound().cloned()))
+            .ok()
+    }
+}
+
+/// Returns `true` if the ranges of `ranges` are pairwise disjoint. Adjacent
+/// ranges (e.g. `[0,3]` and `[4,7]`) are disjoint.
+///
+/// The ranges are sorted first, so that only consecutive ranges have to be
+/// compared: this takes `O(n log n)` time for `n` ranges.
+///
+/// # Example
+/// ```
+/// # extern crate crypto_tk_rs;
+/// use crypto_tk_rs::{ranges_are_disjoint, RcPrfRange};
+/// assert!(ranges_are_disjoint(&[
+///     RcPrfRange::new(8, 10),
+///     RcPrfRange::new(0, 3),
+///     RcPrfRange::new(4, 7),
+/// ]));
+/// assert!(!ranges_are_disjoint(&[
+///     RcPrfRange::new(0, 3),
+///     RcPrfRange::new(8, 10),
+///     RcPrfRange::new(3, 7),
+/// ]));
+/// ```
+#[must_use]
+pub fn ranges_are_disjoint(ranges: &[RcPrfRange]) -> bool {
+    let mut sorted: Vec<&RcPrfRange> = ranges.iter().collect();
+    sorted.sort_unstable_by_key(|r| r.bounds());
+
+    // as the ranges are sorted by their minimum, if a range does not
+    // intersect the next one, it does not intersect any of the following ones
+    sorted.windows(2).all(|w| !w[0].intersects(w[1]))
+}
 
-        cond1 && cond2
+impl RcPrfRange {
+    /// Serialize the range to `writer`, encoding its bounds with the
+    /// `byte_order` byte order. Returns the number of written bytes.
+    ///
+    /// # Example
+    /// ```
+    /// # extern crate crypto_tk_rs;
+    /// use crypto_tk_rs::{ByteOrder, RcPrfRange};
+    ///
+    /// let range = RcPrfRange::new(1, 2);
+    /// let mut bytes = vec![];
+    /// range
+    ///     .serialize_with_byte_order(&mut bytes, ByteOrder::BigEndian)
+    ///     .unwrap();
+    /// assert_eq!(bytes, [0, 0, 0, 0, 0, 0, 0, 1, 0, 0, 0, 0, 0, 0, 0, 2]);
+    ///
+    /// let deserialized = RcPrfRange::deserialize_with_byte_order(
+    ///     &mut &bytes[..],
+    ///     ByteOrder::BigEndian,
+    /// )
+    /// .unwrap();
+    /// assert_eq!(deserialized, range);
+    /// ```
+    pub fn serialize_with_byte_order(
+        &self,
+        writer: &mut dyn std::io::Write,
+        byte_order: ByteOrder,
+    ) -> Result<usize, std::io::Error> {
+        writer.write_all(&byte_order.u64_to_bytes(self.min()))?;
+        writer.write_all(&byte_order.u64_to_bytes(self.max()))?;
+
+        Ok(self.serialization_content_byte_size())
+    }
+
+    /// Deserialize a range from `reader`, whose bounds are encoded with the
+    /// `byte_order` byte order. Returns an error if the read bounds do not
+    /// form a valid range.
+    pub fn deserialize_with_byte_order(
+        reader: &mut dyn std::io::Read,
+        byte_order: ByteOrder,
+    ) -> Result<Self, CleartextContentDeserializationError> {
+        let mut min_bytes = [0u8; 8];
+        reader.read_exact(&mut min_bytes)?;
+        let min = byte_order.u64_from_bytes(min_bytes);
+
+        let mut max_bytes = [0u8; 8];
+        reader.read_exact(&mut max_bytes)?;
+        let max = byte_order.u64_from_bytes(max_bytes);
+
+        RcPrfRange::try_new(min, max).map_err(|e| {
+            CleartextContentDeserializationError::ContentError(format!(
+                "Invalid range bounds: {e}"
+            ))
+        })
     }
 }
 
@@ -264,10 +491,7 @@ impl SerializableCleartextContent for RcPrfRange {
         &self,
         writer: &mut dyn std::io::Write,
     ) -> Result<usize, std::io::Error> {
-        writer.write_all(&self.min().to_le_bytes())?;
-        writer.write_all(&self.max().to_le_bytes())?;
-
-        Ok(self.serialization_content_byte_size())
+        self.serialize_with_byte_order(writer, ByteOrder::default())
     }
 }
 
@@ -275,14 +499,350 @@ impl DeserializableCleartextContent for RcPrfRange {
     fn deserialize_content(
         reader: &mut dyn std::io::Read,
     ) -> Result<Self, CleartextContentDeserializationError> {
-        let mut min_bytes = [0u8; 8];
-        reader.read_exact(&mut min_bytes)?;
-        let min = u64::from_le_bytes(min_bytes);
+        RcPrfRange::deserialize_with_byte_order(reader, ByteOrder::default())
+    }
+}
 
-        let mut max_bytes = [0u8; 8];
-        reader.read_exact(&mut max_bytes)?;
-        let max = u64::from_le_bytes(max_bytes);
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used)]
+    use super::*;
+    use rand::prelude::*;
+    use std::collections::{BTreeSet, HashSet};
+
+    #[test]
+    fn excluded_zero_end_bound() {
+        let ranges = [
+            RcPrfRange::new(0, 0),
+            RcPrfRange::new(0, 10),
+            RcPrfRange::new(3, 10),
+            RcPrfRange::new(0, u64::MAX),
+        ];
+        let empty_bounds = [
+            (Bound::Unbounded, Bound::Excluded(0)),
+            (Bound::Included(0), Bound::Excluded(0)),
+            (Bound::Excluded(0), Bound::Excluded(0)),
+            (Bound::Included(5), Bound::Excluded(0)),
+        ];
+
+        for range in &ranges {
+            assert!(!range.intersects(&(..0)));
+            assert!(!range.intersects(&(0..0)));
+            assert_eq!(range.intersection(&(..0)), None);
+            assert_eq!(range.intersection(&(0..0)), None);
+            assert!(!range.contains_range(&(..0)));
+            assert!(!range.contains_range(&(0..0)));
+
+            for bounds in &empty_bounds {
+                assert!(!range.intersects(bounds));
+                assert_eq!(range.intersection(bounds), None);
+                assert!(!range.contains_range(bounds));
+            }
+
+            // the smallest non-empty range ending with an excluded bound
+            assert_eq!(range.intersects(&(..1)), range.min() == 0);
+            assert_eq!(
+                range.intersection(&(..1)),
+                (range.min() == 0).then(|| RcPrfRange::new(0, 0))
+            );
+            assert_eq!(range.contains_range(&(..1)), range.min() == 0);
+        }
+    }
+
+    #[test]
+    fn empty_bounds_consistency() {
+        let range = RcPrfRange::new(2, 10);
+        #[allow(clippy::reversed_empty_ranges)]
+        {
+            assert!(!range.intersects(&(8..3)));
+            assert_eq!(range.intersection(&(8..3)), None);
+            assert!(!range.contains_range(&(8..3)));
+            assert!(!range.contains_range(&(8..=3)));
+        }
+        let overflowing = (Bound::Excluded(u64::MAX), Bound::Unbounded);
+        assert!(!range.intersects(&overflowing));
+        assert_eq!(range.intersection(&overflowing), None);
+        assert!(!range.contains_range(&overflowing));
+    }
+
+    #[test]
+    fn from_bounds() {
+        assert_eq!(
+            RcPrfRange::from_bounds(3..8).unwrap(),
+            RcPrfRange::new(3, 7)
+        );
+        assert_eq!(
+            RcPrfRange::from_bounds(3..=8).unwrap(),
+            RcPrfRange::new(3, 8)
+        );
+        assert_eq!(
+            RcPrfRange::from_bounds(..8).unwrap(),
+            RcPrfRange::new(0, 7)
+        );
+        assert_eq!(
+            RcPrfRange::from_bounds(..=8).unwrap(),
+            RcPrfRange::new(0, 8)
+        );
+        assert_eq!(
+            RcPrfRange::from_bounds(3..).unwrap(),
+            RcPrfRange::new(3, u64::MAX)
+        );
+        assert_eq!(
+            RcPrfRange::from_bounds(..).unwrap(),
+            RcPrfRange::new(0, u64::MAX)
+        );
+        assert_eq!(
+            RcPrfRange::from_bounds(RcPrfRange::new(3, 8)).unwrap(),
+            RcPrfRange::new(3, 8)
+        );
+        assert_eq!(
+            RcPrfRange::from_bounds((Bound::Excluded(3), Bound::Excluded(8)))
+                .unwrap(),
+            RcPrfRange::new(4, 7)
+        );
+
+        // empty ranges
+        assert!(matches!(
+            RcPrfRange::from_bounds(3..3),
+            Err(RcPrfError::EmptyRange(..))
+        ));
+        assert!(RcPrfRange::from_bounds(..0).is_err());
+        #[allow(clippy::reversed_empty_ranges)]
+        {
+            assert!(RcPrfRange::from_bounds(8..3).is_err());
+            assert!(RcPrfRange::from_bounds(8..=3).is_err());
+        }
+        assert!(RcPrfRange::from_bounds((
+            Bound::Excluded(u64::MAX),
+            Bound::Unbounded
+        ))
+        .is_err());
+        assert!(RcPrfRange::from_bounds((
+            Bound::Excluded(3),
+            Bound::Excluded(4)
+        ))
+        .is_err());
+    }
+
+    #[test]
+    fn prefix_ranges() {
+        for suffix_bits in 0..64u8 {
+            let max_prefix = u64::MAX >> suffix_bits;
+            for prefix in [0u64, 1, 2, 5, max_prefix] {
+                if prefix > max_prefix {
+                    continue;
+                }
+                let range = RcPrfRange::from_prefix(prefix, suffix_bits);
+                assert_eq!(range.bounds().0, prefix << suffix_bits);
+                assert_eq!(range.width(), 1u64 << suffix_bits);
+                assert_eq!(range.as_prefix(), Some((prefix, suffix_bits)));
+            }
+        }
+        assert_eq!(
+            RcPrfRange::from_prefix(0, 64),
+            RcPrfRange::new(0, u64::MAX)
+        );
+        assert_eq!(RcPrfRange::new(0, u64::MAX).as_prefix(), Some((0, 64)));
+
+        // misaligned ranges
+        assert_eq!(RcPrfRange::new(1, 2).as_prefix(), None);
+        assert_eq!(RcPrfRange::new(4, 11).as_prefix(), None);
+        assert_eq!(RcPrfRange::new(8, 13).as_prefix(), None);
+        assert_eq!(RcPrfRange::new(0, 2).as_prefix(), None);
+        assert_eq!(RcPrfRange::new(1, u64::MAX).as_prefix(), None);
+        assert_eq!(RcPrfRange::new(0, u64::MAX - 1).as_prefix(), None);
+    }
+
+    #[test]
+    #[should_panic(expected = "Range overflow")]
+    fn prefix_range_overflow() {
+        let _ = RcPrfRange::from_prefix(u64::MAX, 1);
+    }
+
+    #[test]
+    fn checked_constructors() {
+        assert_eq!(RcPrfRange::try_new(3, 8).unwrap(), RcPrfRange::new(3, 8));
+        assert_eq!(RcPrfRange::try_new(3, 3).unwrap(), RcPrfRange::new(3, 3));
+        assert!(matches!(
+            RcPrfRange::try_new(8, 3),
+            Err(RcPrfError::EmptyRange(
+                Bound::Included(8),
+                Bound::Included(3)
+            ))
+        ));
+
+        assert_eq!(
+            RcPrfRange::try_from_range(3..8).unwrap(),
+            RcPrfRange::from(3..8)
+        );
+        assert!(RcPrfRange::try_from_range(3..3).is_err());
+        #[allow(clippy::reversed_empty_ranges)]
+        {
+            assert!(RcPrfRange::try_from_range(8..3).is_err());
+        }
+    }
+
+    #[test]
+    fn disjoint_ranges() {
+        assert!(ranges_are_disjoint(&[]));
+        assert!(ranges_are_disjoint(&[RcPrfRange::new(0, u64::MAX)]));
+
+        // disjoint
+        assert!(ranges_are_disjoint(&[
+            RcPrfRange::new(20, 30),
+            RcPrfRange::new(0, 3),
+            RcPrfRange::new(10, 12),
+            RcPrfRange::new(u64::MAX, u64::MAX),
+        ]));
+
+        // adjacent
+        assert!(ranges_are_disjoint(&[
+            RcPrfRange::new(4, 7),
+            RcPrfRange::new(8, 8),
+            RcPrfRange::new(0, 3),
+            RcPrfRange::new(9, u64::MAX),
+        ]));
+
+        // overlapping
+        assert!(!ranges_are_disjoint(&[
+            RcPrfRange::new(0, 4),
+            RcPrfRange::new(4, 7),
+        ]));
+        assert!(!ranges_are_disjoint(&[
+            RcPrfRange::new(5, 5),
+            RcPrfRange::new(0, 3),
+            RcPrfRange::new(5, 5),
+        ]));
+        // a range containing several others, which are not consecutive to it
+        // once sorted
+        assert!(!ranges_are_disjoint(&[
+            RcPrfRange::new(2, 3),
+            RcPrfRange::new(10, 12),
+            RcPrfRange::new(0, 20),
+            RcPrfRange::new(30, 40),
+        ]));
+        assert!(!ranges_are_disjoint(&[
+            RcPrfRange::new(0, 10),
+            RcPrfRange::new(0, 2),
+        ]));
+
+        // consistency with the pairwise check on random sets
+        let mut rng = thread_rng();
+        for _ in 0..200 {
+            let ranges: Vec<RcPrfRange> = (0..5)
+                .map(|_| {
+                    let a = rng.gen_range(0..100u64);
+                    let b = rng.gen_range(0..100u64);
+                    RcPrfRange::new(a.min(b), a.max(b))
+                })
+                .collect();
+            let pairwise = ranges.iter().enumerate().all(|(i, r1)| {
+                ranges[i + 1..].iter().all(|r2| !r1.intersects(r2))
+            });
+            assert_eq!(ranges_are_disjoint(&ranges), pairwise);
+        }
+    }
+
+    #[test]
+    fn range_ordering() {
+        let ranges = [
+            RcPrfRange::new(4, 10),
+            RcPrfRange::new(2, 8),
+            RcPrfRange::new(4, 6),
+            RcPrfRange::new(2, 8),
+            RcPrfRange::new(0, 20),
+            RcPrfRange::new(4, 4),
+        ];
+
+        // overlapping ranges are sorted by minimum, then by maximum, and the
+        // duplicates are removed
+        let set: BTreeSet<(u64, u64)> =
+            ranges.iter().map(RcPrfRange::bounds).collect();
+        let sorted: Vec<RcPrfRange> = set
+            .into_iter()
+            .map(|(min, max)| RcPrfRange::new(min, max))
+            .collect();
+
+        assert!(sorted.windows(2).all(|w| w[0] < w[1]));
+        assert_eq!(
+            RcPrfRange::new(4, 6).partial_cmp(&RcPrfRange::new(4, 10)),
+            Some(std::cmp::Ordering::Less)
+        );
+
+        let mut sorted_vec = ranges.to_vec();
+        sorted_vec.sort_unstable_by_key(RcPrfRange::bounds);
+        sorted_vec.dedup();
+        assert_eq!(sorted_vec, sorted);
+
+        assert_eq!(
+            sorted,
+            vec![
+                RcPrfRange::new(0, 20),
+                RcPrfRange::new(2, 8),
+                RcPrfRange::new(4, 4),
+                RcPrfRange::new(4, 6),
+                RcPrfRange::new(4, 10),
+            ]
+        );
+
+        let hash_set: HashSet<RcPrfRange> = ranges.iter().cloned().collect();
+        assert_eq!(hash_set.len(), 5);
+        assert!(hash_set.contains(&RcPrfRange::from(2..9)));
+    }
+
+    #[test]
+    fn big_endian_serialization() {
+        let mut rng = thread_rng();
+
+        for _ in 0..100 {
+            let a: u64 = rng.gen();
+            let b: u64 = rng.gen();
+            let range = RcPrfRange::new(a.min(b), a.max(b));
+
+            let mut le_bytes = vec![];
+            let mut be_bytes = vec![];
+            range.serialize_content(&mut le_bytes).unwrap();
+            let written = range
+                .serialize_with_byte_order(&mut be_bytes, ByteOrder::BigEndian)
+                .unwrap();
+            assert_eq!(written, be_bytes.len());
+
+            let (min, max) = range.bounds();
+            assert_eq!(&be_bytes[..8], &min.to_be_bytes());
+            assert_eq!(&be_bytes[8..], &max.to_be_bytes());
+
+            // the default byte order is little-endian
+            let mut default_bytes = vec![];
+            range
+                .serialize_with_byte_order(
+                    &mut default_bytes,
+                    ByteOrder::default(),
+                )
+                .unwrap();
+            assert_eq!(default_bytes, le_bytes);
+
+            let deserialized = RcPrfRange::deserialize_with_byte_order(
+                &mut &be_bytes[..],
+                ByteOrder::BigEndian,
+            )
+            .unwrap();
+            assert_eq!(deserialized, range);
+        }
+    }
+
+    #[test]
+    fn invalid_range_deserialization() {
+        for byte_order in [ByteOrder::LittleEndian, ByteOrder::BigEndian] {
+            let mut bytes = byte_order.u64_to_bytes(5).to_vec();
+            bytes.extend_from_slice(&byte_order.u64_to_bytes(4));
 
-        Ok(RcPrfRange::new(min, max))
+            assert!(matches!(
+                RcPrfRange::deserialize_with_byte_order(
+                    &mut &bytes[..],
+                    byte_order
+                ),
+                Err(CleartextContentDeserializationError::ContentError(_))
+            ));
+        }
     }
 }
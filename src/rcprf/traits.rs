@@ -1,4 +1,7 @@
 use crate::rcprf::*;
+use crate::serialization::byte_order::ByteOrder;
+
+use std::ops::{Bound, RangeBounds};
 
 pub(crate) mod private {
     use super::*;
@@ -42,6 +45,14 @@ pub(crate) mod private {
         }
 
         fn split_node(&self) -> RcPrfElementPair;
+
+        /// Serialize the content of the element, encoding its integers with
+        /// the `byte_order` byte order
+        fn serialize_content_with_byte_order(
+            &self,
+            writer: &mut dyn std::io::Write,
+            byte_order: ByteOrder,
+        ) -> Result<usize, std::io::Error>;
     }
 }
 
@@ -51,9 +62,12 @@ pub trait RangePrf: private::UncheckedRangePrf {
     fn range(&self) -> RcPrfRange;
 
     /// Evaluate the PRF on the input `x` and put the result in `output`.
-    /// Returns an error when the input is out of the PRF range.
+    /// Returns an error when the input is out of the PRF range, or when
+    /// `output` is empty.
     fn eval(&self, x: u64, output: &mut [u8]) -> Result<(), RcPrfError> {
-        if self.range().contains_leaf(x) {
+        if output.is_empty() {
+            Err(RcPrfError::EmptyOutput)
+        } else if self.range().contains_leaf(x) {
             self.unchecked_eval(x, output);
             Ok(())
         } else {
@@ -64,7 +78,8 @@ pub trait RangePrf: private::UncheckedRangePrf {
     /// Evaluate the PRF on every value of the `range` and put the result in
     /// `outputs` such that the i-th value of the range is put at the i-th
     /// position of the output.
-    /// Returns an error when `range` is not contained in the PRF's range.
+    /// Returns an error when `range` is not contained in the PRF's range, or
+    /// when one of the outputs is empty.
     fn eval_range(
         &self,
         range: &RcPrfRange,
@@ -74,16 +89,94 @@ pub trait RangePrf: private::UncheckedRangePrf {
             Err(RcPrfError::InvalidEvalRange(range.clone(), self.range()))
         } else if range.width() != outputs.len() as u64 {
             Err(RcPrfError::InvalidRangeWidth(outputs.len(), range.width()))
+        } else if outputs.iter().any(|out| out.is_empty()) {
+            Err(RcPrfError::EmptyOutput)
         } else {
             self.unchecked_eval_range(range, outputs);
             Ok(())
         }
     }
 
+    /// Evaluate the PRF on every value of the standard Rust range `range`
+    /// (e.g. `2..5` or `2..=5`), as for `eval_range`.
+    /// Returns an error when `range` is unbounded or empty, or in the same
+    /// cases as `eval_range`.
+    ///
+    /// # Example
+    /// ```
+    /// # extern crate crypto_tk_rs;
+    /// use crypto_tk_rs::{RangePrf, RcPrf};
+    ///
+    /// let rcprf = RcPrf::new(4).unwrap();
+    /// let mut out = [[0u8; 16]; 3];
+    /// let mut outputs: Vec<&mut [u8]> =
+    ///     out.iter_mut().map(|o| &mut o[..]).collect();
+    ///
+    /// rcprf.eval_std_range(2..5, &mut outputs).unwrap();
+    /// assert!(rcprf.eval_std_range(2.., &mut outputs).is_err());
+    /// ```
+    fn eval_std_range<R>(
+        &self,
+        range: R,
+        outputs: &mut [&mut [u8]],
+    ) -> Result<(), RcPrfError>
+    where
+        R: RangeBounds<u64>,
+        Self: Sized,
+    {
+        self.eval_range(&bounded_range(&range)?, outputs)
+    }
+
+    /// Evaluate the PRF on every value of the `range`, with outputs of
+    /// `out_width` bytes, and put the results in the contiguous buffer
+    /// `output`: the evaluation of the i-th value of the range is put at
+    /// offset `i*out_width`.
+    /// Returns an error when `range` is not contained in the PRF's range, when
+    /// `out_width` is 0, or when `output` is not `range.width() * out_width`
+    /// bytes long.
+    ///
+    /// # Example
+    /// ```
+    /// # extern crate crypto_tk_rs;
+    /// use crypto_tk_rs::{RangePrf, RcPrf, RcPrfRange};
+    ///
+    /// let rcprf = RcPrf::new(4).unwrap();
+    /// let mut output = [0u8; 3 * 16];
+    /// rcprf.eval_range_flat(&RcPrfRange::new(2, 4), 16, &mut output).unwrap();
+    ///
+    /// let mut expected = [0u8; 16];
+    /// rcprf.eval(3, &mut expected).unwrap();
+    /// assert_eq!(output[16..32], expected);
+    /// ```
+    fn eval_range_flat(
+        &self,
+        range: &RcPrfRange,
+        out_width: usize,
+        output: &mut [u8],
+    ) -> Result<(), RcPrfError> {
+        if out_width == 0 {
+            return Err(RcPrfError::EmptyOutput);
+        }
+        if range.width().checked_mul(out_width as u64)
+            != Some(output.len() as u64)
+        {
+            return Err(RcPrfError::InvalidFlatOutputLength(
+                output.len(),
+                range.width(),
+                out_width,
+            ));
+        }
+
+        let mut outputs: Vec<&mut [u8]> =
+            output.chunks_mut(out_width).collect();
+        self.eval_range(range, &mut outputs)
+    }
+
     /// Evaluate the PRF on every value of the `range` in parallel and put the
     /// result in `outputs` such that the i-th value of the range is put at the
     /// i-th position of the output.
-    /// Returns an error when `range` is not contained in the PRF's range.
+    /// Returns an error when `range` is not contained in the PRF's range, or
+    /// when one of the outputs is empty.
     #[cfg(feature = "rayon")]
     fn par_eval_range(
         &self,
@@ -94,6 +187,8 @@ pub trait RangePrf: private::UncheckedRangePrf {
             Err(RcPrfError::InvalidEvalRange(range.clone(), self.range()))
         } else if range.width() != outputs.len() as u64 {
             Err(RcPrfError::InvalidRangeWidth(outputs.len(), range.width()))
+        } else if outputs.iter().any(|out| out.is_empty()) {
+            Err(RcPrfError::EmptyOutput)
         } else {
             self.unchecked_par_eval_range(range, outputs);
             Ok(())
@@ -115,6 +210,41 @@ pub trait RangePrf: private::UncheckedRangePrf {
             ))
         }
     }
+
+    /// Constrain the PRF on the standard Rust range `range` (e.g. `2..5` or
+    /// `2..=5`), as for `constrain`.
+    /// Returns an error when `range` is unbounded or empty, or in the same
+    /// cases as `constrain`.
+    fn constrain_std_range<R>(
+        &self,
+        range: R,
+    ) -> Result<ConstrainedRcPrf, RcPrfError>
+    where
+        R: RangeBounds<u64>,
+        Self: Sized,
+    {
+        self.constrain(&bounded_range(&range)?)
+    }
+}
+
+/// Converts a standard Rust range to an `RcPrfRange`, rejecting ranges with a
+/// missing bound
+fn bounded_range<R>(range: &R) -> Result<RcPrfRange, RcPrfError>
+where
+    R: RangeBounds<u64>,
+{
+    match (range.start_bound(), range.end_bound()) {
+        (Bound::Unbounded, _) | (_, Bound::Unbounded) => {
+            Err(RcPrfError::UnboundedRange(
+                range.start_bound().cloned(),
+                range.end_bound().cloned(),
+            ))
+        }
+        _ => RcPrfRange::from_bounds((
+            range.start_bound().cloned(),
+            range.end_bound().cloned(),
+        )),
+    }
 }
 
 /// Trait representing a PRF built on a tree structure
@@ -123,4 +253,11 @@ pub trait TreeBasedPrf {
     /// For range constrained PRFs, this stays the same when constraining the
     /// PRF.
     fn tree_height(&self) -> u8;
+
+    /// Returns the number of leaves of the underlying tree of the PRF,
+    /// saturating at `u64::MAX`. For range constrained PRFs, this is the
+    /// number of leaves of the full tree, not of the constrained range.
+    fn leaf_count(&self) -> u64 {
+        max_leaf_index(self.tree_height()).saturating_add(1)
+    }
 }
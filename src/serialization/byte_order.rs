@@ -0,0 +1,47 @@
+/// Byte order used to encode the integers of a serialized object
+///
+/// The default byte order of the crate is little-endian. Big-endian encoding
+/// is provided for interoperability with protocols mandating it (e.g. network
+/// protocols).
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ByteOrder {
+    /// Least significant byte first (default)
+    #[default]
+    LittleEndian,
+    /// Most significant byte first (a.k.a. network byte order)
+    BigEndian,
+}
+
+impl ByteOrder {
+    /// Encode a 16 bits integer
+    pub(crate) fn u16_to_bytes(self, v: u16) -> [u8; 2] {
+        match self {
+            ByteOrder::LittleEndian => v.to_le_bytes(),
+            ByteOrder::BigEndian => v.to_be_bytes(),
+        }
+    }
+
+    /// Decode a 16 bits integer
+    pub(crate) fn u16_from_bytes(self, bytes: [u8; 2]) -> u16 {
+        match self {
+            ByteOrder::LittleEndian => u16::from_le_bytes(bytes),
+            ByteOrder::BigEndian => u16::from_be_bytes(bytes),
+        }
+    }
+
+    /// Encode a 64 bits integer
+    pub(crate) fn u64_to_bytes(self, v: u64) -> [u8; 8] {
+        match self {
+            ByteOrder::LittleEndian => v.to_le_bytes(),
+            ByteOrder::BigEndian => v.to_be_bytes(),
+        }
+    }
+
+    /// Decode a 64 bits integer
+    pub(crate) fn u64_from_bytes(self, bytes: [u8; 8]) -> u64 {
+        match self {
+            ByteOrder::LittleEndian => u64::from_le_bytes(bytes),
+            ByteOrder::BigEndian => u64::from_be_bytes(bytes),
+        }
+    }
+}
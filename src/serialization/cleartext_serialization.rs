@@ -15,6 +15,93 @@ pub trait DeserializableCleartextContent: Sized {
     fn deserialize_content(
         reader: &mut dyn std::io::Read,
     ) -> Result<Self, CleartextContentDeserializationError>;
+
+    /// Deserialize the content of an object serialized with the format
+    /// version `version`. Types whose layout changed across versions override
+    /// this function to read the older layouts; by default, the layout is the
+    /// same for all the supported versions.
+    fn deserialize_content_versioned(
+        reader: &mut dyn std::io::Read,
+        _version: u8,
+    ) -> Result<Self, CleartextContentDeserializationError> {
+        Self::deserialize_content(reader)
+    }
+
+    /// Deserialize the content of an object, and returns it together with
+    /// the number of bytes read from `reader`
+    fn deserialize_content_counted(
+        reader: &mut dyn std::io::Read,
+    ) -> Result<(Self, usize), CleartextContentDeserializationError> {
+        Self::deserialize_content_versioned_counted(
+            reader,
+            SERIALIZATION_FORMAT_VERSION,
+        )
+    }
+
+    /// Deserialize the content of an object serialized with the format
+    /// version `version`, and returns it together with the number of bytes
+    /// read from `reader`
+    fn deserialize_content_versioned_counted(
+        reader: &mut dyn std::io::Read,
+        version: u8,
+    ) -> Result<(Self, usize), CleartextContentDeserializationError> {
+        let mut counting_reader = CountingReader {
+            inner: reader,
+            count: 0,
+        };
+        let object =
+            Self::deserialize_content_versioned(&mut counting_reader, version)?;
+        Ok((object, counting_reader.count))
+    }
+}
+
+/// Reader wrapper counting the bytes read from the underlying reader
+struct CountingReader<'a> {
+    inner: &'a mut dyn std::io::Read,
+    count: usize,
+}
+
+impl std::io::Read for CountingReader<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        self.count += n;
+        Ok(n)
+    }
+}
+
+/// Version of the cleartext serialization format, written right after the tag
+/// of every serialized object
+///
+/// Version 2 appends the nonce to the key of `Prg`s, and surrounds the key of
+/// `AeadCipher`s with their mode and message budget.
+pub const SERIALIZATION_FORMAT_VERSION: u8 = 2;
+
+/// Oldest version of the cleartext serialization format that can still be
+/// deserialized
+pub const MIN_SERIALIZATION_FORMAT_VERSION: u8 = 1;
+
+/// Size of the serialized format version
+pub const FORMAT_VERSION_SIZE: usize = 1;
+
+/// Read the format version from a byte stream, check that it is supported and
+/// return it
+pub(crate) fn read_format_version(
+    reader: &mut dyn std::io::Read,
+) -> Result<u8, CleartextDeserializationError> {
+    let mut version = [0u8; FORMAT_VERSION_SIZE];
+    reader
+        .read_exact(&mut version)
+        .map_err(CleartextContentDeserializationError::from)?;
+
+    if (MIN_SERIALIZATION_FORMAT_VERSION..=SERIALIZATION_FORMAT_VERSION)
+        .contains(&version[0])
+    {
+        Ok(version[0])
+    } else {
+        Err(CleartextDeserializationError::UnsupportedVersion(
+            version[0],
+        ))
+    }
 }
 
 pub trait SerializableCleartext:
@@ -23,15 +110,18 @@ pub trait SerializableCleartext:
     fn cleartext_serialization_length(&self) -> usize {
         self.serialization_content_byte_size()
             + SerializationTag::SERIALIZATION_SIZE
+            + FORMAT_VERSION_SIZE
     }
 
     fn serialize_cleartext(
         &self,
         writer: &mut dyn std::io::Write,
     ) -> Result<usize, std::io::Error> {
-        // serialize the tag first
+        // serialize the tag first, then the format version
         let tag = self.serialization_tag();
         let mut b = tag.serialize_content(writer)?;
+        writer.write_all(&[SERIALIZATION_FORMAT_VERSION])?;
+        b += FORMAT_VERSION_SIZE;
         b += self.serialize_content(writer)?;
 
         Ok(b)
@@ -48,10 +138,26 @@ pub trait DeserializableCleartext:
     fn deserialize_cleartext(
         reader: &mut dyn std::io::Read,
     ) -> Result<Self, CleartextDeserializationError> {
+        Self::deserialize_cleartext_counted(reader).map(|(object, _)| object)
+    }
+
+    /// Deserialize an object along with its tag and format version, and
+    /// returns it together with the number of bytes read from `reader`
+    fn deserialize_cleartext_counted(
+        reader: &mut dyn std::io::Read,
+    ) -> Result<(Self, usize), CleartextDeserializationError> {
         let tag = SerializationTag::read_tag(reader)?;
 
         if tag == Self::serialization_tag() {
-            Ok(Self::deserialize_content(reader)?)
+            let version = read_format_version(reader)?;
+            let (object, content_size) =
+                Self::deserialize_content_versioned_counted(reader, version)?;
+            Ok((
+                object,
+                SerializationTag::SERIALIZATION_SIZE
+                    + FORMAT_VERSION_SIZE
+                    + content_size,
+            ))
         } else {
             Err(CleartextDeserializationError::InvalidTagError(tag))
         }
@@ -45,6 +45,9 @@ pub enum CleartextDeserializationError {
     /// Serialization tag not matching the deserialized object's type
     #[error("Deserialized tag do not match the object type")]
     InvalidTagError(crate::serialization::tags::SerializationTag),
+    /// Unsupported serialization format version
+    #[error("Unsupported serialization format version ({0})")]
+    UnsupportedVersion(u8),
     /// Error during the content's deserialization
     #[error(transparent)]
     ContentDeserializationError(#[from] CleartextContentDeserializationError),
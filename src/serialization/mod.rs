@@ -3,6 +3,9 @@
 /// Errors raised by the module's functions
 pub mod errors;
 
+/// Byte order of the serialized integers
+pub mod byte_order;
+
 /// (De)Serialization in cleartext of the objects
 pub(crate) mod cleartext_serialization;
 /// Tags identifying the different object types
@@ -10,6 +13,7 @@ pub(crate) mod tags;
 
 pub mod wrapper;
 
+pub use byte_order::ByteOrder;
 pub use wrapper::*;
 
 #[cfg(test)]
@@ -1,8 +1,12 @@
 use std::convert::TryFrom;
 
+use super::byte_order::ByteOrder;
 use super::errors::*;
 
-use crate::{rcprf::*, AeadCipher, Cipher, Key, KeyDerivationPrg, Prf, Prg};
+use crate::{
+    rcprf::*, AeadCipher, Cipher, Key, KeyDerivationPrf, KeyDerivationPrg, Prf,
+    Prg, ShortPrf,
+};
 #[cfg(test)]
 use {strum::IntoEnumIterator, strum_macros::EnumIter};
 
@@ -19,6 +23,9 @@ pub enum SerializationTag {
     ConstrainedRcPrfInnerElement,
     Cipher,
     AeadCipher,
+    ShortPrf,
+    XCipher,
+    KeyDerivationPrf,
 }
 
 impl TryFrom<u16> for SerializationTag {
@@ -49,6 +56,15 @@ impl TryFrom<u16> for SerializationTag {
             x if x == SerializationTag::AeadCipher as u16 => {
                 Ok(SerializationTag::AeadCipher)
             }
+            x if x == SerializationTag::ShortPrf as u16 => {
+                Ok(SerializationTag::ShortPrf)
+            }
+            x if x == SerializationTag::XCipher as u16 => {
+                Ok(SerializationTag::XCipher)
+            }
+            x if x == SerializationTag::KeyDerivationPrf as u16 => {
+                Ok(SerializationTag::KeyDerivationPrf)
+            }
             _ => Err(SerializationTagConversionError(v)),
         }
     }
@@ -61,9 +77,18 @@ impl SerializationTag {
     pub(crate) fn serialize_content(
         self,
         writer: &mut dyn std::io::Write,
+    ) -> std::io::Result<usize> {
+        self.serialize_content_with_byte_order(writer, ByteOrder::default())
+    }
+
+    /// Write the tag to an IO stream, using the `byte_order` byte order
+    pub(crate) fn serialize_content_with_byte_order(
+        self,
+        writer: &mut dyn std::io::Write,
+        byte_order: ByteOrder,
     ) -> std::io::Result<usize> {
         let value = self as u16;
-        writer.write_all(&value.to_le_bytes())?;
+        writer.write_all(&byte_order.u16_to_bytes(value))?;
 
         Ok(SerializationTag::SERIALIZATION_SIZE)
     }
@@ -72,11 +97,19 @@ impl SerializationTag {
     #[allow(dead_code)]
     pub(crate) fn read_tag(
         reader: &mut dyn std::io::Read,
+    ) -> Result<SerializationTag, SerializationTagError> {
+        Self::read_tag_with_byte_order(reader, ByteOrder::default())
+    }
+
+    /// Read a tag encoded with the `byte_order` byte order from a byte stream
+    pub(crate) fn read_tag_with_byte_order(
+        reader: &mut dyn std::io::Read,
+        byte_order: ByteOrder,
     ) -> Result<SerializationTag, SerializationTagError> {
         let mut buf = [0u8; 2];
 
         reader.read_exact(&mut buf)?;
-        let v = u16::from_le_bytes(buf);
+        let v = byte_order.u16_from_bytes(buf);
         Ok(SerializationTag::try_from(v)?)
     }
 }
@@ -103,6 +136,12 @@ impl<T: Key> SerializationTaggedType for KeyDerivationPrg<T> {
     }
 }
 
+impl<T: Key> SerializationTaggedType for KeyDerivationPrf<T> {
+    fn serialization_tag() -> SerializationTag {
+        SerializationTag::KeyDerivationPrf
+    }
+}
+
 impl SerializationTaggedType for RcPrf {
     fn serialization_tag() -> SerializationTag {
         SerializationTag::RcPrf
@@ -139,6 +178,19 @@ impl SerializationTaggedType for AeadCipher {
     }
 }
 
+impl SerializationTaggedType for ShortPrf {
+    fn serialization_tag() -> SerializationTag {
+        SerializationTag::ShortPrf
+    }
+}
+
+#[cfg(feature = "xchacha")]
+impl SerializationTaggedType for crate::XCipher {
+    fn serialization_tag() -> SerializationTag {
+        SerializationTag::XCipher
+    }
+}
+
 pub trait SerializationTagged {
     fn serialization_tag(&self) -> SerializationTag;
 }
@@ -152,6 +204,7 @@ impl<T: SerializationTaggedType> SerializationTagged for T {
 #[cfg(test)]
 mod tests {
     #![allow(clippy::unwrap_used)]
+    #![allow(clippy::expect_used)]
 
     use super::*;
 
@@ -1,6 +1,8 @@
 #![allow(clippy::unwrap_used)]
 
 use super::cleartext_serialization::*;
+use super::errors::CleartextDeserializationError;
+use super::tags::SerializationTag;
 use crate::*;
 use std::io::Cursor;
 
@@ -49,20 +51,108 @@ fn prf_wrapping() {
     test_prf_identity(wrap_unwrap);
 }
 
-fn test_prg_identity<F>(fun: F)
+fn test_short_prf_identity<F>(fun: F)
 where
-    F: Fn(&Prg) -> Prg,
+    F: Fn(&ShortPrf) -> ShortPrf,
 {
-    let prg = Prg::new();
-    let deser_prg = fun(&prg);
+    let prf = ShortPrf::new();
+    let deser_prf = fun(&prf);
+
+    let input = 0u64.to_le_bytes();
+
+    assert_eq!(prf.eval_u64(&input), deser_prf.eval_u64(&input));
+}
+
+#[test]
+fn counted_content_deserialization() {
+    let prf = Prf::new();
+    let mut buffer = vec![];
+    prf.serialize_content(&mut buffer).unwrap();
+    // trailing bytes must not be consumed
+    buffer.extend_from_slice(&[0xAA; 7]);
+
+    let mut cursor = Cursor::new(buffer);
+    let (deser_prf, count) =
+        Prf::deserialize_content_counted(&mut cursor).unwrap();
+    assert_eq!(count, prf.serialization_content_byte_size());
+    assert_eq!(cursor.position() as usize, count);
 
     let mut eval_1 = [0u8; 32];
     let mut eval_2 = [0u8; 32];
+    prf.fill_bytes(&[0u8], &mut eval_1);
+    deser_prf.fill_bytes(&[0u8], &mut eval_2);
+    assert_eq!(eval_1, eval_2);
+}
 
-    prg.fill_pseudo_random_bytes(&mut eval_1);
-    deser_prg.fill_pseudo_random_bytes(&mut eval_2);
+#[test]
+fn counted_cleartext_deserialization() {
+    let prf = Prf::new();
+    let mut buffer = vec![];
+    let written_bytes = prf.serialize_cleartext(&mut buffer).unwrap();
+    buffer.extend_from_slice(&[0xAA; 7]);
+
+    let mut cursor = Cursor::new(buffer);
+    let (_, count) = Prf::deserialize_cleartext_counted(&mut cursor).unwrap();
+    assert_eq!(count, written_bytes);
+    assert_eq!(cursor.position() as usize, count);
+}
 
-    assert_eq!(eval_1, eval_2);
+#[test]
+fn wrapping_trailing_bytes() {
+    let key_bytes = [3u8; 32];
+    let wrapper =
+        CryptoWrapper::from_key(Key256::from_bytes(&mut key_bytes.clone()));
+    let cipher =
+        AeadCipher::from_key(Key256::from_bytes(&mut key_bytes.clone()));
+
+    // a valid plaintext followed by a byte that is not part of the object
+    let mut plaintext = vec![];
+    Prf::new().serialize_cleartext(&mut plaintext).unwrap();
+    plaintext.push(0);
+    let mut ciphertext =
+        vec![0u8; plaintext.len() + AeadCipher::CIPHERTEXT_EXPANSION];
+    cipher.encrypt(&plaintext, &mut ciphertext).unwrap();
+
+    match wrapper.unwrap::<Prf>(&ciphertext) {
+        Err(UnwrappingError::DeserializationError(_)) => (),
+        _ => panic!("Expected a deserialization error"),
+    }
+
+    // without the trailing byte, the object is unwrapped
+    plaintext.pop();
+    ciphertext.pop();
+    cipher.encrypt(&plaintext, &mut ciphertext).unwrap();
+    assert!(wrapper.unwrap::<Prf>(&ciphertext).is_ok());
+}
+
+#[test]
+fn short_prf_serialization() {
+    test_short_prf_identity(ser_deser);
+}
+
+#[test]
+fn short_prf_wrapping() {
+    test_short_prf_identity(wrap_unwrap);
+}
+
+fn test_prg_identity<F>(fun: F)
+where
+    F: Fn(&Prg) -> Prg,
+{
+    for prg in [
+        Prg::new(),
+        Prg::from_key_with_nonce(Key256::new(), *b"prg nonce 01"),
+    ] {
+        let deser_prg = fun(&prg);
+
+        let mut eval_1 = [0u8; 32];
+        let mut eval_2 = [0u8; 32];
+
+        prg.fill_pseudo_random_bytes(&mut eval_1);
+        deser_prg.fill_pseudo_random_bytes(&mut eval_2);
+
+        assert_eq!(eval_1, eval_2);
+    }
 }
 
 #[test]
@@ -75,6 +165,54 @@ fn prg_wrapping() {
     test_prg_identity(wrap_unwrap);
 }
 
+#[test]
+fn aead_cipher_version_1_deserialization() {
+    // before version 2, an AEAD cipher was serialized as its key only, and
+    // always used the derived-key mode
+    let key_bytes = [7u8; 32];
+    let mut ser_buffer = vec![];
+    SerializationTag::AeadCipher
+        .serialize_content(&mut ser_buffer)
+        .unwrap();
+    ser_buffer.push(1);
+    ser_buffer.extend_from_slice(&key_bytes);
+
+    let mut cursor = Cursor::new(&ser_buffer);
+    let deser_cipher = AeadCipher::deserialize_cleartext(&mut cursor).unwrap();
+    assert_eq!(cursor.position() as usize, ser_buffer.len());
+    assert_eq!(deser_cipher.mode(), AeadMode::DerivedKey);
+
+    let mut key_copy = key_bytes;
+    let cipher = AeadCipher::from_key(Key256::from_bytes(&mut key_copy));
+    let mut ciphertext = [0u8; 5 + AeadCipher::CIPHERTEXT_EXPANSION];
+    cipher.encrypt(b"Hello", &mut ciphertext).unwrap();
+    assert_eq!(deser_cipher.decrypt_to_vec(&ciphertext).unwrap(), b"Hello");
+}
+
+#[test]
+fn prg_version_1_deserialization() {
+    // before version 2, a PRG was serialized as its key only
+    let key_bytes = [7u8; 32];
+    let mut ser_buffer = vec![];
+    SerializationTag::Prg
+        .serialize_content(&mut ser_buffer)
+        .unwrap();
+    ser_buffer.push(1);
+    ser_buffer.extend_from_slice(&key_bytes);
+
+    let mut cursor = Cursor::new(&ser_buffer);
+    let deser_prg = Prg::deserialize_cleartext(&mut cursor).unwrap();
+    assert_eq!(cursor.position() as usize, ser_buffer.len());
+
+    let mut key_copy = key_bytes;
+    let prg = Prg::from_key(Key256::from_bytes(&mut key_copy));
+    let mut eval_1 = [0u8; 32];
+    let mut eval_2 = [0u8; 32];
+    prg.fill_pseudo_random_bytes(&mut eval_1);
+    deser_prg.fill_pseudo_random_bytes(&mut eval_2);
+    assert_eq!(eval_1, eval_2);
+}
+
 fn test_key_derivation_prg_identity<F>(fun: F)
 where
     F: Fn(&KeyDerivationPrg<Key256>) -> KeyDerivationPrg<Key256>,
@@ -98,6 +236,29 @@ fn key_derivation_prg_wrapping() {
     test_key_derivation_prg_identity(wrap_unwrap);
 }
 
+fn test_key_derivation_prf_identity<F>(fun: F)
+where
+    F: Fn(&KeyDerivationPrf<Key256>) -> KeyDerivationPrf<Key256>,
+{
+    let prf = KeyDerivationPrf::<Key256>::new();
+    let deser_prf = fun(&prf);
+
+    let k1 = prf.derive_key(b"key derivation input");
+    let k2 = deser_prf.derive_key(b"key derivation input");
+
+    assert_eq!(k1.content(), k2.content());
+}
+
+#[test]
+fn key_derivation_prf_serialization() {
+    test_key_derivation_prf_identity(ser_deser);
+}
+
+#[test]
+fn key_derivation_prf_wrapping() {
+    test_key_derivation_prf_identity(wrap_unwrap);
+}
+
 fn test_rcprf_identity<F>(fun: F)
 where
     F: Fn(&RcPrf) -> RcPrf,
@@ -207,20 +368,52 @@ fn cipher_wrapping() {
     test_cipher_identity(wrap_unwrap);
 }
 
-fn test_aead_cipher_identity<F>(fun: F)
+#[cfg(feature = "xchacha")]
+fn test_xcipher_identity<F>(fun: F)
+where
+    F: Fn(&XCipher) -> XCipher,
+{
+    let cipher = XCipher::from_key(Key256::new());
+    let deser_cipher = fun(&cipher);
+
+    let plaintext = TEST_PLAINTEXT;
+    let mut ciphertext =
+        vec![0u8; plaintext.len() + XCipher::CIPHERTEXT_EXPANSION];
+    let mut dec_result = vec![0u8; plaintext.len()];
+
+    cipher.encrypt(plaintext, &mut ciphertext).unwrap();
+    deser_cipher.decrypt(&ciphertext, &mut dec_result).unwrap();
+
+    assert_eq!(plaintext, &dec_result[..]);
+}
+
+#[cfg(feature = "xchacha")]
+#[test]
+fn xcipher_serialization() {
+    test_xcipher_identity(ser_deser);
+}
+
+#[cfg(feature = "xchacha")]
+#[test]
+fn xcipher_wrapping() {
+    test_xcipher_identity(wrap_unwrap);
+}
+
+fn test_aead_cipher_identity<F>(mode: AeadMode, fun: F)
 where
     F: Fn(&AeadCipher) -> AeadCipher,
 {
     let k = Key256::new();
-    let cipher = AeadCipher::from_key(k);
+    let cipher = AeadCipherBuilder::new().mode(mode).build(k);
 
     let deser_cipher = fun(&cipher);
+    assert_eq!(deser_cipher.mode(), mode);
 
     let plaintext = TEST_PLAINTEXT;
     // If the given length overflows, the call to 'encrypt' will return an
     // error
     let mut ciphertext =
-        vec![0u8; plaintext.len() + AeadCipher::CIPHERTEXT_EXPANSION];
+        vec![0u8; plaintext.len() + cipher.ciphertext_expansion()];
     let mut dec_result = vec![0u8; plaintext.len()];
 
     cipher.encrypt(plaintext, &mut ciphertext).unwrap();
@@ -232,10 +425,237 @@ where
 
 #[test]
 fn aead_cipher_serialization() {
-    test_aead_cipher_identity(ser_deser);
+    test_aead_cipher_identity(AeadMode::DerivedKey, ser_deser);
+    test_aead_cipher_identity(AeadMode::Direct, ser_deser);
+}
+
+#[test]
+fn aead_cipher_budget_serialization() {
+    let cipher = AeadCipherBuilder::new()
+        .mode(AeadMode::Direct)
+        .budget(3)
+        .build(Key256::new());
+    let mut ciphertext = vec![0u8; 5 + cipher.ciphertext_expansion()];
+    cipher.encrypt(b"Hello", &mut ciphertext).unwrap();
+
+    // the remaining budget is restored
+    let deser_cipher = ser_deser(&cipher);
+    assert_eq!(deser_cipher.remaining_budget(), Some(2));
+    deser_cipher.encrypt(b"Hello", &mut ciphertext).unwrap();
+    deser_cipher.encrypt(b"Hello", &mut ciphertext).unwrap();
+    assert!(deser_cipher.encrypt(b"Hello", &mut ciphertext).is_err());
+
+    // including an exhausted one
+    let deser_cipher = ser_deser(&deser_cipher);
+    assert_eq!(deser_cipher.remaining_budget(), Some(0));
+    assert_eq!(
+        ser_deser(&AeadCipher::from_key(Key256::new())).remaining_budget(),
+        None
+    );
+
+    // inconsistent budgets are rejected
+    let mut ser_buffer = vec![];
+    cipher.serialize_cleartext(&mut ser_buffer).unwrap();
+    let l = ser_buffer.len();
+    ser_buffer[l - 8..].copy_from_slice(&4u64.to_le_bytes());
+    assert!(
+        AeadCipher::deserialize_cleartext(&mut Cursor::new(&ser_buffer))
+            .is_err()
+    );
 }
 
 #[test]
 fn aead_cipher_wrapping() {
     test_cipher_identity(wrap_unwrap);
+    test_aead_cipher_identity(AeadMode::DerivedKey, wrap_unwrap);
+    test_aead_cipher_identity(AeadMode::Direct, wrap_unwrap);
+}
+
+#[test]
+fn aead_cipher_invalid_mode() {
+    let cipher = AeadCipher::from_key(Key256::new());
+    let mut ser_buffer = vec![];
+    cipher.serialize_cleartext(&mut ser_buffer).unwrap();
+
+    // the mode byte follows the tag and the format version
+    ser_buffer[SerializationTag::SERIALIZATION_SIZE + FORMAT_VERSION_SIZE] =
+        0xFF;
+    let mut cursor = Cursor::new(ser_buffer);
+    assert!(AeadCipher::deserialize_cleartext(&mut cursor).is_err());
+}
+
+#[test]
+fn unsupported_format_version() {
+    let prf = Prf::new();
+    let mut ser_buffer = vec![];
+    prf.serialize_cleartext(&mut ser_buffer).unwrap();
+    assert_eq!(
+        ser_buffer[SerializationTag::SERIALIZATION_SIZE],
+        SERIALIZATION_FORMAT_VERSION
+    );
+
+    ser_buffer[SerializationTag::SERIALIZATION_SIZE] += 1;
+    match Prf::deserialize_cleartext(&mut Cursor::new(&ser_buffer)) {
+        Err(CleartextDeserializationError::UnsupportedVersion(v)) => {
+            assert_eq!(v, SERIALIZATION_FORMAT_VERSION + 1);
+        }
+        _ => panic!("Expected an unsupported version error"),
+    }
+
+    // the version of nested objects is checked too
+    let constrained_rcprf = RcPrf::new(6)
+        .unwrap()
+        .constrain(&RcPrfRange::new(3, 4))
+        .unwrap();
+    let mut ser_buffer = vec![];
+    constrained_rcprf
+        .serialize_cleartext(&mut ser_buffer)
+        .unwrap();
+    // skip the tag and version of the constrained RcPrf, the number of
+    // elements, and the tag of the first element
+    let first_elt_version_index = 2 * SerializationTag::SERIALIZATION_SIZE
+        + FORMAT_VERSION_SIZE
+        + std::mem::size_of::<u64>();
+    assert_eq!(
+        ser_buffer[first_elt_version_index],
+        SERIALIZATION_FORMAT_VERSION
+    );
+    ser_buffer[first_elt_version_index] += 1;
+    assert!(ConstrainedRcPrf::deserialize_cleartext(&mut Cursor::new(
+        &ser_buffer
+    ))
+    .is_err());
+}
+
+#[test]
+fn wrapping_type_mismatch() {
+    let wrapper = CryptoWrapper::from_key(Key256::new());
+    let bytes = wrapper.wrap(&Prf::new()).unwrap();
+
+    match wrapper.unwrap::<Cipher>(&bytes) {
+        Err(UnwrappingError::TypeMismatch { expected, found }) => {
+            assert_eq!(expected, SerializationTag::Cipher);
+            assert_eq!(found, SerializationTag::Prf);
+        }
+        _ => panic!("Expected a type mismatch"),
+    }
+}
+
+#[test]
+fn wrapping_with_ad() {
+    test_prf_identity(|prf| {
+        let wrapper = CryptoWrapper::from_key(Key256::new());
+        let bytes = wrapper.wrap_with_ad(prf, b"key namespace").unwrap();
+        wrapper.unwrap_with_ad(&bytes, b"key namespace").unwrap()
+    });
+
+    let wrapper = CryptoWrapper::from_key(Key256::new());
+    let bytes = wrapper
+        .wrap_with_ad(&KeyDerivationPrg::<Key256>::new(), b"namespace 1")
+        .unwrap();
+
+    for ad in [&b"namespace 2"[..], b""] {
+        match wrapper.unwrap_with_ad::<KeyDerivationPrg<Key256>>(&bytes, ad) {
+            Err(UnwrappingError::DecryptionError(_)) => (),
+            _ => panic!("Expected a decryption error"),
+        }
+    }
+    assert!(wrapper.unwrap::<KeyDerivationPrg<Key256>>(&bytes).is_err());
+
+    // wrapping without associated data is wrapping with empty associated data
+    let bytes = wrapper.wrap(&Prf::new()).unwrap();
+    assert!(wrapper.unwrap_with_ad::<Prf>(&bytes, b"").is_ok());
+    assert!(wrapper
+        .unwrap_with_ad::<Prf>(&bytes, b"namespace 1")
+        .is_err());
+}
+
+#[cfg(feature = "hex")]
+#[test]
+fn prf_hex_wrapping() {
+    test_prf_identity(|prf| {
+        let wrapper = CryptoWrapper::from_key(Key256::new());
+        let hex_string = wrapper.wrap_hex(prf).unwrap();
+        assert!(hex_string.chars().all(|c| c.is_ascii_hexdigit()));
+        wrapper.unwrap_hex(&hex_string).unwrap()
+    });
+}
+
+#[cfg(feature = "hex")]
+#[test]
+fn invalid_hex_unwrapping() {
+    let wrapper = CryptoWrapper::from_key(Key256::new());
+    let hex_string = wrapper.wrap_hex(&Prf::new()).unwrap();
+
+    for invalid in [
+        format!("{}z", &hex_string[1..]),
+        hex_string[1..].to_string(),
+    ] {
+        match wrapper.unwrap_hex::<Prf>(&invalid) {
+            Err(UnwrappingError::InvalidHex(_)) => (),
+            _ => panic!("Expected an invalid hex error"),
+        }
+    }
+}
+
+#[test]
+fn versioned_wrapping() {
+    test_prf_identity(|prf| {
+        let wrapper = VersionedWrapper::from_key(Key256::new(), 42, 1);
+        let bytes = wrapper.wrap(prf).unwrap();
+        wrapper.unwrap(&bytes).unwrap()
+    });
+}
+
+#[test]
+fn versioned_wrapping_mismatch() {
+    let prf = Prf::new();
+    let mut key_buf = [7u8; 32];
+    let mut key_buf_copy = key_buf;
+    let mut key_buf_copy_2 = key_buf;
+
+    let wrapper =
+        VersionedWrapper::from_key(Key256::from_bytes(&mut key_buf), 42, 1);
+    let bytes = wrapper.wrap(&prf).unwrap();
+
+    // same key but different key id
+    let other_id = VersionedWrapper::from_key(
+        Key256::from_bytes(&mut key_buf_copy),
+        43,
+        1,
+    );
+    match other_id.unwrap::<Prf>(&bytes) {
+        Err(UnwrappingError::KeyIdMismatch { expected, found }) => {
+            assert_eq!(expected, 43);
+            assert_eq!(found, 42);
+        }
+        _ => panic!("Expected a key id mismatch"),
+    }
+
+    let other_version = VersionedWrapper::from_key(Key256::new(), 42, 2);
+    match other_version.unwrap::<Prf>(&bytes) {
+        Err(UnwrappingError::VersionMismatch { expected, found }) => {
+            assert_eq!(expected, 2);
+            assert_eq!(found, 1);
+        }
+        _ => panic!("Expected a version mismatch"),
+    }
+
+    match wrapper.unwrap::<Prf>(&bytes[..VersionedWrapper::HEADER_SIZE - 1]) {
+        Err(UnwrappingError::MissingHeader) => (),
+        _ => panic!("Expected a missing header error"),
+    }
+
+    // tampering with the header is detected by the authentication
+    let mut tampered = bytes;
+    tampered[2] ^= 1;
+    let tampered_wrapper = VersionedWrapper::from_key(
+        Key256::from_bytes(&mut key_buf_copy_2),
+        43,
+        1,
+    );
+    match tampered_wrapper.unwrap::<Prf>(&tampered) {
+        Err(UnwrappingError::DecryptionError(_)) => (),
+        _ => panic!("Expected a decryption error"),
+    }
 }
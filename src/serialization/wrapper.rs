@@ -2,6 +2,10 @@
 
 use crate::errors::UnwrappingError;
 use crate::serialization::cleartext_serialization::*;
+use crate::serialization::errors::{
+    CleartextContentDeserializationError, CleartextDeserializationError,
+};
+use crate::tags::SerializationTaggedType;
 use crate::{AeadCipher, Key256, WrappingError};
 use std::{io::Cursor, ops::DerefMut};
 
@@ -17,6 +21,40 @@ pub struct CryptoWrapper {
 pub trait Wrappable: SerializableCleartext + DeserializableCleartext {}
 impl<T> Wrappable for T where T: SerializableCleartext + DeserializableCleartext {}
 
+/// Deserialize an object of type `T` from decrypted bytes, reporting both the
+/// expected and the found serialization tags if the bytes do not represent an
+/// object of type `T`.
+/// The bytes must contain exactly one object: trailing bytes are rejected.
+fn deserialize_unwrapped<T: Wrappable>(
+    bytes: &[u8],
+) -> Result<T, UnwrappingError> {
+    let mut cursor = Cursor::new(bytes);
+
+    let (object, read_bytes) = T::deserialize_cleartext_counted(&mut cursor)
+        .map_err(|e| match e {
+            CleartextDeserializationError::InvalidTagError(found) => {
+                UnwrappingError::TypeMismatch {
+                    expected: <T as SerializationTaggedType>::serialization_tag(
+                    ),
+                    found,
+                }
+            }
+            e => UnwrappingError::from(e),
+        })?;
+
+    if read_bytes == bytes.len() {
+        Ok(object)
+    } else {
+        Err(CleartextDeserializationError::ContentDeserializationError(
+            CleartextContentDeserializationError::ContentError(format!(
+                "{} trailing bytes after the unwrapped object",
+                bytes.len() - read_bytes
+            )),
+        )
+        .into())
+    }
+}
+
 impl CryptoWrapper {
     /// Initialize a new wrapper
     #[must_use]
@@ -31,6 +69,18 @@ impl CryptoWrapper {
     pub fn wrap<T: Wrappable>(
         &self,
         object: &T,
+    ) -> Result<Vec<u8>, WrappingError> {
+        self.wrap_with_ad(object, b"")
+    }
+
+    /// Wrap an object to a ciphertext bound to the associated data `ad`:
+    /// serialize the object and encrypt the resulting bytes, authenticating
+    /// `ad`. The same associated data must be given to `unwrap_with_ad` to
+    /// unwrap the object. The associated data is not part of the ciphertext.
+    pub fn wrap_with_ad<T: Wrappable>(
+        &self,
+        object: &T,
+        ad: &[u8],
     ) -> Result<Vec<u8>, WrappingError> {
         // try to avoid reallocations by constructing a vector with the right
         // length from the beginning
@@ -46,7 +96,8 @@ impl CryptoWrapper {
         // error
         let mut ct = vec![0u8; plain_length + AeadCipher::CIPHERTEXT_EXPANSION];
 
-        self.cipher.encrypt(&buf, &mut ct)?;
+        let written_bytes = self.cipher.encrypt_with_ad(&buf, ad, &mut ct)?;
+        debug_assert_eq!(written_bytes, ct.len());
 
         Ok(ct)
     }
@@ -56,10 +107,157 @@ impl CryptoWrapper {
         &self,
         bytes: &[u8],
     ) -> Result<T, UnwrappingError> {
-        let buf = Zeroizing::new(self.cipher.decrypt_to_vec(bytes)?);
+        self.unwrap_with_ad(bytes, b"")
+    }
+
+    /// Unwrap an object from a sequence of bytes produced by `wrap_with_ad`
+    /// with the associated data `ad`. Returns a decryption error if the
+    /// associated data does not match.
+    pub fn unwrap_with_ad<T: Wrappable>(
+        &self,
+        bytes: &[u8],
+        ad: &[u8],
+    ) -> Result<T, UnwrappingError> {
+        let buf =
+            Zeroizing::new(self.cipher.decrypt_to_vec_with_ad(bytes, ad)?);
 
-        let mut cursor = Cursor::new(&*buf);
+        deserialize_unwrapped(&buf)
+    }
+
+    /// Wrap an object and encode the resulting ciphertext as a lowercase
+    /// hexadecimal string
+    ///
+    /// # Example
+    /// ```
+    /// # extern crate crypto_tk_rs;
+    /// use crypto_tk_rs::{CryptoWrapper, Key, Key256, Prf};
+    ///
+    /// let wrapper = CryptoWrapper::from_key(Key256::new());
+    /// let hex_string = wrapper.wrap_hex(&Prf::new()).unwrap();
+    /// let prf: Prf = wrapper.unwrap_hex(&hex_string).unwrap();
+    /// ```
+    #[cfg(feature = "hex")]
+    pub fn wrap_hex<T: Wrappable>(
+        &self,
+        object: &T,
+    ) -> Result<String, WrappingError> {
+        Ok(hex::encode(self.wrap(object)?))
+    }
+
+    /// Unwrap an object from a hexadecimal string produced by `wrap_hex`.
+    /// Returns an `InvalidHex` error if `s` is not a valid hexadecimal
+    /// string.
+    #[cfg(feature = "hex")]
+    pub fn unwrap_hex<T: Wrappable>(
+        &self,
+        s: &str,
+    ) -> Result<T, UnwrappingError> {
+        self.unwrap(&hex::decode(s)?)
+    }
+}
+
+/// A wrapper tagging every wrapped object with a key identifier and a format
+/// version.
+///
+/// The wrapped blobs are made of a cleartext header (the format version as a
+/// 2 bytes little endian integer, followed by the key identifier as a 8 bytes
+/// little endian integer) and of the output of `CryptoWrapper::wrap_with_ad`
+/// on the object, with the header as associated data, so it cannot be
+/// tampered with. When unwrapping, the header is checked before any
+/// decryption is attempted, which allows to cheaply reject blobs that were
+/// wrapped with another key or format version.
+pub struct VersionedWrapper {
+    /// The underlying wrapper used to encrypt the objects
+    wrapper: CryptoWrapper,
+    /// Identifier of the wrapping key
+    key_id: u64,
+    /// Version of the wrapping format
+    version: u16,
+}
+
+impl VersionedWrapper {
+    /// Size of the cleartext header prepended to wrapped objects
+    pub const HEADER_SIZE: usize = 2 + 8;
+
+    /// Initialize a new wrapper with the given key identifier and format
+    /// version
+    #[must_use]
+    pub fn from_key(key: Key256, key_id: u64, version: u16) -> Self {
+        VersionedWrapper {
+            wrapper: CryptoWrapper::from_key(key),
+            key_id,
+            version,
+        }
+    }
+
+    /// Identifier of the wrapping key
+    #[must_use]
+    pub fn key_id(&self) -> u64 {
+        self.key_id
+    }
+
+    /// Version of the wrapping format
+    #[must_use]
+    pub fn version(&self) -> u16 {
+        self.version
+    }
+
+    fn header(&self) -> [u8; Self::HEADER_SIZE] {
+        let mut header = [0u8; Self::HEADER_SIZE];
+        header[..2].copy_from_slice(&self.version.to_le_bytes());
+        header[2..].copy_from_slice(&self.key_id.to_le_bytes());
+        header
+    }
+
+    /// Wrap an object to a ciphertext: serialize the object, encrypt the
+    /// resulting bytes and prepend the header
+    pub fn wrap<T: Wrappable>(
+        &self,
+        object: &T,
+    ) -> Result<Vec<u8>, WrappingError> {
+        let header = self.header();
+        let ct = self.wrapper.wrap_with_ad(object, &header)?;
+
+        let mut out = Vec::with_capacity(Self::HEADER_SIZE + ct.len());
+        out.extend_from_slice(&header);
+        out.extend_from_slice(&ct);
+
+        Ok(out)
+    }
+
+    /// Unwrap an object from a sequence of bytes.
+    /// Returns an error without attempting decryption if the key identifier
+    /// or the format version of the blob do not match the wrapper's.
+    pub fn unwrap<T: Wrappable>(
+        &self,
+        bytes: &[u8],
+    ) -> Result<T, UnwrappingError> {
+        if bytes.len() < Self::HEADER_SIZE {
+            return Err(UnwrappingError::MissingHeader);
+        }
+        let (header, ct) = bytes.split_at(Self::HEADER_SIZE);
+
+        let mut version_bytes = [0u8; 2];
+        version_bytes.copy_from_slice(&header[..2]);
+        let found_version = u16::from_le_bytes(version_bytes);
+
+        let mut key_id_bytes = [0u8; 8];
+        key_id_bytes.copy_from_slice(&header[2..]);
+        let found_key_id = u64::from_le_bytes(key_id_bytes);
+
+        if found_version != self.version {
+            return Err(UnwrappingError::VersionMismatch {
+                expected: self.version,
+                found: found_version,
+            });
+        }
+        if found_key_id != self.key_id {
+            return Err(UnwrappingError::KeyIdMismatch {
+                expected: self.key_id,
+                found: found_key_id,
+            });
+        }
 
-        Ok(T::deserialize_cleartext(&mut cursor)?)
+        self.wrapper.unwrap_with_ad(ct, header)
     }
 }
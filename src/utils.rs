@@ -42,3 +42,49 @@ where
     let one_2: T = std::convert::From::from(1u8);
     one_1 + ((x - one_2) / y)
 }
+
+/// Compute ceil(log2(n)), i.e. the smallest `k` such that `2^k >= n`.
+/// `ceil_log2(0)` and `ceil_log2(1)` are both 0.
+///
+/// # Example
+///
+/// ```
+/// # extern crate crypto_tk_rs;
+/// use crypto_tk_rs::ceil_log2;
+///
+/// assert_eq!(ceil_log2(1), 0);
+/// assert_eq!(ceil_log2(8), 3);
+/// assert_eq!(ceil_log2(9), 4);
+/// assert_eq!(ceil_log2(1000), 10);
+/// ```
+#[must_use]
+pub fn ceil_log2(n: u64) -> u32 {
+    if n <= 1 {
+        return 0;
+    }
+    u64::BITS - (n - 1).leading_zeros()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ceil_log2_boundaries() {
+        assert_eq!(ceil_log2(0), 0);
+        assert_eq!(ceil_log2(1), 0);
+        assert_eq!(ceil_log2(2), 1);
+        assert_eq!(ceil_log2(3), 2);
+
+        for k in 2..64 {
+            let p = 1u64 << k;
+            assert_eq!(ceil_log2(p - 1), k);
+            assert_eq!(ceil_log2(p), k);
+            assert_eq!(ceil_log2(p + 1), k + 1);
+        }
+
+        assert_eq!(ceil_log2(1u64 << 63), 63);
+        assert_eq!(ceil_log2((1u64 << 63) + 1), 64);
+        assert_eq!(ceil_log2(u64::MAX), 64);
+    }
+}
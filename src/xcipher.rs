@@ -0,0 +1,244 @@
+//! Encryption with extended nonces
+
+use chacha20::cipher::{KeyIvInit, StreamCipher};
+use chacha20::{XChaCha20, XNonce};
+
+use rand::RngCore;
+use zeroize::Zeroize;
+
+use crate::insecure_clone::{private::InsecureClone, CryptographyClone};
+use crate::serialization::cleartext_serialization::{
+    DeserializableCleartextContent, SerializableCleartextContent,
+};
+use crate::serialization::errors::CleartextContentDeserializationError;
+
+use crate::{DecryptionError, EncryptionError};
+use crate::{Key256, KeyAccessor};
+
+/// Encryption & decryption (unauthenticated), with extended nonces
+///
+/// `XCipher` implements encryption (and decryption), using the `XChacha20`
+/// stream cipher. As for `Cipher`, you probably want to use authenticated
+/// encryption for increased security.
+///
+/// The nonce is randomly generated and written at the beginning of the
+/// ciphertext. Unlike Chacha20, `XChacha20` uses 192 bits nonces, which are
+/// large enough to be safely chosen at random for a very large number of
+/// messages. Hence, unlike `Cipher`, `XCipher` directly encrypts with the main
+/// key, without deriving a per-message key.
+///
+/// # Example
+/// ```
+/// # extern crate crypto_tk_rs;
+/// use crypto_tk_rs::{Key, Key256, XCipher};
+///
+/// let cipher = XCipher::from_key(Key256::new());
+/// let plaintext = b"Test plaintext";
+///
+/// let mut ciphertext = vec![0u8; plaintext.len() + XCipher::CIPHERTEXT_EXPANSION];
+/// cipher.encrypt(plaintext, &mut ciphertext).unwrap();
+///
+/// let mut dec_result = vec![0u8; plaintext.len()];
+/// cipher.decrypt(&ciphertext, &mut dec_result).unwrap();
+/// assert_eq!(&dec_result[..], plaintext);
+/// ```
+#[derive(Zeroize)]
+#[zeroize(drop)]
+pub struct XCipher {
+    key: Key256,
+}
+
+impl InsecureClone for XCipher {
+    fn insecure_clone(&self) -> Self {
+        XCipher {
+            key: self.key.insecure_clone(),
+        }
+    }
+}
+
+impl CryptographyClone for XCipher {}
+
+impl XCipher {
+    /// Size of a nonce, in bytes
+    pub const NONCE_SIZE: usize = 24;
+
+    /// The ciphertext expansion, i.e. the number of additional bytes due to the
+    /// encryption
+    pub const CIPHERTEXT_EXPANSION: usize = XCipher::NONCE_SIZE;
+
+    /// Construct a cipher from a 256 bits key
+    #[must_use]
+    pub fn from_key(key: Key256) -> XCipher {
+        XCipher { key }
+    }
+
+    /// Encrypt a byte slice and write the result of the encryption in
+    /// `ciphertext`. Returns the number of bytes written to `ciphertext`, or
+    /// an error if the `ciphertext` slice cannot contain the result, i.e. if
+    /// it is not at least `CIPHERTEXT_EXPANSION` bytes longer than
+    /// `plaintext`.
+    pub fn encrypt(
+        &self,
+        plaintext: &[u8],
+        ciphertext: &mut [u8],
+    ) -> Result<usize, EncryptionError> {
+        if ciphertext
+            .len()
+            .saturating_sub(XCipher::CIPHERTEXT_EXPANSION)
+            < plaintext.len()
+        {
+            return Err(EncryptionError::CiphertextLengthError {
+                plaintext_length: plaintext.len(),
+                ciphertext_length: ciphertext.len(),
+            });
+        }
+
+        let (nonce, body) = ciphertext.split_at_mut(XCipher::NONCE_SIZE);
+        let body = &mut body[..plaintext.len()];
+
+        rand::thread_rng().fill_bytes(nonce);
+        body.copy_from_slice(plaintext);
+        self.apply_keystream(nonce, body);
+
+        Ok(plaintext.len() + XCipher::CIPHERTEXT_EXPANSION)
+    }
+
+    /// Decrypt a byte slice and write the result of the decryption in
+    /// `plaintext`. Returns an error if the `plaintext` slice cannot
+    /// contain the result, i.e. if it is not at least `CIPHERTEXT_EXPANSION`
+    /// bytes smaller than `ciphertext`. Also returns an error if
+    /// `ciphertext`'s length is smaller than `CIPHERTEXT_EXPANSION` bytes
+    pub fn decrypt(
+        &self,
+        ciphertext: &[u8],
+        plaintext: &mut [u8],
+    ) -> Result<(), DecryptionError> {
+        let l = ciphertext.len();
+        if l < XCipher::CIPHERTEXT_EXPANSION {
+            return Err(DecryptionError::CiphertextLengthError {
+                ciphertext_length: l,
+                ciphertext_expansion: XCipher::CIPHERTEXT_EXPANSION,
+            });
+        }
+
+        if l > plaintext.len() + XCipher::CIPHERTEXT_EXPANSION {
+            return Err(DecryptionError::PlaintextLengthError {
+                plaintext_length: plaintext.len(),
+                ciphertext_length: l,
+            });
+        }
+
+        // The first test prevents an underflow
+        let real_plaintext_length = l - XCipher::CIPHERTEXT_EXPANSION;
+        let (nonce, body) = ciphertext.split_at(XCipher::NONCE_SIZE);
+
+        plaintext[..real_plaintext_length].copy_from_slice(body);
+        self.apply_keystream(nonce, &mut plaintext[..real_plaintext_length]);
+
+        Ok(())
+    }
+
+    /// XOR `buffer` with the `XChacha20` keystream for `nonce`
+    fn apply_keystream(&self, nonce: &[u8], buffer: &mut [u8]) {
+        let key = chacha20::Key::from_slice(self.key.content());
+        XChaCha20::new(key, XNonce::from_slice(nonce)).apply_keystream(buffer);
+    }
+}
+
+impl SerializableCleartextContent for XCipher {
+    fn serialization_content_byte_size(&self) -> usize {
+        self.key.serialization_content_byte_size()
+    }
+    fn serialize_content(
+        &self,
+        writer: &mut dyn std::io::Write,
+    ) -> Result<usize, std::io::Error> {
+        self.key.serialize_content(writer)
+    }
+}
+
+impl DeserializableCleartextContent for XCipher {
+    fn deserialize_content(
+        reader: &mut dyn std::io::Read,
+    ) -> Result<Self, CleartextContentDeserializationError> {
+        Ok(XCipher::from_key(Key256::deserialize_content(reader)?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used)]
+    #![allow(clippy::expect_used)]
+    use crate::Key;
+
+    use super::*;
+    use std::collections::HashSet;
+
+    const TEST_PLAINTEXT: &[u8] = b"Test plaintext";
+
+    #[test]
+    fn encryption_correctness() {
+        let plaintext = TEST_PLAINTEXT;
+        let mut ciphertext =
+            vec![0u8; plaintext.len() + XCipher::CIPHERTEXT_EXPANSION];
+        let mut dec_result = vec![0u8; plaintext.len()];
+
+        let cipher = XCipher::from_key(Key256::new());
+        let written_bytes = cipher.encrypt(plaintext, &mut ciphertext).unwrap();
+        assert_eq!(written_bytes, ciphertext.len());
+        assert_ne!(plaintext, &ciphertext[XCipher::NONCE_SIZE..]);
+
+        cipher.decrypt(&ciphertext, &mut dec_result).unwrap();
+        assert_eq!(plaintext, &dec_result[..]);
+    }
+
+    #[test]
+    fn nonce_uniqueness() {
+        let plaintext = TEST_PLAINTEXT;
+        let cipher = XCipher::from_key(Key256::new());
+
+        let mut nonces = HashSet::new();
+        let mut bodies = HashSet::new();
+        for _ in 0..1000 {
+            let mut ciphertext =
+                vec![0u8; plaintext.len() + XCipher::CIPHERTEXT_EXPANSION];
+            cipher.encrypt(plaintext, &mut ciphertext).unwrap();
+
+            let (nonce, body) = ciphertext.split_at(XCipher::NONCE_SIZE);
+            assert!(nonces.insert(nonce.to_vec()));
+            assert!(bodies.insert(body.to_vec()));
+        }
+    }
+
+    #[test]
+    fn encryption_errors() {
+        let cipher = XCipher::from_key(Key256::new());
+        cipher
+            .encrypt(TEST_PLAINTEXT, &mut [0u8; 30])
+            .expect_err("Expected invalid ciphertext length error");
+    }
+
+    #[test]
+    fn decryption_errors() {
+        let plaintext = TEST_PLAINTEXT;
+        let mut ciphertext =
+            vec![0u8; plaintext.len() + XCipher::CIPHERTEXT_EXPANSION];
+        let mut dec_result = vec![0u8; plaintext.len() - 1];
+
+        let cipher = XCipher::from_key(Key256::new());
+        cipher.encrypt(plaintext, &mut ciphertext).unwrap();
+
+        match cipher
+            .decrypt(&ciphertext[0..10], &mut dec_result)
+            .unwrap_err()
+        {
+            DecryptionError::CiphertextLengthError { .. } => (),
+            _ => panic!("Invalid Error"),
+        }
+
+        match cipher.decrypt(&ciphertext, &mut dec_result).unwrap_err() {
+            DecryptionError::PlaintextLengthError { .. } => (),
+            _ => panic!("Invalid Error"),
+        }
+    }
+}